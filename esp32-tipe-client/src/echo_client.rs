@@ -106,6 +106,8 @@ where
                         if let Some(nonce) = transmission_nonce {
                             println!("Sending message with nonce: {}", nonce);
                         }
+                        // The queue might have needed more than one frame.
+                        should_transmit = self.device.pending_frames() > 0;
                         self.device.start_reception()?;
                     }
                 }
@@ -119,10 +121,19 @@ where
                 } else {
                     print!(".");
                 }
+                // Beacon acknowledgments are queued by the radio itself.
+                if self.device.pending_frames() > 0 {
+                    should_transmit = true;
+                }
                 if self.device.is_beacon_needed() {
                     println!("Transmitting beacons (ATPC Update needed)...");
                     self.device.transmit_beacon()?;
                     self.device.start_reception()?;
+                } else if self.device.is_probe_needed() {
+                    if let Some(peer) = self.device.transmit_probe()? {
+                        println!("Probing peer {} (ATPC initialization)...", peer);
+                    }
+                    self.device.start_reception()?;
                 }
             }
         }
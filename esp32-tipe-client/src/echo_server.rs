@@ -126,16 +126,26 @@ where
                         c = 0;
                         if should_transmit {
                             self.try_transmit()?;
-                            should_transmit = false;
+                            // The queue might have needed more than one frame.
+                            should_transmit = self.device.pending_frames() > 0;
                             self.device.start_reception()?;
                         }
                         println!();
                     }
                 }
+                // Beacon acknowledgments are queued by the radio itself.
+                if self.device.pending_frames() > 0 {
+                    should_transmit = true;
+                }
                 if self.device.is_beacon_needed() {
                     println!("Transmitting beacons (ATPC Update needed)...");
                     self.device.transmit_beacon()?;
                     self.device.start_reception()?;
+                } else if self.device.is_probe_needed() {
+                    if let Some(peer) = self.device.transmit_probe()? {
+                        println!("Probing peer {} (ATPC initialization)...", peer);
+                    }
+                    self.device.start_reception()?;
                 }
             }
         }
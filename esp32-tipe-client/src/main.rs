@@ -88,8 +88,21 @@ fn main() -> Result<()> {
                 sf: SpreadingFactor::Sf9,
                 ..Default::default()
             });
+            // Faster variants, selected for the neighbors with a good link.
+            let data_rates = [(SpreadingFactor::Sf8, -10.0), (SpreadingFactor::Sf7, -7.5)]
+                .into_iter()
+                .map(|(sf, min_snr)| radio_tipe_poc::radio::DataRate {
+                    radio_channel: Channel::LoRa(LoRaChannel {
+                        freq: freq.into(),
+                        sf,
+                        ..Default::default()
+                    }),
+                    min_snr,
+                })
+                .collect();
             radio_tipe_poc::radio::Channel {
                 radio_channel,
+                data_rates,
                 delay: delay_params.clone(),
             }
         })
@@ -0,0 +1,202 @@
+//! Simulated-link benchmark of the ATPC implementations.
+//!
+//! Each ATPC transmits frames to a single neighbor over a simulated link, whose path loss
+//! slowly drifts (shadowing), suddenly drops (fading events) and is noisy. The benchmark
+//! reports, for each ATPC, the delivery ratio and the energy spent by the radio (beacons included).
+//!
+//! Run it with `cargo bench --bench atpc`.
+use radio_tipe_poc::atpc::{DefaultATPC, KalmanATPC, TestingATPC, ATPC};
+use radio_tipe_poc::LoRaAddress;
+
+use std::time::Duration;
+
+/// Transmission powers of the simulated radio (dBm).
+const TRANSMISSION_POWERS: [i8; 6] = [2, 5, 8, 11, 14, 17];
+/// Sensitivity of the simulated receiver (dBm).
+const SENSITIVITY: f32 = -120.0;
+/// RSSI target of the simulated peers (dBm).
+const RSSI_TARGET: i16 = -100;
+/// Minimal RSSI considered acceptable by the ATPCs (dBm).
+const LOWER_RSSI: i16 = -115;
+/// Time on air of a frame (s).
+const TIME_ON_AIR: f32 = 0.4;
+/// Number of frames transmitted by scenario.
+const TRANSMISSIONS: u64 = 5000;
+/// Address of the simulated neighbor.
+const NEIGHBOR: LoRaAddress = 0x0042;
+
+/// Small deterministic pseudo-random generator (xorshift64*).
+struct Rng(u64);
+
+impl Rng {
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        ((self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32) / ((1u64 << 24) as f32)
+    }
+
+    /// Standard normal sample (Box-Muller).
+    fn gaussian(&mut self) -> f32 {
+        let u1 = self.next_f32().max(f32::EPSILON);
+        let u2 = self.next_f32();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+    }
+}
+
+/// Simulated radio link.
+struct Link {
+    rng: Rng,
+    /// Median path loss (dB).
+    median: f32,
+    /// Current shadowing (dB).
+    shadowing: f32,
+    /// Remaining transmissions of the current fading event.
+    fading: u32,
+    /// Standard deviation of the RSSI noise (dB).
+    noise: f32,
+}
+
+impl Link {
+    fn new(seed: u64, median: f32, noise: f32) -> Self {
+        Self {
+            rng: Rng(seed),
+            median,
+            shadowing: 0.0,
+            fading: 0,
+            noise,
+        }
+    }
+
+    /// Advances the link state of one transmission.
+    fn step(&mut self) {
+        self.shadowing = 0.98 * self.shadowing + 0.5 * self.rng.gaussian();
+        if self.fading > 0 {
+            self.fading -= 1;
+        } else if self.rng.next_f32() < 0.005 {
+            self.fading = 50;
+        }
+    }
+
+    /// Gets the RSSI of a transmission, `None` if it is below the sensitivity.
+    fn receive(&mut self, tx_power: i8) -> Option<i16> {
+        let fading = if self.fading > 0 { 10.0 } else { 0.0 };
+        let rssi = (tx_power as f32) - self.median - self.shadowing - fading
+            + self.noise * self.rng.gaussian();
+        (rssi >= SENSITIVITY).then(|| rssi.round() as i16)
+    }
+}
+
+/// Results of a scenario.
+#[derive(Default)]
+struct Report {
+    delivered: u64,
+    beacons: u64,
+    energy: f32,
+}
+
+/// Energy (J) consumed to transmit a frame at a transmission power.
+///
+/// Rough model of a SX1276 with a PA_BOOST output: 3.3V and a current growing with the power.
+fn energy(tx_power: i8) -> f32 {
+    let current = 0.020 + 0.0045 * (tx_power as f32).max(0.0) + 0.0002 * (tx_power as f32).powi(2);
+    3.3 * current * TIME_ON_AIR
+}
+
+/// Runs a scenario: transmits [TRANSMISSIONS] frames to [NEIGHBOR] over the link.
+fn run<A: ATPC>(mut atpc: A, mut link: Link) -> Report {
+    let mut report = Report::default();
+    let mut nonce = 0;
+    atpc.register_neighbor(NEIGHBOR);
+    for _ in 0..TRANSMISSIONS {
+        link.step();
+        // Sweeps and probes (addressed to the single neighbor) are simulated alike.
+        if atpc.is_beacon_needed() || atpc.next_probe().is_some() {
+            let powers = atpc.get_beacon_powers();
+            let sweep = nonce;
+            let mut rssi = Vec::with_capacity(powers.len());
+            for (tpi, tp) in powers.iter().enumerate() {
+                atpc.register_beacon(tpi, nonce);
+                nonce += 1;
+                report.beacons += 1;
+                report.energy += energy(*tp);
+                rssi.push(link.receive(*tp));
+            }
+            atpc.report_beacon_acknowledgment(NEIGHBOR, sweep, &rssi);
+        }
+        let tx_power = atpc.get_tx_power(NEIGHBOR);
+        atpc.register_transmission(nonce, tx_power, &[NEIGHBOR]);
+        report.energy += energy(tx_power);
+        match link.receive(tx_power) {
+            Some(rssi) => {
+                report.delivered += 1;
+                atpc.report_successful_reception(NEIGHBOR, nonce, RSSI_TARGET - rssi);
+            }
+            None => atpc.report_failed_reception(NEIGHBOR),
+        }
+        nonce += 1;
+    }
+    report
+}
+
+fn main() {
+    let beacon_delay = Duration::from_secs(8 * 3600);
+    let scenarios = [
+        ("Short stable link", 110.0, 1.0),
+        ("Long stable link", 125.0, 1.0),
+        ("Long noisy link", 125.0, 4.0),
+    ];
+    println!(
+        "{:<20} {:<10} {:>10} {:>8} {:>12} {:>14}",
+        "Scenario", "ATPC", "Delivery", "Beacons", "Energy (J)", "Energy/msg (mJ)"
+    );
+    for (seed, (name, median, noise)) in scenarios.iter().enumerate() {
+        let seed = 0x9e37_79b9_7f4a_7c15 ^ (seed as u64 + 1);
+        let reports = [
+            (
+                "Testing",
+                run(
+                    TestingATPC::new(TRANSMISSION_POWERS.to_vec()),
+                    Link::new(seed, *median, *noise),
+                ),
+            ),
+            (
+                "Default",
+                run(
+                    DefaultATPC::new(
+                        TRANSMISSION_POWERS.to_vec(),
+                        3,
+                        LOWER_RSSI,
+                        RSSI_TARGET,
+                        beacon_delay,
+                    ),
+                    Link::new(seed, *median, *noise),
+                ),
+            ),
+            (
+                "Kalman",
+                run(
+                    KalmanATPC::new(
+                        TRANSMISSION_POWERS.to_vec(),
+                        3,
+                        LOWER_RSSI,
+                        RSSI_TARGET,
+                        beacon_delay,
+                    ),
+                    Link::new(seed, *median, *noise),
+                ),
+            ),
+        ];
+        for (atpc, report) in reports {
+            println!(
+                "{:<20} {:<10} {:>9.1}% {:>8} {:>12.2} {:>14.2}",
+                name,
+                atpc,
+                100.0 * (report.delivered as f32) / (TRANSMISSIONS as f32),
+                report.beacons,
+                report.energy,
+                1000.0 * report.energy / (report.delivered.max(1) as f32),
+            );
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Parses arbitrary bytes as the beacon payloads and beacon acknowledgments of the ATPC.
+//!
+//! ```text
+//! cargo +nightly fuzz run beacon
+//! ```
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use radio_tipe_poc::frame::{BeaconAcknowledgment, BeaconPayload};
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok((payload, read)) = BeaconPayload::try_from_bytes(bytes) {
+        assert!(payload.tpi < payload.levels);
+        assert_eq!(payload.to_bytes(), &bytes[..read]);
+    }
+    if let Ok((ack, read)) = BeaconAcknowledgment::try_from_bytes(bytes) {
+        assert_eq!(ack.to_bytes(), &bytes[..read]);
+    }
+});
@@ -0,0 +1,29 @@
+//! Parses arbitrary bytes as a radio frame, as received from the radio.
+//!
+//! ```text
+//! cargo +nightly fuzz run frame
+//! ```
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use radio_tipe_poc::frame::{RadioFrameView, RadioFrameWithHeaders, RadioHeaders};
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok((headers, read)) = RadioHeaders::try_from_bytes(bytes) {
+        assert_eq!(headers.to_bytes(), &bytes[..read]);
+    }
+    if let Ok((view, read)) = RadioFrameView::try_from_bytes(bytes) {
+        // Every accessor of a validated view stays in the frame.
+        let _ = view.headers.recipients.to_header();
+        let _ = view.headers.extensions.clone().count();
+        let _ = view.acknowledgments().count();
+        for id in 0..=view.headers.payloads {
+            let _ = view.decompressed_payload(id);
+        }
+        let frame = view.to_frame();
+        assert_eq!(frame.to_bytes(), &bytes[..read]);
+        let (_, owned_read) = RadioFrameWithHeaders::try_from_bytes(bytes)
+            .expect("the owned parser rejected a frame accepted by the view");
+        assert_eq!(owned_read, read);
+    }
+});
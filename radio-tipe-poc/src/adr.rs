@@ -0,0 +1,148 @@
+//! Adaptive Data Rate (ADR), the selection of the data rate (spreading factor and bandwidth) by
+//! destination.
+//!
+//! Similarly to the ADR of LoRaWAN, the [DataRateAdapter] keeps the SNR of the latest frames
+//! received from each neighbor, and selects the fastest data rate whose demodulation floor
+//! (see [DataRate::min_snr](crate::radio::DataRate::min_snr)) is below the best observed SNR
+//! minus a safety margin. Links are assumed to be symmetric: the SNR of the frames received from
+//! a neighbor is an estimate of the SNR of our frames at the neighbor.
+//!
+//! The first physical frame of a transmission is always sent with the default data rate of the
+//! first channel (the one every peer listens to), and announces the data rate of the following
+//! physical frames in an extension of its headers (see
+//! [DATA_RATE_EXTENSION](crate::frame::DATA_RATE_EXTENSION)). The frames at the default data rate
+//! do not announce it, they keep the encoding of the nodes without ADR.
+use crate::LoRaAddress;
+
+use alloc::collections::VecDeque;
+use core::num::NonZeroUsize;
+
+use lru::LruCache;
+
+/// Number of SNR samples kept by neighbor.
+const ADR_HISTORY: usize = 20;
+/// Minimal number of SNR samples before leaving the default data rate.
+const ADR_MIN_SAMPLES: usize = 5;
+/// Default safety margin (in dB), as recommended for LoRaWAN.
+const DEFAULT_ADR_MARGIN: f32 = 10.0;
+/// Highest data rate index that can be announced in a frame.
+pub const MAX_DATA_RATE: u8 = 0x0F;
+
+/// Selection of the data rate by destination, see the [module documentation](self).
+///
+/// Data rates are designated by their index: 0 is the default data rate of the channels, the
+/// following indexes are the faster variants of the channels (from the slowest to the fastest).
+pub struct DataRateAdapter {
+    /// SNR (in dB) of the latest frames received from the most recent neighbors.
+    neighbors: LruCache<LoRaAddress, VecDeque<f32>>,
+    /// Safety margin (in dB) above the demodulation floor of a data rate.
+    margin: f32,
+}
+
+impl Default for DataRateAdapter {
+    fn default() -> Self {
+        Self::new(DEFAULT_ADR_MARGIN)
+    }
+}
+
+impl DataRateAdapter {
+    /// Builds a new data rate adapter with the given safety margin (in dB).
+    pub fn new(margin: f32) -> Self {
+        Self {
+            neighbors: LruCache::new(NonZeroUsize::new(128).unwrap()),
+            margin,
+        }
+    }
+
+    /// Sets the safety margin (in dB) above the demodulation floor of a data rate.
+    pub fn set_margin(&mut self, margin: f32) {
+        self.margin = margin;
+    }
+
+    /// Reports the SNR (in dB) of a frame received from a neighbor.
+    pub fn report_reception(&mut self, neighbor_addr: LoRaAddress, snr: f32) {
+        if !self.neighbors.contains(&neighbor_addr) {
+            self.neighbors
+                .push(neighbor_addr, VecDeque::with_capacity(ADR_HISTORY));
+        }
+        if let Some(history) = self.neighbors.get_mut(&neighbor_addr) {
+            if history.len() >= ADR_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(snr);
+        }
+    }
+
+    /// Reports a missing acknowledgment of a neighbor.
+    ///
+    /// The link might have degraded, the neighbor goes back to the default data rate until
+    /// enough new samples are collected.
+    pub fn report_failure(&mut self, neighbor_addr: LoRaAddress) {
+        if let Some(history) = self.neighbors.get_mut(&neighbor_addr) {
+            history.clear();
+        }
+    }
+
+    /// Gets the data rate to use to transmit to a neighbor.
+    ///
+    /// `min_snrs` are the demodulation floors of the faster data rates (the data rate `i` has
+    /// the floor `min_snrs[i - 1]`).
+    pub fn get_data_rate(&self, neighbor_addr: LoRaAddress, min_snrs: &[f32]) -> u8 {
+        let Some(history) = self.neighbors.peek(&neighbor_addr) else {
+            return 0;
+        };
+        if history.len() < ADR_MIN_SAMPLES {
+            return 0;
+        }
+        let snr_max = history.iter().copied().fold(f32::MIN, f32::max);
+        min_snrs
+            .iter()
+            .take(MAX_DATA_RATE as usize)
+            .rposition(|min_snr| snr_max - self.margin >= *min_snr)
+            .map(|i| (i + 1) as u8)
+            .unwrap_or(0)
+    }
+
+    /// Gets the data rate to use to transmit to several neighbors, the slowest of their data rates.
+    pub fn get_min_data_rate(&self, neighbor_addrs: &[LoRaAddress], min_snrs: &[f32]) -> u8 {
+        neighbor_addrs
+            .iter()
+            .map(|na| self.get_data_rate(*na, min_snrs))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Demodulation floors of SF8 and SF7.
+    const MIN_SNRS: [f32; 2] = [-10.0, -7.5];
+
+    #[test]
+    fn adr_select_data_rate() {
+        let mut adr = DataRateAdapter::default();
+        assert_eq!(adr.get_data_rate(0x0042, &MIN_SNRS), 0);
+        for snr in [-1.0, 0.0, 1.0, 0.5] {
+            adr.report_reception(0x0042, snr);
+        }
+        // Not enough samples.
+        assert_eq!(adr.get_data_rate(0x0042, &MIN_SNRS), 0);
+        adr.report_reception(0x0042, 1.0);
+        // 1dB - 10dB of margin only allows SF8.
+        assert_eq!(adr.get_data_rate(0x0042, &MIN_SNRS), 1);
+        adr.report_reception(0x0042, 3.0);
+        assert_eq!(adr.get_data_rate(0x0042, &MIN_SNRS), 2);
+
+        for _ in 0..5 {
+            adr.report_reception(0x0043, -5.0);
+        }
+        assert_eq!(adr.get_data_rate(0x0043, &MIN_SNRS), 0);
+        assert_eq!(adr.get_min_data_rate(&[0x0042, 0x0043], &MIN_SNRS), 0);
+        assert_eq!(adr.get_min_data_rate(&[0x0042], &MIN_SNRS), 2);
+
+        adr.report_failure(0x0042);
+        assert_eq!(adr.get_data_rate(0x0042, &MIN_SNRS), 0);
+    }
+}
@@ -1,9 +1,17 @@
 //! Adaptive Transmission Power Control interfaces and basic implementations.
 //!
 //! This module provides the public trait to implement an ATPC at the application level.
-//! Moreover it provides two implementations, a naive implementation that basically disable
-//! the ATPC and a [standard implementation](DefaultATPC) based on
-//! [Shan Lin's work](https://www.cs.virginia.edu/~stankovic/psfiles/ATPC.pdf).
+//! Moreover it provides three implementations, a naive implementation that basically disable
+//! the ATPC, a [standard implementation](DefaultATPC) based on
+//! [Shan Lin's work](https://www.cs.virginia.edu/~stankovic/psfiles/ATPC.pdf) and an
+//! [implementation based on a Kalman filter](KalmanATPC) that learns from every acknowledgment.
+//!
+//! The beacons of the provided implementations are scheduled by a [BeaconScheduler]: periodic
+//! sweeps broadcasted to every neighbor, and probes addressed to a single neighbor that needs
+//! to be initialized.
+//!
+//! The transmission power of the frames addressed to several neighbors is selected according to
+//! a [GroupPolicy].
 //!
 //! ## Usages
 //! Either just use a provided implementation and passed it to your [LoRaRadio](crate::radio::LoRaRadio).
@@ -12,15 +20,36 @@
 //! let mut device = LoRaRadio::new(lora, &channels, atpc, -100, None, None, 0b0101_0011);
 //! ```
 //! Or implement your own ATPC by creating your structure who implement the [ATPC] trait.
+//!
+//! ## Persistence
+//! The [DefaultATPC] can export its neighbor table as an [ATPCSnapshot] and resume from it after
+//! a reboot, sparing a full round of beacons. Snapshots are written to and read from an
+//! [ATPCStorage] (a file, a NVS partition...) with the serde format of your choice.
+//! ```rust,ignore
+//! atpc.save_to(&mut storage)?;
+//! // After a reboot...
+//! let restored = atpc.restore_from(&mut storage, Duration::from_secs(24 * 3600))?;
+//! ```
 use crate::frame::FrameNonce;
+use crate::prelude::*;
+use crate::sys::{self, Instant};
 use crate::LoRaAddress;
 
-use std::cmp::Ordering;
-use std::num::NonZeroUsize;
-use std::time::Duration;
-use std::time::Instant;
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::num::NonZeroUsize;
+use core::time::Duration;
 
 use lru::LruCache;
+use serde::{Deserialize, Serialize};
+
+mod group;
+mod kalman;
+mod scheduler;
+
+pub use group::{plan_split, GroupPolicy};
+pub use kalman::KalmanATPC;
+pub use scheduler::BeaconScheduler;
 
 /// Modelisation of the RSSI on the receiver end when the transmitter uses a particular
 /// Transmission Power (Transmission Level).
@@ -28,11 +57,153 @@ use lru::LruCache;
 /// This model uses the following approximation: `RSSI = a * TP + b` for a particular `ControlModel(a,b)`.
 ///
 /// This model follows the design provided in [Shan Lin's work](https://www.cs.virginia.edu/~stankovic/psfiles/ATPC.pdf).
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct ControlModel(f32, f32);
+
+/// Minimal number of RSSI samples for a [FitQuality] to be reliable.
+const MIN_RELIABLE_SAMPLES: usize = 3;
+/// Maximal root-mean-square error (in dB) for a [FitQuality] to be reliable.
+const MAX_RELIABLE_RMSE: f32 = 4.0;
+/// Minimal residual (in dB) of a sample to the fit of the other samples to be rejected as an
+/// outlier.
+const OUTLIER_RESIDUAL: f32 = 8.0;
+/// Delta RSSI (in dB) assumed for a missing acknowledgment: the frame was received far below
+/// the RSSI target, if at all.
+const FAILED_RECEPTION_DRSSI: i16 = 30;
+/// Default number of consecutive missing acknowledgments before a link is considered down.
+const DEFAULT_MAX_FAILURES: u32 = 5;
+/// Default period without any acknowledgment before a link is considered down.
+const DEFAULT_MAX_IDLE: Duration = Duration::from_secs(24 * 3600);
+
+/// Quality of the fit of the control model of a neighbor on its RSSI samples.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FitQuality {
+    /// Number of RSSI samples used by the fit.
+    pub samples: usize,
+    /// Number of RSSI samples rejected as outliers.
+    pub rejected: usize,
+    /// Root-mean-square error of the fit (in dB).
+    pub rmse: f32,
+    /// Coefficient of determination of the fit.
+    pub r_squared: f32,
+}
+
+impl FitQuality {
+    /// Is the fit reliable enough to skip a new round of beacons?
+    pub fn is_reliable(&self) -> bool {
+        self.samples >= MIN_RELIABLE_SAMPLES && self.rmse <= MAX_RELIABLE_RMSE
+    }
+}
+
+impl ControlModel {
+    /// Fits a control model on `(TP, RSSI)` samples with an ordinary least-squares regression.
+    ///
+    /// Returns `None` if the samples do not span at least two transmission powers.
+    fn least_squares(samples: &[(f32, f32)]) -> Option<Self> {
+        let n = samples.len() as f32;
+        let sum_tp: f32 = samples.iter().map(|(tp, _)| tp).sum();
+        let sum_rssi: f32 = samples.iter().map(|(_, rssi)| rssi).sum();
+        let sum_tp_tp: f32 = samples.iter().map(|(tp, _)| tp * tp).sum();
+        let sum_tp_rssi: f32 = samples.iter().map(|(tp, rssi)| tp * rssi).sum();
+        let denominator = n * sum_tp_tp - sum_tp * sum_tp;
+        if samples.len() < 2 || denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        let slope = (n * sum_tp_rssi - sum_tp * sum_rssi) / denominator;
+        let intercept = (sum_rssi - slope * sum_tp) / n;
+        Some(ControlModel(slope, intercept))
+    }
+
+    /// Fits a control model on `(TP, RSSI)` samples, rejecting the outliers.
+    ///
+    /// A sample is an outlier when it is farther than [OUTLIER_RESIDUAL] from the fit of the
+    /// other samples. Samples are only rejected while [MIN_RELIABLE_SAMPLES] remain.
+    fn fit(samples: &[(f32, f32)]) -> Option<(Self, FitQuality)> {
+        let mut samples = samples.to_vec();
+        let mut rejected = 0;
+        let mut model = Self::least_squares(&samples)?;
+        while samples.len() > MIN_RELIABLE_SAMPLES {
+            let (worst, _) = samples
+                .iter()
+                .enumerate()
+                .map(|(i, (tp, rssi))| (i, (model.predict(*tp) - rssi).abs()))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+            let mut others = samples.clone();
+            let (tp, rssi) = others.remove(worst);
+            match Self::least_squares(&others) {
+                Some(candidate) if (candidate.predict(tp) - rssi).abs() > OUTLIER_RESIDUAL => {
+                    samples = others;
+                    model = candidate;
+                    rejected += 1;
+                }
+                _ => break,
+            }
+        }
+        let mean = samples.iter().map(|(_, rssi)| rssi).sum::<f32>() / samples.len() as f32;
+        let ss_res: f32 = samples
+            .iter()
+            .map(|(tp, rssi)| {
+                let residual = rssi - model.predict(*tp);
+                residual * residual
+            })
+            .sum();
+        let ss_tot: f32 = samples
+            .iter()
+            .map(|(_, rssi)| (rssi - mean) * (rssi - mean))
+            .sum();
+        let quality = FitQuality {
+            samples: samples.len(),
+            rejected,
+            rmse: sys::sqrtf(ss_res / samples.len() as f32),
+            r_squared: if ss_tot > 0.0 {
+                1.0 - ss_res / ss_tot
+            } else {
+                1.0
+            },
+        };
+        Some((model, quality))
+    }
+
+    /// Predicts the RSSI for a transmission power.
+    fn predict(&self, tp: f32) -> f32 {
+        self.0 * tp + self.1
+    }
+
+    /// Is this model usable to calculate a transmission power?
+    ///
+    /// The RSSI must increase with the transmission power.
+    fn is_valid(&self) -> bool {
+        self.0.is_finite() && self.1.is_finite() && self.0 > 0.0
+    }
+}
+
+/// Aging of the neighbors of the [DefaultATPC].
+///
+/// The link with a neighbor is considered down after `max_failures` consecutive missing
+/// acknowledgments, or when no acknowledgment was received from it for `max_idle`. The neighbor
+/// is then initialized again (it will be probed), or forgotten if `evict` is set.
 #[derive(Clone, PartialEq, Eq, Debug)]
-struct ControlModel(i16, i16);
+pub struct AgingPolicy {
+    /// Consecutive missing acknowledgments before a link is considered down.
+    pub max_failures: u32,
+    /// Period without any acknowledgment before a link is considered down.
+    pub max_idle: Duration,
+    /// Forget the neighbors whose link is down, instead of initializing them again.
+    pub evict: bool,
+}
+
+impl Default for AgingPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: DEFAULT_MAX_FAILURES,
+            max_idle: DEFAULT_MAX_IDLE,
+            evict: false,
+        }
+    }
+}
 
 /// Status of a neighbor for the [DefaultATPC].
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 enum NeighborStatus {
     /// This neighbor has not yet answered to our beacons (or partially). We currently have no
     /// information on the transmission power needed for this peer.
@@ -51,11 +222,20 @@ struct NeighborModel {
     pub status: NeighborStatus,
     /// Dedicated control model for this particular node.
     pub control_model: ControlModel,
-    /// RSSI responses for the various transmissions power levels.
+    /// RSSI responses for the various transmissions power levels, `None` if the peer missed
+    /// the beacon at this level.
     ///
     /// Those are calculated with the acknowledgments given by the peer. This includes
     /// the answers to our beacons.
-    pub rssi: Vec<i16>,
+    pub rssi: Vec<Option<i16>>,
+    /// Quality of the fit of the control model on the RSSI responses.
+    pub fit_quality: Option<FitQuality>,
+    /// Last time the control model of this peer was updated.
+    pub last_update: Instant,
+    /// Last time an acknowledgment was received from this peer.
+    pub last_seen: Instant,
+    /// Number of consecutive missing acknowledgments of this peer.
+    pub failures: u32,
 }
 
 impl Ord for NeighborModel {
@@ -87,12 +267,106 @@ impl NeighborModel {
         NeighborModel {
             node_address,
             status: NeighborStatus::Initializing,
-            control_model: ControlModel(0, 0),
-            rssi: vec![0; ntp],
+            control_model: ControlModel(0.0, 0.0),
+            rssi: vec![None; ntp],
+            fit_quality: None,
+            last_update: Instant::now(),
+            last_seen: Instant::now(),
+            failures: 0,
         }
     }
 }
 
+/// Persisted state of a neighbor, see [ATPCSnapshot].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct NeighborRecord {
+    node_address: LoRaAddress,
+    status: NeighborStatus,
+    control_model: ControlModel,
+    rssi: Vec<Option<i16>>,
+    #[serde(default)]
+    fit_quality: Option<FitQuality>,
+    /// Last update of the control model, as seconds since the UNIX epoch.
+    last_update: u64,
+}
+
+/// Snapshot of the neighbor table of a [DefaultATPC].
+///
+/// Timestamps are stored as seconds since the UNIX epoch, as [Instant]s do not survive a reboot.
+/// Therefore the clock of the device must be set (by NTP, GPS or a RTC) before taking or
+/// restoring a snapshot, otherwise staleness checks are meaningless.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ATPCSnapshot {
+    /// Time of the snapshot, as seconds since the UNIX epoch.
+    pub saved_at: u64,
+    /// Time of the last beacon transmission, as seconds since the UNIX epoch.
+    pub last_beacon: u64,
+    /// The transmission powers of the ATPC that took this snapshot.
+    ///
+    /// The RSSI samples of the neighbors are only meaningful for those transmission powers.
+    pub transmission_powers: Vec<i8>,
+    /// The neighbors, from the least recently used to the most recently used.
+    neighbors: Vec<NeighborRecord>,
+}
+
+impl ATPCSnapshot {
+    /// Gets the number of neighbors in this snapshot.
+    pub fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    /// Is this snapshot empty?
+    pub fn is_empty(&self) -> bool {
+        self.neighbors.is_empty()
+    }
+}
+
+/// Error while restoring an [ATPCSnapshot].
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotError<E> {
+    /// The snapshot was taken with other transmission powers.
+    #[error("Snapshot transmission powers {snapshot:?} do not match the ATPC ones {expected:?}.")]
+    IncompatibleTransmissionPowers {
+        snapshot: Vec<i8>,
+        expected: Vec<i8>,
+    },
+    /// The storage failed to save or load the snapshot.
+    #[error("Snapshot storage error.")]
+    StorageError(#[source] E),
+}
+
+/// Persistent storage of [ATPCSnapshot]s.
+///
+/// Implementations are free to choose the location and the serde format of the snapshots.
+pub trait ATPCStorage {
+    /// Error of the storage.
+    type Error;
+
+    /// Saves a snapshot, replacing the previous one.
+    fn save(&mut self, snapshot: &ATPCSnapshot) -> Result<(), Self::Error>;
+
+    /// Loads the last saved snapshot, if any.
+    fn load(&mut self) -> Result<Option<ATPCSnapshot>, Self::Error>;
+}
+
+/// Converts an [Instant] in the past to seconds since the UNIX epoch.
+///
+/// `system_now` is the current time since the UNIX epoch, see [sys::unix_time].
+fn instant_to_timestamp(instant: Instant, now: Instant, system_now: Duration) -> u64 {
+    system_now
+        .saturating_sub(now.saturating_duration_since(instant))
+        .as_secs()
+}
+
+/// Converts seconds since the UNIX epoch to an [Instant], returns also the age of the timestamp.
+///
+/// Timestamps in the future are considered as current, and timestamps older than the monotonic
+/// clock are clamped to `now`.
+fn timestamp_to_instant(timestamp: u64, now: Instant, system_now: Duration) -> (Instant, Duration) {
+    let age = system_now.saturating_sub(Duration::from_secs(timestamp));
+    (now.checked_sub(age).unwrap_or(now), age)
+}
+
 /// Abstract representation of an Adaptable Transmission Power Control (ATPC).
 ///
 /// This trait is an essential component of the [LoRaRadio](crate::device::radio::LoRaRadio).
@@ -101,9 +375,28 @@ impl NeighborModel {
 /// transmission.
 pub trait ATPC {
     /// Should the radio transmit beacons ? It is mostly determined by the time elapsed from the last
-    /// transmission of beacons and the registration of unknown peers that are waiting for initialization.
+    /// transmission of beacons.
+    ///
+    /// The unknown peers that are waiting for initialization should rather be probed, see
+    /// [next_probe](ATPC::next_probe).
     fn is_beacon_needed(&self) -> bool;
 
+    /// Should the radio probe a neighbor ? A probe is a sweep of beacons addressed to a single
+    /// neighbor, usually one waiting for initialization.
+    ///
+    /// The default implementation never probes.
+    fn is_probe_needed(&self) -> bool {
+        false
+    }
+
+    /// Gives the next neighbor to probe, if any, and registers the probe: the following beacons
+    /// (see [register_beacon](ATPC::register_beacon)) are addressed to this neighbor only.
+    ///
+    /// The default implementation never probes.
+    fn next_probe(&mut self) -> Option<LoRaAddress> {
+        None
+    }
+
     /// Gives a list of transmission power to use to transmit the beacons.
     /// Those might or not be equal to the transmission powers given at construction of an ATPC.
     ///
@@ -117,6 +410,19 @@ pub trait ATPC {
     /// update the [ControlModel] of each neighbor.
     fn register_beacon(&mut self, tpi: usize, nonce: FrameNonce);
 
+    /// Registers a transmission with its nonce, its transmission power and its recipients.
+    ///
+    /// This allows an ATPC to learn from the acknowledgments of every transmission, and not only
+    /// from the beacons. The default implementation ignores transmissions.
+    fn register_transmission(
+        &mut self,
+        nonce: FrameNonce,
+        tx_power: i8,
+        recipients: &[LoRaAddress],
+    ) {
+        let _ = (nonce, tx_power, recipients);
+    }
+
     /// Registers a neighbor. This indicates an interest by the radio to transmit data to this peer.
     ///
     /// This function might cause (if the peer is unknown) a transmission of beacons.
@@ -128,6 +434,10 @@ pub trait ATPC {
     /// Calculates the needed transmission power for a particular neighbor.
     fn get_tx_power(&mut self, neighbor_addr: LoRaAddress) -> i8;
 
+    /// Gives the transmission power to use when no recipient is known, for instance for a
+    /// multicast group without any known member.
+    fn get_default_tx_power(&mut self) -> i8;
+
     /// Calculates the needed transmission power for a particular set of neighbors.
     fn get_min_tx_power(&mut self, mut neighbor_addrs: Vec<LoRaAddress>) -> (i8, Vec<LoRaAddress>) {
         // Minimal default implementation.
@@ -151,6 +461,31 @@ pub trait ATPC {
         }
     }
 
+    /// Calculates the needed transmission power for a particular set of neighbors, according to
+    /// a [GroupPolicy].
+    ///
+    /// As [get_min_tx_power](ATPC::get_min_tx_power), it returns the transmission power and the
+    /// neighbors that need exactly this transmission power.
+    fn get_group_tx_power(
+        &mut self,
+        neighbor_addrs: Vec<LoRaAddress>,
+        policy: &GroupPolicy,
+    ) -> (i8, Vec<LoRaAddress>) {
+        match policy {
+            GroupPolicy::DeliveryRatio(_) if neighbor_addrs.len() > 1 => {
+                let tx_powers: Vec<(LoRaAddress, i8)> = neighbor_addrs
+                    .iter()
+                    .map(|na| (*na, self.get_tx_power(*na)))
+                    .collect();
+                match policy.select_tx_power(&tx_powers) {
+                    Some(selected) => selected,
+                    None => self.get_min_tx_power(neighbor_addrs),
+                }
+            }
+            _ => self.get_min_tx_power(neighbor_addrs),
+        }
+    }
+
     /// Reports the reception of an acknownledgment (maybe for a beacon) by a neighbor.
     ///
     /// This will update the [ControlModel] of this particular peer accordingly to the given
@@ -166,6 +501,32 @@ pub trait ATPC {
     ///
     /// This will update the [ControlModel] of this particular peer accordingly
     fn report_failed_reception(&mut self, neighbor_addr: LoRaAddress);
+
+    /// Reports the acknowledgment of a whole sweep of beacons by a neighbor.
+    ///
+    /// The sweep is identified by the nonce of its first beacon, and `rssi` holds the RSSI (in dBm)
+    /// of each beacon received by the neighbor, by transmission power level (the index in
+    /// [get_beacon_powers](ATPC::get_beacon_powers)). Missed beacons are `None`.
+    ///
+    /// The default implementation ignores beacon acknowledgments.
+    fn report_beacon_acknowledgment(
+        &mut self,
+        neighbor_addr: LoRaAddress,
+        sweep: FrameNonce,
+        rssi: &[Option<i16>],
+    ) {
+        let _ = (neighbor_addr, sweep, rssi);
+    }
+
+    /// Takes the neighbors whose link was considered down since the last call, for instance
+    /// after repeated missing acknowledgments or a long silence.
+    ///
+    /// The radio polls this function to notify the application (see
+    /// [TxClient::link_down](crate::device::TxClient::link_down)). The default implementation
+    /// never considers a link down.
+    fn take_lost_links(&mut self) -> Vec<LoRaAddress> {
+        Vec::new()
+    }
 }
 
 /// Default implementation of the ATPC, based on [Shan Lin's work](https://www.cs.virginia.edu/~stankovic/psfiles/ATPC.pdf).
@@ -173,6 +534,8 @@ pub trait ATPC {
 /// It provides an efficient implementation that can adapt to its surrounding and with a small cost
 /// of only three beacon tranmissions per day. Moreover the design is pretty simple and offer
 /// good results in different real case scenarios.
+///
+/// The links with the neighbors age according to an [AgingPolicy].
 pub struct DefaultATPC {
     /// LRU Cache to remember the parameters associated with the most recent neighbors.
     neighbors: LruCache<LoRaAddress, NeighborModel>,
@@ -183,22 +546,31 @@ pub struct DefaultATPC {
     default_tp: u8,
     /// The minimal RSSI threashold that the radio will consider acceptable.
     lower_rssi: i16,
-    /// Delay between beacon broadcasting.
-    ///
-    /// 8h seems a good value.
-    beacon_delay: Duration,
+    /// The RSSI target of the peers, used to recover the RSSI from the acknowledgments.
+    rssi_target: i16,
+    /// Scheduler of the beacon sweeps (8h between them seems a good value) and probes.
+    scheduler: BeaconScheduler,
+    /// The neighbor probed by the next beacons, if any.
+    probe: Option<LoRaAddress>,
     /// The latest beacons transmitted as a nonce-transmission power level value.
     beacons: LruCache<FrameNonce, u8>,
-    /// Last time a beacon was transmitted.
+    /// Last time a sweep of beacons was broadcasted.
     last_beacon: Instant,
+    /// Aging of the neighbors.
+    aging: AgingPolicy,
+    /// The neighbors whose link was considered down, not taken yet by the radio.
+    lost_links: Vec<LoRaAddress>,
 }
 
 impl DefaultATPC {
     /// Builds a new instance of the Default ATPC.
+    ///
+    /// The RSSI target of the peers is assumed to be the same than ours.
     pub fn new(
         transmission_powers: Vec<i8>,
         default_tp: impl Into<u8>,
         lower_rssi: i16,
+        rssi_target: i16,
         beacon_delay: Duration,
     ) -> Self {
         let default_tp_ = default_tp.into();
@@ -209,40 +581,84 @@ impl DefaultATPC {
             transmission_powers,
             default_tp: default_tp_,
             lower_rssi,
+            rssi_target,
+            scheduler: BeaconScheduler::new(beacon_delay),
+            probe: None,
             beacons: LruCache::new(NonZeroUsize::new(tp_len + 1).unwrap()),
             last_beacon: Instant::now(),
-            beacon_delay,
+            aging: AgingPolicy::default(),
+            lost_links: Vec::new(),
+        }
+    }
+
+    /// Gets the scheduler of the beacons, to configure its jitter, backoff and cap.
+    pub fn scheduler_mut(&mut self) -> &mut BeaconScheduler {
+        &mut self.scheduler
+    }
+
+    /// Sets the aging of the neighbors.
+    pub fn set_aging(&mut self, aging: AgingPolicy) {
+        self.aging = aging;
+    }
+
+    /// Considers the link with a neighbor down: the neighbor is initialized again, or forgotten,
+    /// depending on the [AgingPolicy].
+    fn link_down(&mut self, neighbor_addr: LoRaAddress) {
+        if self.aging.evict {
+            self.neighbors.pop(&neighbor_addr);
+        } else if let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) {
+            // The previous samples do not describe the link anymore.
+            *neigh = NeighborModel::new(neighbor_addr, self.transmission_powers.len());
+        }
+        self.scheduler.reset_probe(neighbor_addr);
+        if !self.lost_links.contains(&neighbor_addr) {
+            self.lost_links.push(neighbor_addr);
+        }
+    }
+
+    /// Records an acknowledgment of a neighbor, for its aging.
+    fn neighbor_seen(&mut self, neighbor_addr: LoRaAddress) {
+        if let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) {
+            neigh.last_seen = Instant::now();
+            neigh.failures = 0;
         }
     }
 
+    /// Should this neighbor be probed? Either it was not initialized yet, or its control model
+    /// does not fit its samples.
+    fn needs_probe(neigh: &NeighborModel) -> bool {
+        neigh.status == NeighborStatus::Initializing
+            || !neigh.fit_quality.is_some_and(|q| q.is_reliable())
+    }
+
     /// Rebuilds the [ControlModel] of a specific neighbor.
     ///
-    /// Mostly used to update a node following a beacon acknowledgment.
+    /// Mostly used to update a node following a beacon acknowledgment. The missing samples
+    /// are ignored, and the neighbor stays in (or goes back to) initialization if the samples
+    /// do not allow a valid model.
     fn rebuid_neighbor_model(&mut self, neighbor_addr: LoRaAddress) {
         if let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) {
-            let n = self.transmission_powers.len();
-            let sum_tp: f32 = self
+            let samples: Vec<(f32, f32)> = self
                 .transmission_powers
                 .iter()
-                .fold(0.0, |acc, x| acc + (*x as f32));
-            let sum_rssi: f32 = neigh.rssi.iter().fold(0.0, |acc, x| acc + (*x as f32));
-            let sum_tp_rssi: f32 = (0..self.transmission_powers.len())
-                .into_iter()
-                .fold(0.0, |acc, i| {
-                    acc + (self.transmission_powers[i] as f32) * (neigh.rssi[i] as f32)
-                });
-            let denominator: f32 = (n as f32)
-                * self
-                    .transmission_powers
-                    .iter()
-                    .fold(0.0, |acc, x| acc + (*x as f32) * (*x as f32))
-                + sum_tp * sum_tp;
-
-            neigh.control_model.0 =
-                (((sum_rssi * sum_tp * sum_tp) - (sum_tp * sum_tp_rssi)) / denominator) as i16;
-            neigh.control_model.1 =
-                ((((n as f32) * sum_tp_rssi) - (sum_tp * sum_rssi)) / denominator) as i16;
-            neigh.status = NeighborStatus::Runtime;
+                .zip(neigh.rssi.iter())
+                .filter_map(|(tp, rssi)| rssi.map(|rssi| (*tp as f32, rssi as f32)))
+                .collect();
+            match ControlModel::fit(&samples) {
+                Some((model, quality)) if model.is_valid() => {
+                    neigh.control_model = model;
+                    neigh.fit_quality = Some(quality);
+                    neigh.status = NeighborStatus::Runtime;
+                    neigh.last_update = Instant::now();
+                }
+                _ => {
+                    neigh.fit_quality = None;
+                    neigh.status = NeighborStatus::Initializing;
+                }
+            }
+            if !Self::needs_probe(neigh) {
+                self.scheduler.reset_probe(neighbor_addr);
+            }
         }
     }
 
@@ -255,7 +671,8 @@ impl DefaultATPC {
             if (delta > 0 && tp < self.transmission_powers[self.transmission_powers.len() - 1])
                 || (delta < 0 && tp > self.transmission_powers[0])
             {
-                neigh.control_model.1 -= delta;
+                neigh.control_model.1 -= delta as f32;
+                neigh.last_update = Instant::now();
             }
         }
     }
@@ -266,27 +683,159 @@ impl DefaultATPC {
             .neighbors
             .get(&neighbor_addr)
             .expect("calculating TP for an inexistant neighbor.");
-        let tp_target = (self.lower_rssi - neigh.control_model.1) / neigh.control_model.0;
+        if neigh.status == NeighborStatus::Initializing || !neigh.control_model.is_valid() {
+            return self.transmission_powers[self.default_tp as usize];
+        }
+        let tp_target = (self.lower_rssi as f32 - neigh.control_model.1) / neigh.control_model.0;
         if let Some(tp) = self
             .transmission_powers
             .iter()
-            .find(|tp| (**tp as i16) >= tp_target)
+            .find(|tp| (**tp as f32) >= tp_target)
         {
             return *tp;
         } else {
             return self.transmission_powers[self.transmission_powers.len() - 1];
         }
     }
+
+    /// Gets the quality of the fit of the control model of a neighbor.
+    ///
+    /// Returns `None` if the neighbor is unknown or was not initialized yet.
+    pub fn get_fit_quality(&self, neighbor_addr: LoRaAddress) -> Option<FitQuality> {
+        self.neighbors
+            .peek(&neighbor_addr)
+            .and_then(|neigh| neigh.fit_quality)
+    }
+
+    /// Exports the neighbor table of this ATPC.
+    pub fn export(&self) -> ATPCSnapshot {
+        let now = Instant::now();
+        let system_now = sys::unix_time();
+        let neighbors = self
+            .neighbors
+            .iter()
+            .rev()
+            .map(|(_, n)| NeighborRecord {
+                node_address: n.node_address,
+                status: n.status.clone(),
+                control_model: n.control_model.clone(),
+                rssi: n.rssi.clone(),
+                fit_quality: n.fit_quality,
+                last_update: instant_to_timestamp(n.last_update, now, system_now),
+            })
+            .collect();
+        ATPCSnapshot {
+            saved_at: instant_to_timestamp(now, now, system_now),
+            last_beacon: instant_to_timestamp(self.last_beacon, now, system_now),
+            transmission_powers: self.transmission_powers.clone(),
+            neighbors,
+        }
+    }
+
+    /// Imports a neighbor table previously exported with [DefaultATPC::export].
+    ///
+    /// The neighbors whose control model is older than `max_age` are stale: they are restored
+    /// but need to be initialized again, which will trigger a probe.
+    /// The neighbors already known by this ATPC are kept as is.
+    ///
+    /// Returns the number of neighbors restored with a valid control model.
+    pub fn import(
+        &mut self,
+        snapshot: ATPCSnapshot,
+        max_age: Duration,
+    ) -> Result<usize, SnapshotError<Infallible>> {
+        self.import_snapshot(snapshot, max_age)
+    }
+
+    /// Imports a neighbor table, see [DefaultATPC::import].
+    fn import_snapshot<E>(
+        &mut self,
+        snapshot: ATPCSnapshot,
+        max_age: Duration,
+    ) -> Result<usize, SnapshotError<E>> {
+        if snapshot.transmission_powers != self.transmission_powers {
+            return Err(SnapshotError::IncompatibleTransmissionPowers {
+                snapshot: snapshot.transmission_powers,
+                expected: self.transmission_powers.clone(),
+            });
+        }
+        let now = Instant::now();
+        let system_now = sys::unix_time();
+        let (last_beacon, _) = timestamp_to_instant(snapshot.last_beacon, now, system_now);
+        if last_beacon > self.last_beacon {
+            self.last_beacon = last_beacon;
+            self.scheduler.resume(last_beacon);
+        }
+        let mut restored = 0;
+        for record in snapshot.neighbors {
+            if self.neighbors.contains(&record.node_address) {
+                continue;
+            }
+            let (last_update, age) = timestamp_to_instant(record.last_update, now, system_now);
+            let mut neigh = NeighborModel::new(record.node_address, self.transmission_powers.len());
+            if record.status == NeighborStatus::Runtime
+                && age <= max_age
+                && record.rssi.len() == neigh.rssi.len()
+                && record.control_model.is_valid()
+            {
+                neigh.status = NeighborStatus::Runtime;
+                neigh.control_model = record.control_model;
+                neigh.rssi = record.rssi;
+                neigh.fit_quality = record.fit_quality;
+                neigh.last_update = last_update;
+                neigh.last_seen = last_update;
+                restored += 1;
+            }
+            self.neighbors.push(record.node_address, neigh);
+        }
+        Ok(restored)
+    }
+
+    /// Saves the neighbor table of this ATPC in a storage.
+    pub fn save_to<S: ATPCStorage>(&self, storage: &mut S) -> Result<(), SnapshotError<S::Error>> {
+        storage
+            .save(&self.export())
+            .map_err(SnapshotError::StorageError)
+    }
+
+    /// Resumes this ATPC from the snapshot saved in a storage, see [DefaultATPC::import].
+    ///
+    /// Returns the number of neighbors restored with a valid control model (0 if the storage
+    /// holds no snapshot).
+    pub fn restore_from<S: ATPCStorage>(
+        &mut self,
+        storage: &mut S,
+        max_age: Duration,
+    ) -> Result<usize, SnapshotError<S::Error>> {
+        match storage.load().map_err(SnapshotError::StorageError)? {
+            Some(snapshot) => self.import_snapshot(snapshot, max_age),
+            None => Ok(0),
+        }
+    }
 }
 
 impl ATPC for DefaultATPC {
     fn is_beacon_needed(&self) -> bool {
-        return self.last_beacon.elapsed() > self.beacon_delay
-            || self
-                .neighbors
-                .iter()
-                .find(|(_, n)| n.status == NeighborStatus::Initializing)
-                .is_some();
+        return self.scheduler.is_sweep_due(Instant::now());
+    }
+
+    fn is_probe_needed(&self) -> bool {
+        let now = Instant::now();
+        self.neighbors
+            .iter()
+            .any(|(addr, n)| Self::needs_probe(n) && self.scheduler.is_probe_due(*addr, now))
+    }
+
+    fn next_probe(&mut self) -> Option<LoRaAddress> {
+        let now = Instant::now();
+        let addr = self
+            .neighbors
+            .iter()
+            .find(|(addr, n)| Self::needs_probe(n) && self.scheduler.is_probe_due(**addr, now))
+            .map(|(addr, _)| *addr)?;
+        self.scheduler.register_probe(addr, now);
+        self.probe = Some(addr);
+        Some(addr)
     }
 
     fn get_beacon_powers(&self) -> Vec<i8> {
@@ -294,7 +843,12 @@ impl ATPC for DefaultATPC {
     }
 
     fn register_beacon(&mut self, tpi: usize, nonce: FrameNonce) {
-        self.last_beacon = Instant::now();
+        // The first beacon of a sweep consumes the pending probe, if any.
+        if tpi == 0 && self.probe.take().is_none() {
+            let now = Instant::now();
+            self.last_beacon = now;
+            self.scheduler.register_sweep(now);
+        }
         self.beacons.push(nonce, tpi as u8);
     }
 
@@ -310,6 +864,7 @@ impl ATPC for DefaultATPC {
     }
 
     fn unregister_neighbor(&mut self, neighbor_addr: LoRaAddress) -> bool {
+        self.scheduler.reset_probe(neighbor_addr);
         return self.neighbors.pop_entry(&neighbor_addr).is_some();
     }
 
@@ -320,27 +875,30 @@ impl ATPC for DefaultATPC {
         self.transmission_powers[self.default_tp as usize]
     }
 
+    fn get_default_tx_power(&mut self) -> i8 {
+        self.transmission_powers[self.default_tp as usize]
+    }
+
     fn get_min_tx_power(&mut self, mut neighbor_addrs: Vec<LoRaAddress>) -> (i8, Vec<LoRaAddress>) {
         let mut tx_power = None;
         let mut should_update = Vec::new();
         neighbor_addrs.sort();
         for na in &neighbor_addrs {
             let tp = self.get_tx_power(*na);
-            if tx_power.is_none() || tp == tx_power.unwrap() {
-                should_update.push(*na);
-            } else if tp > tx_power.unwrap() {
-                tx_power = Some(tp);
-                should_update.clear();
-                should_update.push(*na);
+            match tx_power {
+                Some(max) if tp < max => {}
+                Some(max) if tp == max => should_update.push(*na),
+                _ => {
+                    tx_power = Some(tp);
+                    should_update.clear();
+                    should_update.push(*na);
+                }
             }
         }
         if let Some(tx_power) = tx_power {
             (tx_power, should_update)
         } else {
-            (
-                self.transmission_powers[self.default_tp as usize],
-                neighbor_addrs,
-            )
+            (self.get_default_tx_power(), neighbor_addrs)
         }
     }
 
@@ -352,16 +910,66 @@ impl ATPC for DefaultATPC {
     ) {
         if let Some(tpi) = self.beacons.get(&nonce) {
             if let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) {
-                neigh.rssi[*tpi as usize] = drssi;
+                // The samples are absolute RSSIs, as the ones of the beacon acknowledgments.
+                neigh.rssi[*tpi as usize] = Some(self.rssi_target - drssi);
                 self.rebuid_neighbor_model(neighbor_addr);
             }
         } else {
             self.update_neighbor_model(neighbor_addr, drssi);
         }
+        self.neighbor_seen(neighbor_addr);
     }
 
     fn report_failed_reception(&mut self, neighbor_addr: LoRaAddress) {
-        self.update_neighbor_model(neighbor_addr, -30);
+        self.update_neighbor_model(neighbor_addr, FAILED_RECEPTION_DRSSI);
+        let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) else {
+            return;
+        };
+        // Only the links that were up can go down.
+        if neigh.status == NeighborStatus::Runtime {
+            neigh.failures += 1;
+            if neigh.failures >= self.aging.max_failures {
+                self.link_down(neighbor_addr);
+            }
+        }
+    }
+
+    fn report_beacon_acknowledgment(
+        &mut self,
+        neighbor_addr: LoRaAddress,
+        sweep: FrameNonce,
+        rssi: &[Option<i16>],
+    ) {
+        // Ignore the acknowledgments of unknown (or too old) sweeps.
+        if self.beacons.get(&sweep) != Some(&0)
+            || rssi.len() != self.transmission_powers.len()
+            || rssi.iter().all(|r| r.is_none())
+        {
+            return;
+        }
+        // Peers answering our beacons are neighbors worth tracking.
+        self.register_neighbor(neighbor_addr);
+        if let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) {
+            // A new sweep replaces the previous samples, even the missed beacons.
+            neigh.rssi = rssi.to_vec();
+        }
+        self.neighbor_seen(neighbor_addr);
+        self.rebuid_neighbor_model(neighbor_addr);
+    }
+
+    fn take_lost_links(&mut self) -> Vec<LoRaAddress> {
+        let idle: Vec<LoRaAddress> = self
+            .neighbors
+            .iter()
+            .filter(|(_, n)| {
+                n.status == NeighborStatus::Runtime && n.last_seen.elapsed() > self.aging.max_idle
+            })
+            .map(|(addr, _)| *addr)
+            .collect();
+        for neighbor_addr in idle {
+            self.link_down(neighbor_addr);
+        }
+        core::mem::take(&mut self.lost_links)
     }
 }
 
@@ -416,6 +1024,10 @@ impl ATPC for TestingATPC {
         return tp;
     }
 
+    fn get_default_tx_power(&mut self) -> i8 {
+        self.get_tx_power(0)
+    }
+
     fn get_min_tx_power(&mut self, neighbor_addrs: Vec<LoRaAddress>) -> (i8, Vec<LoRaAddress>) {
         return (self.get_tx_power(*&neighbor_addrs[0]), neighbor_addrs);
     }
@@ -433,3 +1045,249 @@ impl ATPC for TestingATPC {
         // NO OP
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime_atpc() -> DefaultATPC {
+        let mut atpc = DefaultATPC::new(vec![2, 6, 10], 1, -100, -90, Duration::from_secs(3600));
+        atpc.register_neighbor(0x0042);
+        atpc.register_neighbor(0x0043);
+        for (tpi, nonce) in [(0, 10), (1, 11), (2, 12)] {
+            atpc.register_beacon(tpi, nonce);
+            // RSSI = -110 + 5 * tpi, for a RSSI target of -90.
+            atpc.report_successful_reception(0x0042, nonce, 20 - 5 * tpi as i16);
+        }
+        atpc
+    }
+
+    #[test]
+    fn atpc_snapshot_restore() {
+        let atpc = runtime_atpc();
+        let snapshot = atpc.export();
+        assert_eq!(snapshot.len(), 2);
+
+        let mut resumed = DefaultATPC::new(vec![2, 6, 10], 1, -100, -90, Duration::from_secs(3600));
+        let restored = resumed.import(snapshot, Duration::from_secs(60)).unwrap();
+        assert_eq!(restored, 1);
+        let neigh = resumed.neighbors.peek(&0x0042).unwrap();
+        let orig = atpc.neighbors.peek(&0x0042).unwrap();
+        assert_eq!(neigh.status, NeighborStatus::Runtime);
+        assert_eq!(neigh.control_model, orig.control_model);
+        assert_eq!(neigh.rssi, orig.rssi);
+        // The other neighbor was never initialized.
+        assert_eq!(
+            resumed.neighbors.peek(&0x0043).unwrap().status,
+            NeighborStatus::Initializing
+        );
+    }
+
+    #[test]
+    fn atpc_snapshot_staleness() {
+        let mut snapshot = runtime_atpc().export();
+        for record in snapshot.neighbors.iter_mut() {
+            record.last_update -= 7200;
+        }
+        let mut resumed = DefaultATPC::new(vec![2, 6, 10], 1, -100, -90, Duration::from_secs(3600));
+        let restored = resumed
+            .import(snapshot.clone(), Duration::from_secs(3600))
+            .unwrap();
+        assert_eq!(restored, 0);
+        assert_eq!(
+            resumed.neighbors.peek(&0x0042).unwrap().status,
+            NeighborStatus::Initializing
+        );
+        assert!(resumed.is_probe_needed());
+
+        let mut other =
+            DefaultATPC::new(vec![2, 6, 10, 14], 1, -100, -90, Duration::from_secs(3600));
+        assert!(matches!(
+            other.import(snapshot, Duration::from_secs(3600)),
+            Err(SnapshotError::IncompatibleTransmissionPowers { .. })
+        ));
+    }
+
+    #[test]
+    fn atpc_fit_control_model() {
+        // RSSI = 2 * TP - 120
+        let samples: Vec<(f32, f32)> = [2.0, 6.0, 10.0, 14.0]
+            .iter()
+            .map(|tp| (*tp, 2.0 * tp - 120.0))
+            .collect();
+        let (model, quality) = ControlModel::fit(&samples).unwrap();
+        assert!((model.0 - 2.0).abs() < 1e-4);
+        assert!((model.1 + 120.0).abs() < 1e-3);
+        assert_eq!(quality.samples, 4);
+        assert_eq!(quality.rejected, 0);
+        assert!(quality.rmse < 1e-3);
+        assert!(quality.is_reliable());
+
+        // A single transmission power does not define a model.
+        assert!(ControlModel::fit(&[(6.0, -100.0), (6.0, -98.0)]).is_none());
+    }
+
+    #[test]
+    fn atpc_fit_rejects_outliers() {
+        let samples = [
+            (2.0, -116.0),
+            (6.0, -108.0),
+            (10.0, -80.0), // Outlier
+            (14.0, -92.0),
+            (17.0, -86.0),
+        ];
+        let (model, quality) = ControlModel::fit(&samples).unwrap();
+        assert_eq!(quality.rejected, 1);
+        assert_eq!(quality.samples, 4);
+        assert!((model.0 - 2.0).abs() < 1e-3);
+        assert!((model.1 + 120.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn atpc_beacon_acknowledgment_missing_samples() {
+        let mut atpc =
+            DefaultATPC::new(vec![2, 6, 10, 14], 1, -100, -90, Duration::from_secs(3600));
+        atpc.register_neighbor(0x0042);
+        for (tpi, nonce) in [(0, 10), (1, 11), (2, 12), (3, 13)] {
+            atpc.register_beacon(tpi, nonce);
+        }
+        // Only the two highest levels were received: the model is valid, but unreliable.
+        atpc.report_beacon_acknowledgment(0x0042, 10, &[None, None, Some(-100), Some(-92)]);
+        let quality = atpc.get_fit_quality(0x0042).unwrap();
+        assert_eq!(quality.samples, 2);
+        assert!(!quality.is_reliable());
+        assert_eq!(atpc.get_tx_power(0x0042), 10);
+
+        // Unknown sweeps are ignored.
+        atpc.report_beacon_acknowledgment(0x0042, 99, &[Some(0), Some(0), Some(0), Some(0)]);
+        assert_eq!(atpc.get_fit_quality(0x0042), Some(quality));
+    }
+
+    #[test]
+    fn atpc_beacon_acknowledgment_mixed_paths() {
+        let mut atpc =
+            DefaultATPC::new(vec![2, 6, 10, 14], 1, -100, -90, Duration::from_secs(3600));
+        atpc.register_neighbor(0x0042);
+        for (tpi, nonce) in [(0, 10), (1, 11), (2, 12), (3, 13)] {
+            atpc.register_beacon(tpi, nonce);
+        }
+        // RSSI = 2 * TP - 120, the third beacon is missed by the sweep acknowledgment...
+        atpc.report_beacon_acknowledgment(0x0042, 10, &[Some(-116), Some(-108), None, Some(-92)]);
+        // ...but acknowledged on its own, received at -100dBm.
+        atpc.report_successful_reception(0x0042, 12, 10);
+        let neigh = atpc.neighbors.peek(&0x0042).unwrap();
+        assert_eq!(
+            neigh.rssi,
+            vec![Some(-116), Some(-108), Some(-100), Some(-92)]
+        );
+        let quality = atpc.get_fit_quality(0x0042).unwrap();
+        assert_eq!(quality.samples, 4);
+        assert_eq!(quality.rejected, 0);
+        assert!(quality.rmse < 1e-3);
+        assert_eq!(atpc.get_tx_power(0x0042), 10);
+    }
+
+    #[test]
+    fn atpc_invalid_model_uses_default_power() {
+        let mut atpc = DefaultATPC::new(vec![2, 6, 10], 1, -100, -90, Duration::from_secs(3600));
+        atpc.register_neighbor(0x0042);
+        // Still initializing.
+        assert_eq!(atpc.get_tx_power(0x0042), 6);
+        for (tpi, nonce) in [(0, 10), (1, 11), (2, 12)] {
+            atpc.register_beacon(tpi, nonce);
+        }
+        // A flat response does not allow to calculate a transmission power.
+        atpc.report_beacon_acknowledgment(0x0042, 10, &[Some(-90), Some(-90), Some(-90)]);
+        assert!(atpc.get_fit_quality(0x0042).is_none());
+        assert_eq!(atpc.get_tx_power(0x0042), 6);
+        assert!(atpc.is_probe_needed());
+    }
+
+    #[test]
+    fn atpc_min_tx_power_of_group() {
+        let mut atpc = runtime_atpc();
+        // 0x0042 needs 10dBm, 0x0043 (initializing) and 0x0044 (unknown) the default 6dBm.
+        assert_eq!(
+            atpc.get_min_tx_power(vec![0x0044, 0x0043, 0x0042]),
+            (10, vec![0x0042])
+        );
+        assert_eq!(
+            atpc.get_min_tx_power(vec![0x0044, 0x0043]),
+            (6, vec![0x0043, 0x0044])
+        );
+        assert_eq!(
+            atpc.get_group_tx_power(
+                vec![0x0044, 0x0043, 0x0042],
+                &GroupPolicy::DeliveryRatio(0.5)
+            ),
+            (6, vec![0x0043, 0x0044])
+        );
+    }
+
+    #[test]
+    fn atpc_link_down_after_failures() {
+        let mut atpc = runtime_atpc();
+        assert_eq!(atpc.get_tx_power(0x0042), 10);
+        for _ in 0..4 {
+            atpc.report_failed_reception(0x0042);
+        }
+        // An acknowledgment resets the count of missing acknowledgments.
+        atpc.report_successful_reception(0x0042, 99, 0);
+        for _ in 0..4 {
+            atpc.report_failed_reception(0x0042);
+        }
+        assert!(atpc.take_lost_links().is_empty());
+        atpc.report_failed_reception(0x0042);
+        // Never initialized, the other neighbor has no link to lose.
+        for _ in 0..5 {
+            atpc.report_failed_reception(0x0043);
+        }
+        assert_eq!(atpc.take_lost_links(), vec![0x0042]);
+        assert!(atpc.take_lost_links().is_empty());
+        let neigh = atpc.neighbors.peek(&0x0042).unwrap();
+        assert_eq!(neigh.status, NeighborStatus::Initializing);
+        assert!(neigh.rssi.iter().all(|r| r.is_none()));
+        assert_eq!(atpc.get_tx_power(0x0042), 6);
+        assert!(atpc.is_probe_needed());
+    }
+
+    #[test]
+    fn atpc_link_down_when_idle() {
+        let mut atpc = runtime_atpc();
+        atpc.set_aging(AgingPolicy {
+            max_idle: Duration::ZERO,
+            evict: true,
+            ..AgingPolicy::default()
+        });
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(atpc.take_lost_links(), vec![0x0042]);
+        assert!(!atpc.neighbors.contains(&0x0042));
+        assert!(atpc.neighbors.contains(&0x0043));
+    }
+
+    #[test]
+    fn atpc_probe_unresponsive_neighbor() {
+        let mut atpc = DefaultATPC::new(vec![2, 6, 10], 1, -100, -90, Duration::from_secs(3600));
+        assert!(!atpc.is_beacon_needed());
+        assert!(!atpc.is_probe_needed());
+        atpc.register_neighbor(0x0042);
+        // A new neighbor is probed right away, not by a whole sweep.
+        assert!(!atpc.is_beacon_needed());
+        assert!(atpc.is_probe_needed());
+        let last_beacon = atpc.last_beacon;
+        assert_eq!(atpc.next_probe(), Some(0x0042));
+        for (tpi, nonce) in [(0, 10), (1, 11), (2, 12)] {
+            atpc.register_beacon(tpi, nonce);
+        }
+        // The probe is not a sweep, and the unresponsive neighbor is backed off.
+        assert_eq!(atpc.last_beacon, last_beacon);
+        assert!(!atpc.is_probe_needed());
+        assert_eq!(atpc.next_probe(), None);
+        assert_eq!(atpc.scheduler.get_probe_attempts(0x0042), 1);
+
+        // The neighbor finally answers the probe.
+        atpc.report_beacon_acknowledgment(0x0042, 10, &[Some(-106), Some(-98), Some(-90)]);
+        assert_eq!(atpc.scheduler.get_probe_attempts(0x0042), 0);
+        assert!(!atpc.is_probe_needed());
+    }
+}
@@ -0,0 +1,215 @@
+//! Selection of the transmission power of the frames addressed to several neighbors.
+//!
+//! By default a group frame is transmitted at the highest transmission power needed by its
+//! recipients, so every one of them can receive it. A [GroupPolicy] allows to trade the delivery
+//! to the farthest recipients for energy, either by only targeting a fraction of the recipients,
+//! or by splitting the frame in several frames at different transmission powers.
+use crate::prelude::*;
+use crate::sys;
+use crate::LoRaAddress;
+
+/// Policy selecting the transmission power of a frame addressed to several neighbors.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum GroupPolicy {
+    /// Reach every recipient, with the highest of their transmission powers.
+    #[default]
+    All,
+    /// Reach at least this fraction (between 0 and 1) of the recipients, with the lowest
+    /// transmission power that allows it. The farthest recipients might miss the frame.
+    DeliveryRatio(f32),
+    /// Reach every recipient, but split the frame in two frames at different transmission powers
+    /// when it saves energy: the messages for the closest recipients are transmitted first, at a
+    /// lower transmission power, the others follow. A message for both close and far recipients
+    /// is transmitted in both frames.
+    Split,
+}
+
+impl GroupPolicy {
+    /// Selects the transmission power of a frame given the transmission power needed by each of
+    /// its recipients.
+    ///
+    /// Returns the transmission power and the (sorted) recipients needing exactly this
+    /// transmission power, the only ones whose missing acknowledgments should update the ATPC.
+    /// Returns `None` without recipients.
+    pub fn select_tx_power(
+        &self,
+        tx_powers: &[(LoRaAddress, i8)],
+    ) -> Option<(i8, Vec<LoRaAddress>)> {
+        if tx_powers.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<i8> = tx_powers.iter().map(|(_, tp)| *tp).collect();
+        sorted.sort();
+        let tx_power = match self {
+            GroupPolicy::All | GroupPolicy::Split => sorted[sorted.len() - 1],
+            GroupPolicy::DeliveryRatio(ratio) => {
+                let n = sorted.len();
+                let k = (sys::ceilf(ratio.clamp(0.0, 1.0) * n as f32) as usize).clamp(1, n);
+                sorted[k - 1]
+            }
+        };
+        let mut farthest: Vec<LoRaAddress> = tx_powers
+            .iter()
+            .filter(|(_, tp)| *tp == tx_power)
+            .map(|(na, _)| *na)
+            .collect();
+        farthest.sort();
+        Some((tx_power, farthest))
+    }
+}
+
+/// Plans the split of a transmission in two frames, see [GroupPolicy::Split].
+///
+/// `tx_powers` holds the transmission power needed by each recipient, `full_cost` is the energy
+/// of the transmission in a single frame, and `split_cost` gives the energy of the two frames when
+/// the recipients needing at most the given transmission power are served by the first frame
+/// (`None` if that split is not possible).
+///
+/// Returns the transmission power threshold of the cheapest split, `None` if no split saves
+/// energy.
+pub fn plan_split<F>(
+    tx_powers: &[(LoRaAddress, i8)],
+    full_cost: f64,
+    mut split_cost: F,
+) -> Option<i8>
+where
+    F: FnMut(i8) -> Option<f64>,
+{
+    let mut thresholds: Vec<i8> = tx_powers.iter().map(|(_, tp)| *tp).collect();
+    thresholds.sort();
+    thresholds.dedup();
+    // Splitting at the highest transmission power leaves the second frame empty.
+    thresholds.pop();
+    let mut best: Option<(i8, f64)> = None;
+    for threshold in thresholds {
+        if let Some(cost) = split_cost(threshold) {
+            if cost < full_cost && best.is_none_or(|(_, c)| cost < c) {
+                best = Some((threshold, cost));
+            }
+        }
+    }
+    best.map(|(threshold, _)| threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atpc::ATPC;
+    use crate::frame::FrameNonce;
+
+    use std::collections::HashMap;
+
+    /// ATPC with fixed transmission powers, relying on the default implementations of the trait.
+    struct FixedATPC(HashMap<LoRaAddress, i8>);
+
+    impl ATPC for FixedATPC {
+        fn is_beacon_needed(&self) -> bool {
+            false
+        }
+
+        fn get_beacon_powers(&self) -> Vec<i8> {
+            Vec::new()
+        }
+
+        fn register_beacon(&mut self, _tpi: usize, _nonce: FrameNonce) {}
+
+        fn register_neighbor(&mut self, _neighbor_addr: LoRaAddress) -> bool {
+            false
+        }
+
+        fn unregister_neighbor(&mut self, _neighbor_addr: LoRaAddress) -> bool {
+            false
+        }
+
+        fn get_tx_power(&mut self, neighbor_addr: LoRaAddress) -> i8 {
+            self.0[&neighbor_addr]
+        }
+
+        fn get_default_tx_power(&mut self) -> i8 {
+            0
+        }
+
+        fn report_successful_reception(
+            &mut self,
+            _neighbor_addr: LoRaAddress,
+            _nonce: FrameNonce,
+            _drssi: i16,
+        ) {
+        }
+
+        fn report_failed_reception(&mut self, _neighbor_addr: LoRaAddress) {}
+    }
+
+    fn fixed_atpc() -> FixedATPC {
+        FixedATPC(HashMap::from([
+            (0x0041, 2),
+            (0x0042, 14),
+            (0x0043, 5),
+            (0x0044, 14),
+            (0x0045, 8),
+        ]))
+    }
+
+    #[test]
+    fn group_policy_all_matches_default() {
+        let mut atpc = fixed_atpc();
+        let addrs = vec![0x0044, 0x0041, 0x0042, 0x0043, 0x0045];
+        let expected = atpc.get_min_tx_power(addrs.clone());
+        assert_eq!(expected, (14, vec![0x0042, 0x0044]));
+        assert_eq!(
+            atpc.get_group_tx_power(addrs.clone(), &GroupPolicy::All),
+            expected
+        );
+        assert_eq!(
+            atpc.get_group_tx_power(addrs.clone(), &GroupPolicy::DeliveryRatio(1.0)),
+            expected
+        );
+        assert_eq!(
+            atpc.get_group_tx_power(addrs, &GroupPolicy::Split),
+            expected
+        );
+    }
+
+    #[test]
+    fn group_policy_delivery_ratio() {
+        let mut atpc = fixed_atpc();
+        let addrs = vec![0x0041, 0x0042, 0x0043, 0x0044, 0x0045];
+        // 3 out of 5 recipients.
+        assert_eq!(
+            atpc.get_group_tx_power(addrs.clone(), &GroupPolicy::DeliveryRatio(0.6)),
+            (8, vec![0x0045])
+        );
+        // At least one recipient.
+        assert_eq!(
+            atpc.get_group_tx_power(addrs.clone(), &GroupPolicy::DeliveryRatio(0.0)),
+            (2, vec![0x0041])
+        );
+        // A single recipient is always reached.
+        assert_eq!(
+            atpc.get_group_tx_power(vec![0x0042], &GroupPolicy::DeliveryRatio(0.1)),
+            atpc.get_min_tx_power(vec![0x0042])
+        );
+        assert_eq!(GroupPolicy::DeliveryRatio(0.5).select_tx_power(&[]), None);
+    }
+
+    #[test]
+    fn group_plan_split() {
+        let tx_powers = [(0x0041, 2), (0x0042, 14), (0x0043, 5), (0x0044, 14)];
+        // Every recipient in one frame costs 100, splitting at 5dBm saves energy.
+        let split = plan_split(&tx_powers, 100.0, |threshold| match threshold {
+            2 => Some(90.0),
+            5 => Some(70.0),
+            _ => panic!("no split at the highest transmission power"),
+        });
+        assert_eq!(split, Some(5));
+        // Splits that cost more, or are impossible, are ignored.
+        let split = plan_split(&tx_powers, 60.0, |threshold| {
+            (threshold == 5).then_some(70.0)
+        });
+        assert_eq!(split, None);
+        assert_eq!(
+            plan_split(&[(0x0041, 2), (0x0042, 2)], 10.0, |_| Some(1.0)),
+            None
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! ATPC implementation based on a Kalman filter.
+//!
+//! Contrary to the [DefaultATPC](super::DefaultATPC) that builds its control model
+//! from beacons (and only shifts it on transmissions), this implementation estimates the path loss
+//! towards each neighbor with a one-dimensional Kalman filter fed by every acknowledgment.
+//!
+//! The path loss is modelled as a random walk: each new observation first increases the
+//! uncertainty of the estimate by the process noise (the fading of the link), then the estimate
+//! is corrected by the observation, weighted by the measurement noise (the noise of the RSSI).
+//!
+//! ## Usage
+//! ```rust,ignore
+//! let atpc = radio_tipe_poc::atpc::KalmanATPC::new(vec![2, 5, 8, 11, 14, 17], 3, -110, -100, Duration::from_secs(8 * 3600));
+//! let mut device = LoRaRadio::new(lora, &channels, atpc, -100, None, None, 0b0101_0011);
+//! ```
+use super::{BeaconScheduler, ATPC};
+use crate::frame::FrameNonce;
+use crate::prelude::*;
+use crate::sys::{self, Instant};
+use crate::LoRaAddress;
+
+use core::num::NonZeroUsize;
+use core::time::Duration;
+
+use lru::LruCache;
+
+/// Default process noise, the variance (in dB²) added to the path loss estimate at each update.
+const DEFAULT_PROCESS_NOISE: f32 = 1.0;
+/// Default measurement noise, the variance (in dB²) of a RSSI observation.
+const DEFAULT_MEASUREMENT_NOISE: f32 = 9.0;
+/// Default safety margin, in standard deviations of the path loss estimate.
+const DEFAULT_MARGIN: f32 = 1.0;
+/// Path loss increase (in dB) assumed on a missing acknowledgment.
+const FAILURE_STEP: f32 = 3.0;
+
+/// Path loss estimate of a neighbor for the [KalmanATPC].
+#[derive(Clone, Debug)]
+struct PathLossEstimate {
+    /// Estimated path loss (in dB), `None` until the first observation.
+    path_loss: Option<f32>,
+    /// Variance of the estimate (in dB²).
+    variance: f32,
+}
+
+impl PathLossEstimate {
+    /// Builds an empty estimate.
+    fn new() -> Self {
+        Self {
+            path_loss: None,
+            variance: 0.0,
+        }
+    }
+
+    /// Updates the estimate with an observed path loss.
+    fn observe(&mut self, path_loss: f32, process_noise: f32, measurement_noise: f32) {
+        match self.path_loss {
+            None => {
+                self.path_loss = Some(path_loss);
+                self.variance = measurement_noise;
+            }
+            Some(estimate) => {
+                let variance = self.variance + process_noise;
+                let gain = variance / (variance + measurement_noise);
+                self.path_loss = Some(estimate + gain * (path_loss - estimate));
+                self.variance = (1.0 - gain) * variance;
+            }
+        }
+    }
+
+    /// Updates the estimate following a missing acknowledgment.
+    ///
+    /// The link is assumed to have degraded: the path loss is increased and the estimate becomes
+    /// less certain.
+    fn degrade(&mut self, process_noise: f32) {
+        if let Some(estimate) = self.path_loss {
+            self.path_loss = Some(estimate + FAILURE_STEP);
+            self.variance += process_noise;
+        }
+    }
+}
+
+/// ATPC implementation tracking the path loss towards each neighbor with a Kalman filter.
+///
+/// Every acknowledgment is an observation of the path loss: the acknowledging peer reports the
+/// delta between its RSSI target and the RSSI of our frame, and the transmission power of the
+/// frame is known (see [register_transmission](ATPC::register_transmission)). Beacons are mostly
+/// needed to initialize the neighbors we never received an acknowledgment from, which are probed.
+///
+/// Note that the RSSI target of the peers is assumed to be the same than ours.
+pub struct KalmanATPC {
+    /// LRU Cache to remember the estimates associated with the most recent neighbors.
+    neighbors: LruCache<LoRaAddress, PathLossEstimate>,
+    /// The transmission powers usable by the ATPC (and the radio).
+    transmission_powers: Vec<i8>,
+    /// The default transmission power (the index of it in `transmission_powers`) that will
+    /// be use if a node is unknown or still initializing.
+    default_tp: u8,
+    /// The minimal RSSI threashold that the radio will consider acceptable.
+    lower_rssi: i16,
+    /// The RSSI target of the peers, used to recover the RSSI from the acknowledgments.
+    rssi_target: i16,
+    /// Variance (in dB²) added to the path loss estimates at each update.
+    process_noise: f32,
+    /// Variance (in dB²) of the RSSI observations.
+    measurement_noise: f32,
+    /// Safety margin on the path loss, in standard deviations of the estimate.
+    margin: f32,
+    /// Scheduler of the beacon sweeps and probes.
+    scheduler: BeaconScheduler,
+    /// The neighbor probed by the next beacons, if any.
+    probe: Option<LoRaAddress>,
+    /// The latest transmissions as a nonce-transmission power value.
+    transmissions: LruCache<FrameNonce, i8>,
+    /// The latest beacons transmitted as a nonce-transmission power level value.
+    beacons: LruCache<FrameNonce, u8>,
+}
+
+impl KalmanATPC {
+    /// Builds a new instance of the Kalman ATPC.
+    pub fn new(
+        transmission_powers: Vec<i8>,
+        default_tp: impl Into<u8>,
+        lower_rssi: i16,
+        rssi_target: i16,
+        beacon_delay: Duration,
+    ) -> Self {
+        let default_tp_ = default_tp.into();
+        let tp_len = transmission_powers.len();
+        assert!(default_tp_ < tp_len as u8);
+        Self {
+            neighbors: LruCache::new(NonZeroUsize::new(128).unwrap()),
+            transmission_powers,
+            default_tp: default_tp_,
+            lower_rssi,
+            rssi_target,
+            process_noise: DEFAULT_PROCESS_NOISE,
+            measurement_noise: DEFAULT_MEASUREMENT_NOISE,
+            margin: DEFAULT_MARGIN,
+            scheduler: BeaconScheduler::new(beacon_delay),
+            probe: None,
+            transmissions: LruCache::new(NonZeroUsize::new(60).unwrap()),
+            beacons: LruCache::new(NonZeroUsize::new(tp_len + 1).unwrap()),
+        }
+    }
+
+    /// Gets the scheduler of the beacons, to configure its jitter, backoff and cap.
+    pub fn scheduler_mut(&mut self) -> &mut BeaconScheduler {
+        &mut self.scheduler
+    }
+
+    /// Sets the noises of the filter (in dB²).
+    ///
+    /// A higher process noise adapts faster to fading, a higher measurement noise smooths
+    /// the RSSI noise.
+    pub fn set_noise(&mut self, process_noise: f32, measurement_noise: f32) {
+        assert!(process_noise >= 0.0 && measurement_noise > 0.0);
+        self.process_noise = process_noise;
+        self.measurement_noise = measurement_noise;
+    }
+
+    /// Sets the safety margin on the path loss, in standard deviations of the estimate.
+    pub fn set_margin(&mut self, margin: f32) {
+        self.margin = margin;
+    }
+
+    /// Gets the estimated path loss (in dB) towards a neighbor and its standard deviation.
+    ///
+    /// Returns `None` if the neighbor is unknown or was never observed.
+    pub fn get_path_loss(&self, neighbor_addr: LoRaAddress) -> Option<(f32, f32)> {
+        self.neighbors
+            .peek(&neighbor_addr)
+            .and_then(|neigh| neigh.path_loss.map(|pl| (pl, sys::sqrtf(neigh.variance))))
+    }
+
+    /// Feeds the filter of a neighbor with an observed RSSI at a transmission power.
+    fn observe(&mut self, neighbor_addr: LoRaAddress, tx_power: i8, rssi: i16) {
+        self.register_neighbor(neighbor_addr);
+        if let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) {
+            neigh.observe(
+                (tx_power as f32) - (rssi as f32),
+                self.process_noise,
+                self.measurement_noise,
+            );
+            self.scheduler.reset_probe(neighbor_addr);
+        }
+    }
+}
+
+impl ATPC for KalmanATPC {
+    fn is_beacon_needed(&self) -> bool {
+        self.scheduler.is_sweep_due(Instant::now())
+    }
+
+    fn is_probe_needed(&self) -> bool {
+        let now = Instant::now();
+        self.neighbors.iter().any(|(addr, neigh)| {
+            neigh.path_loss.is_none() && self.scheduler.is_probe_due(*addr, now)
+        })
+    }
+
+    fn next_probe(&mut self) -> Option<LoRaAddress> {
+        let now = Instant::now();
+        let addr = self
+            .neighbors
+            .iter()
+            .find(|(addr, neigh)| {
+                neigh.path_loss.is_none() && self.scheduler.is_probe_due(**addr, now)
+            })
+            .map(|(addr, _)| *addr)?;
+        self.scheduler.register_probe(addr, now);
+        self.probe = Some(addr);
+        Some(addr)
+    }
+
+    fn get_beacon_powers(&self) -> Vec<i8> {
+        self.transmission_powers.clone()
+    }
+
+    fn register_beacon(&mut self, tpi: usize, nonce: FrameNonce) {
+        // The first beacon of a sweep consumes the pending probe, if any.
+        if tpi == 0 && self.probe.take().is_none() {
+            self.scheduler.register_sweep(Instant::now());
+        }
+        self.beacons.push(nonce, tpi as u8);
+    }
+
+    fn register_transmission(
+        &mut self,
+        nonce: FrameNonce,
+        tx_power: i8,
+        _recipients: &[LoRaAddress],
+    ) {
+        self.transmissions.push(nonce, tx_power);
+    }
+
+    fn register_neighbor(&mut self, neighbor_addr: LoRaAddress) -> bool {
+        if self.neighbors.contains(&neighbor_addr) {
+            false
+        } else {
+            self.neighbors.push(neighbor_addr, PathLossEstimate::new());
+            true
+        }
+    }
+
+    fn unregister_neighbor(&mut self, neighbor_addr: LoRaAddress) -> bool {
+        self.scheduler.reset_probe(neighbor_addr);
+        self.neighbors.pop_entry(&neighbor_addr).is_some()
+    }
+
+    fn get_tx_power(&mut self, neighbor_addr: LoRaAddress) -> i8 {
+        let Some((path_loss, deviation)) = self.get_path_loss(neighbor_addr) else {
+            return self.transmission_powers[self.default_tp as usize];
+        };
+        let tp_target = (self.lower_rssi as f32) + path_loss + self.margin * deviation;
+        self.transmission_powers
+            .iter()
+            .find(|tp| (**tp as f32) >= tp_target)
+            .copied()
+            .unwrap_or(self.transmission_powers[self.transmission_powers.len() - 1])
+    }
+
+    fn get_default_tx_power(&mut self) -> i8 {
+        self.transmission_powers[self.default_tp as usize]
+    }
+
+    fn report_successful_reception(
+        &mut self,
+        neighbor_addr: LoRaAddress,
+        nonce: FrameNonce,
+        drssi: i16,
+    ) {
+        let tx_power = match self.transmissions.get(&nonce) {
+            Some(tp) => *tp,
+            None => match self.beacons.get(&nonce) {
+                Some(tpi) => self.transmission_powers[*tpi as usize],
+                None => return,
+            },
+        };
+        self.observe(neighbor_addr, tx_power, self.rssi_target - drssi);
+    }
+
+    fn report_failed_reception(&mut self, neighbor_addr: LoRaAddress) {
+        if let Some(neigh) = self.neighbors.get_mut(&neighbor_addr) {
+            neigh.degrade(self.process_noise);
+        }
+    }
+
+    fn report_beacon_acknowledgment(
+        &mut self,
+        neighbor_addr: LoRaAddress,
+        sweep: FrameNonce,
+        rssi: &[Option<i16>],
+    ) {
+        if self.beacons.get(&sweep) != Some(&0) || rssi.len() != self.transmission_powers.len() {
+            return;
+        }
+        for (tpi, r) in rssi.iter().enumerate() {
+            if let Some(r) = r {
+                self.observe(neighbor_addr, self.transmission_powers[tpi], *r);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kalman_atpc_converges() {
+        let mut atpc = KalmanATPC::new(vec![2, 5, 8, 11, 14, 17], 3, -110, -100, Duration::MAX);
+        atpc.register_neighbor(0x0042);
+        assert!(atpc.is_probe_needed());
+        assert_eq!(atpc.get_tx_power(0x0042), 11);
+        // Path loss of 120dB, frames transmitted at 14dBm are received at -106dBm.
+        for nonce in 0..20 {
+            atpc.register_transmission(nonce, 14, &[0x0042]);
+            let drssi = if nonce % 2 == 0 { 4 } else { 8 };
+            atpc.report_successful_reception(0x0042, nonce, drssi);
+        }
+        let (path_loss, deviation) = atpc.get_path_loss(0x0042).unwrap();
+        assert!((path_loss - 120.0).abs() < 1.0);
+        assert!(deviation < 3.0);
+        assert!(!atpc.is_probe_needed());
+        assert!(!atpc.is_beacon_needed());
+        assert_eq!(atpc.get_tx_power(0x0042), 14);
+
+        // Missing acknowledgments increase the transmission power.
+        atpc.report_failed_reception(0x0042);
+        assert_eq!(atpc.get_tx_power(0x0042), 17);
+    }
+
+    #[test]
+    fn kalman_atpc_ignores_unknown_transmissions() {
+        let mut atpc = KalmanATPC::new(vec![2, 5, 8], 1, -110, -100, Duration::MAX);
+        atpc.register_neighbor(0x0042);
+        atpc.report_successful_reception(0x0042, 1234, 0);
+        assert!(atpc.get_path_loss(0x0042).is_none());
+        atpc.register_beacon(0, 10);
+        atpc.register_beacon(1, 11);
+        atpc.register_beacon(2, 12);
+        atpc.report_beacon_acknowledgment(0x0042, 10, &[None, Some(-112), Some(-109)]);
+        let (path_loss, _) = atpc.get_path_loss(0x0042).unwrap();
+        assert!((path_loss - 117.0).abs() < 0.5);
+    }
+}
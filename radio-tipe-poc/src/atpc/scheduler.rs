@@ -0,0 +1,220 @@
+//! Scheduling of the beacons of an ATPC.
+//!
+//! Two kinds of beacon transmissions are scheduled:
+//! - periodic *sweeps*, broadcasted to every neighbor, every `beacon_delay` (with a random
+//!   jitter to avoid synchronized beacon storms across nodes);
+//! - *probes*, a sweep addressed to a single neighbor that needs to be (re)initialized. The
+//!   first probe is due immediately, then an unresponsive neighbor is probed again after an
+//!   exponential backoff.
+//!
+//! Both are capped to a maximal number of transmissions per window, in order to respect the
+//! duty cycle whatever the number of unresponsive neighbors.
+use crate::sys::{self, HashMap, Instant};
+use crate::LoRaAddress;
+
+use alloc::collections::VecDeque;
+use core::time::Duration;
+
+/// Default jitter, as a fraction of the delays.
+const DEFAULT_JITTER: f32 = 0.1;
+/// Default delay before probing again an unresponsive neighbor, doubled at each attempt.
+const DEFAULT_MIN_BACKOFF: Duration = Duration::from_secs(60);
+/// Default maximal number of sweeps and probes per window.
+const DEFAULT_MAX_SWEEPS: usize = 4;
+/// Default window of the cap on sweeps and probes.
+const DEFAULT_SWEEP_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Scheduler of the beacon sweeps and probes of an ATPC, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct BeaconScheduler {
+    /// Delay between two periodic sweeps.
+    beacon_delay: Duration,
+    /// Random jitter applied to the delays, as a fraction of them.
+    jitter: f32,
+    /// Delay before the second probe of an unresponsive neighbor.
+    min_backoff: Duration,
+    /// Maximal delay between two probes of an unresponsive neighbor.
+    max_backoff: Duration,
+    /// Maximal number of sweeps and probes per window.
+    max_sweeps: usize,
+    /// Window of the cap on sweeps and probes.
+    window: Duration,
+    /// Instant of the next periodic sweep, `None` if it is too far to be represented.
+    next_sweep: Option<Instant>,
+    /// Instants of the sweeps and probes in the current window.
+    recent: VecDeque<Instant>,
+    /// Probe attempts and instant of the next probe, by neighbor to probe.
+    probes: HashMap<LoRaAddress, (u32, Option<Instant>)>,
+}
+
+impl BeaconScheduler {
+    /// Builds a new scheduler with periodic sweeps every `beacon_delay`.
+    ///
+    /// The first periodic sweep happens after `beacon_delay`.
+    pub fn new(beacon_delay: Duration) -> Self {
+        let mut scheduler = Self {
+            beacon_delay,
+            jitter: DEFAULT_JITTER,
+            min_backoff: DEFAULT_MIN_BACKOFF,
+            max_backoff: beacon_delay.max(DEFAULT_MIN_BACKOFF),
+            max_sweeps: DEFAULT_MAX_SWEEPS,
+            window: DEFAULT_SWEEP_WINDOW,
+            next_sweep: None,
+            recent: VecDeque::new(),
+            probes: HashMap::new(),
+        };
+        scheduler.next_sweep = scheduler.after(Instant::now(), beacon_delay);
+        scheduler
+    }
+
+    /// Sets the random jitter applied to the delays, as a fraction of them (0 disables it).
+    pub fn set_jitter(&mut self, jitter: f32) {
+        assert!((0.0..1.0).contains(&jitter));
+        self.jitter = jitter;
+    }
+
+    /// Sets the delays between the probes of an unresponsive neighbor: the delay starts at
+    /// `min_backoff` and is doubled at each attempt, up to `max_backoff`.
+    pub fn set_backoff(&mut self, min_backoff: Duration, max_backoff: Duration) {
+        self.min_backoff = min_backoff;
+        self.max_backoff = max_backoff.max(min_backoff);
+    }
+
+    /// Sets the maximal number of sweeps and probes per window.
+    pub fn set_cap(&mut self, max_sweeps: usize, window: Duration) {
+        self.max_sweeps = max_sweeps;
+        self.window = window;
+    }
+
+    /// Gets the instant after a delay with the random jitter applied, `None` if it is too far
+    /// to be represented.
+    fn after(&self, now: Instant, delay: Duration) -> Option<Instant> {
+        let mut rand = [0u8; 2];
+        // Error silenced here! Without randomness, there is no jitter.
+        let _ = sys::fill_random(&mut rand);
+        let u = (u16::from_be_bytes(rand) as f64) / (u16::MAX as f64) * 2.0 - 1.0;
+        let factor = (1.0 + (self.jitter as f64) * u).max(0.0);
+        Duration::try_from_secs_f64(delay.as_secs_f64() * factor)
+            .ok()
+            .and_then(|delay| now.checked_add(delay))
+    }
+
+    /// Is the cap on sweeps and probes reached?
+    fn is_capped(&self, now: Instant) -> bool {
+        self.recent
+            .iter()
+            .filter(|i| now.saturating_duration_since(**i) < self.window)
+            .count()
+            >= self.max_sweeps
+    }
+
+    /// Records a sweep or a probe in the current window.
+    fn record(&mut self, now: Instant) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|i| now.saturating_duration_since(*i) >= self.window)
+        {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(now);
+    }
+
+    /// Is a periodic sweep due?
+    pub fn is_sweep_due(&self, now: Instant) -> bool {
+        self.next_sweep.is_some_and(|next| now >= next) && !self.is_capped(now)
+    }
+
+    /// Registers a periodic sweep, and schedules the next one.
+    pub fn register_sweep(&mut self, now: Instant) {
+        self.record(now);
+        self.next_sweep = self.after(now, self.beacon_delay);
+    }
+
+    /// Schedules the next periodic sweep after a sweep that happened before the scheduler
+    /// existed (for instance before a reboot).
+    pub fn resume(&mut self, last_sweep: Instant) {
+        self.next_sweep = self.after(last_sweep, self.beacon_delay);
+    }
+
+    /// Is a probe of this neighbor due (assuming it needs to be probed)?
+    ///
+    /// The first probe of a neighbor is due immediately.
+    pub fn is_probe_due(&self, neighbor_addr: LoRaAddress, now: Instant) -> bool {
+        self.probes
+            .get(&neighbor_addr)
+            .is_none_or(|(_, next)| next.is_some_and(|next| now >= next))
+            && !self.is_capped(now)
+    }
+
+    /// Registers a probe of a neighbor, and schedules the next one with an exponential backoff.
+    pub fn register_probe(&mut self, neighbor_addr: LoRaAddress, now: Instant) {
+        self.record(now);
+        let attempts = self.probes.get(&neighbor_addr).map_or(0, |(a, _)| *a);
+        let backoff = self
+            .min_backoff
+            .saturating_mul(1 << attempts.min(16))
+            .min(self.max_backoff);
+        let next = self.after(now, backoff);
+        self.probes.insert(neighbor_addr, (attempts + 1, next));
+    }
+
+    /// Resets the backoff of a neighbor, for instance once it answered.
+    pub fn reset_probe(&mut self, neighbor_addr: LoRaAddress) {
+        self.probes.remove(&neighbor_addr);
+    }
+
+    /// Gets the number of probes transmitted to a neighbor since its last reset.
+    pub fn get_probe_attempts(&self, neighbor_addr: LoRaAddress) -> u32 {
+        self.probes.get(&neighbor_addr).map_or(0, |(a, _)| *a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduler_sweeps_and_cap() {
+        let mut scheduler = BeaconScheduler::new(Duration::from_secs(100));
+        scheduler.set_jitter(0.0);
+        scheduler.set_cap(2, Duration::from_secs(1000));
+        let t0 = Instant::now();
+        scheduler.resume(t0);
+        assert!(!scheduler.is_sweep_due(t0));
+        let t1 = t0 + Duration::from_secs(101);
+        assert!(scheduler.is_sweep_due(t1));
+        scheduler.register_sweep(t1);
+        assert!(!scheduler.is_sweep_due(t1 + Duration::from_secs(50)));
+        let t2 = t1 + Duration::from_secs(100);
+        assert!(scheduler.is_sweep_due(t2));
+        scheduler.register_sweep(t2);
+        // Capped until the first sweep leaves the window.
+        assert!(!scheduler.is_sweep_due(t2 + Duration::from_secs(100)));
+        assert!(scheduler.is_sweep_due(t1 + Duration::from_secs(1000)));
+    }
+
+    #[test]
+    fn scheduler_probe_backoff() {
+        let mut scheduler = BeaconScheduler::new(Duration::from_secs(3600));
+        scheduler.set_jitter(0.0);
+        scheduler.set_backoff(Duration::from_secs(10), Duration::from_secs(30));
+        let t0 = Instant::now();
+        assert!(scheduler.is_probe_due(0x0042, t0));
+        scheduler.register_probe(0x0042, t0);
+        assert!(!scheduler.is_probe_due(0x0042, t0 + Duration::from_secs(9)));
+        let t1 = t0 + Duration::from_secs(10);
+        assert!(scheduler.is_probe_due(0x0042, t1));
+        scheduler.register_probe(0x0042, t1);
+        assert!(!scheduler.is_probe_due(0x0042, t1 + Duration::from_secs(19)));
+        let t2 = t1 + Duration::from_secs(20);
+        assert!(scheduler.is_probe_due(0x0042, t2));
+        scheduler.register_probe(0x0042, t2);
+        // Backoff capped at 30s.
+        assert!(scheduler.is_probe_due(0x0042, t2 + Duration::from_secs(30)));
+        assert_eq!(scheduler.get_probe_attempts(0x0042), 3);
+        scheduler.reset_probe(0x0042);
+        assert_eq!(scheduler.get_probe_attempts(0x0042), 0);
+        assert!(scheduler.is_probe_due(0x0042, t2));
+    }
+}
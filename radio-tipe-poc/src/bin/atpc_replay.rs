@@ -0,0 +1,143 @@
+//! Replays a transmission trace through the ATPC implementations, see [radio_tipe_poc::trace].
+//!
+//! ```text
+//! cargo run --bin atpc_replay -- capture.log [--powers 2,5,8,11,14,17] [--sensitivity -120] [--rssi-target -100]
+//! ```
+use radio_tipe_poc::atpc::{DefaultATPC, KalmanATPC, TestingATPC};
+use radio_tipe_poc::trace::{baseline, read_csv, replay, ReplayConfig, ReplayReport};
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Default transmission powers of the ATPCs (dBm), the ones of the RFM95W.
+const DEFAULT_TRANSMISSION_POWERS: [i8; 6] = [2, 5, 8, 11, 14, 17];
+/// Margin (dB) above the sensitivity considered acceptable by the ATPCs.
+const LOWER_RSSI_MARGIN: i16 = 5;
+/// Delay between the beacon sweeps of the ATPCs.
+const BEACON_DELAY: Duration = Duration::from_secs(8 * 3600);
+
+const USAGE: &str = "Usage: atpc_replay <trace> [--powers 2,5,8,11,14,17] [--sensitivity -120] [--rssi-target -100]";
+
+/// Parses the command line: the trace path, the transmission powers and the replay parameters.
+fn parse_args() -> Result<(String, Vec<i8>, ReplayConfig), String> {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    let mut powers = DEFAULT_TRANSMISSION_POWERS.to_vec();
+    let mut config = ReplayConfig::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--powers" => {
+                powers = value()?
+                    .split(',')
+                    .map(|tp| tp.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "Invalid transmission powers".to_owned())?;
+                powers.sort();
+                powers.dedup();
+            }
+            "--sensitivity" => {
+                config.sensitivity = value()?
+                    .parse()
+                    .map_err(|_| "Invalid sensitivity".to_owned())?;
+            }
+            "--rssi-target" => {
+                config.rssi_target = value()?
+                    .parse()
+                    .map_err(|_| "Invalid RSSI target".to_owned())?;
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+    if powers.is_empty() {
+        return Err("No transmission power".to_owned());
+    }
+    Ok((path.ok_or("Missing trace")?, powers, config))
+}
+
+fn print_report(name: &str, report: &ReplayReport) {
+    let powers: Vec<String> = report
+        .tx_powers
+        .iter()
+        .map(|(tp, n)| format!("{}dBm:{}", tp, n))
+        .collect();
+    println!(
+        "{:<10} {:>9.1}% {:>8} {:>12.1} {:>16.2}  {}",
+        name,
+        100.0 * report.delivery_ratio(),
+        report.beacons,
+        report.energy_mj,
+        report.energy_mj / (report.delivered.max(1) as f64),
+        powers.join(" "),
+    );
+}
+
+fn main() -> ExitCode {
+    let (path, powers, config) = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let trace = match File::open(&path)
+        .map_err(|err| err.into())
+        .and_then(|file| read_csv(BufReader::new(file)))
+    {
+        Ok(trace) => trace,
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+    if trace.is_empty() {
+        eprintln!("No record in {}.", path);
+        return ExitCode::FAILURE;
+    }
+
+    let default_tp = (powers.len() / 2) as u8;
+    let lower_rssi = config.sensitivity + LOWER_RSSI_MARGIN;
+    println!(
+        "{} records, {:.1}s",
+        trace.len(),
+        (trace[trace.len() - 1].time_ms - trace[0].time_ms) as f64 / 1000.0
+    );
+    println!("ATPC         Delivery  Beacons  Energy (mJ)  Energy/msg (mJ)  Powers");
+    print_report("Recorded", &baseline(&trace, &config));
+    print_report(
+        "Testing",
+        &replay(&mut TestingATPC::new(powers.clone()), &trace, &config),
+    );
+    print_report(
+        "Default",
+        &replay(
+            &mut DefaultATPC::new(
+                powers.clone(),
+                default_tp,
+                lower_rssi,
+                config.rssi_target,
+                BEACON_DELAY,
+            ),
+            &trace,
+            &config,
+        ),
+    );
+    print_report(
+        "Kalman",
+        &replay(
+            &mut KalmanATPC::new(
+                powers,
+                default_tp,
+                lower_rssi,
+                config.rssi_target,
+                BEACON_DELAY,
+            ),
+            &trace,
+            &config,
+        ),
+    );
+    ExitCode::SUCCESS
+}
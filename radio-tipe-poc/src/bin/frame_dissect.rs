@@ -0,0 +1,621 @@
+//! Decodes captured LoRa packets, see [radio_tipe_poc::frame].
+//!
+//! ```text
+//! cargo run --bin frame_dissect -- capture.log
+//! cargo run --bin frame_dissect -- --binary lead.bin fragment.bin
+//! ```
+//!
+//! Hex dumps hold a packet per line, as printed by `esp32-lora-tests` (`[01, 0A, FF]`) or as bare
+//! hex bytes, the other lines are ignored. Binary captures hold a packet per file. `-` reads the
+//! standard input.
+//!
+//! Every packet starts with its physical header (frame type), the fragments
+//! following a lead packet are reassembled as the radio does. The decoding errors are reported
+//! with the packet and the byte where they were detected.
+use radio_tipe_poc::compression::CompressionError;
+use radio_tipe_poc::frame::{
+    AddressHeader, BeaconAcknowledgment, BeaconPayload, FrameError, FrameNonce, FrameType,
+    PayloadFlag, RadioFrameView, RadioHeadersView, RecipientsView, COMPACT_VERSION,
+};
+
+use std::io::Read;
+use std::process::ExitCode;
+
+/// Maximum number of physical frames of a frame, as accepted by the radio.
+const MAX_FRAGMENTS: u8 = 5;
+
+const USAGE: &str = "Usage: frame_dissect [--binary] <capture>... (- for the standard input)";
+
+/// Appends a line to the output of a dissection.
+macro_rules! emit {
+    ($out:expr, $($arg:tt)*) => {{
+        $out.push_str(&format!($($arg)*));
+        $out.push('\n');
+    }};
+}
+
+/// A captured physical frame.
+struct Packet {
+    /// Origin of the packet: file and line or file.
+    source: String,
+    bytes: Vec<u8>,
+}
+
+/// Parses the command line: are the captures binary, and their paths.
+fn parse_args() -> Result<(bool, Vec<String>), String> {
+    let mut binary = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--binary" => binary = true,
+            _ if arg == "-" || !arg.starts_with("--") => paths.push(arg),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+    if paths.is_empty() {
+        return Err("Missing capture".to_owned());
+    }
+    Ok((binary, paths))
+}
+
+fn read_capture(path: &str) -> std::io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        std::fs::read(path)
+    }
+}
+
+/// Parses a line of hex dump, `None` if the line is not one (e.g. a log message).
+fn parse_hex_line(line: &str) -> Option<Vec<u8>> {
+    let dump = match (line.find('['), line.rfind(']')) {
+        (Some(start), Some(end)) if start < end => &line[(start + 1)..end],
+        _ => line,
+    };
+    let mut bytes = Vec::new();
+    for token in dump
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+    {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if token.is_empty() || !token.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        if token.len() == 1 {
+            bytes.push(u8::from_str_radix(token, 16).ok()?);
+            continue;
+        }
+        if token.len() % 2 != 0 {
+            return None;
+        }
+        for i in (0..token.len()).step_by(2) {
+            bytes.push(u8::from_str_radix(&token[i..(i + 2)], 16).ok()?);
+        }
+    }
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+/// Splits a capture into its packets: the whole capture if it is binary, else each line of hex
+/// dump.
+fn parse_capture(path: &str, capture: &[u8], binary: bool) -> Vec<Packet> {
+    if binary {
+        return vec![Packet {
+            source: path.to_owned(),
+            bytes: capture.to_vec(),
+        }];
+    }
+    String::from_utf8_lossy(capture)
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            parse_hex_line(line).map(|bytes| Packet {
+                source: format!("{}:{}", path, i + 1),
+                bytes,
+            })
+        })
+        .collect()
+}
+
+/// Formats a unix timestamp (s) as an UTC date.
+fn format_utc(secs: u64) -> String {
+    // Civil calendar from the days since 1970-01-01, after H. Hinnant's `civil_from_days`.
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    let time = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Formats a nonce: its timestamp (the high 48 bits) and random part.
+fn format_nonce(nonce: FrameNonce) -> String {
+    format!(
+        "{:#018x} ({}, random {:#06x})",
+        nonce,
+        format_utc(nonce >> 16),
+        nonce & 0xFFFF
+    )
+}
+
+fn format_address(ah: &AddressHeader) -> String {
+    let address = if ah.is_global() {
+        "global".to_owned()
+    } else if let Some(group) = ah.get_group() {
+        format!("group {}", group)
+    } else {
+        format!("{:#06x}", ah.get_address())
+    };
+    if ah.get_acknowledgment() {
+        format!("{} (ack)", address)
+    } else {
+        address
+    }
+}
+
+fn format_payload_flag(pl: PayloadFlag) -> String {
+    format!("payloads {:?}", pl.to_message_ids())
+}
+
+/// Formats a payload as text if it is printable, as hex bytes otherwise.
+fn format_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(|c| c.is_control() && c != '\n') => {
+            format!("{:?}", text)
+        }
+        _ => format!("{:02X?}", bytes),
+    }
+}
+
+/// Locates the byte `offset` of a (reassembled) frame in its packets.
+///
+/// The first byte of each packet is its physical header, which is not part of the frame.
+fn locate(fragments: &[&Packet], offset: usize) -> String {
+    let mut offset = offset;
+    for packet in fragments {
+        let len = packet.bytes.len().saturating_sub(1);
+        if offset < len {
+            return format!("{}, byte {}", packet.source, offset + 1);
+        }
+        offset -= len;
+    }
+    // Missing bytes are reported right after the end of the frame.
+    let last = fragments[fragments.len() - 1];
+    format!("{}, byte {} (end of packet)", last.source, last.bytes.len())
+}
+
+/// Formats a frame error, located in the packets of the frame.
+///
+/// `base` is the position, in the frame, of the component which was being parsed.
+fn format_error(fragments: &[&Packet], base: usize, err: &FrameError) -> String {
+    let offset = match err {
+        FrameError::InvalidPayload {
+            source:
+                CompressionError::InvalidReference { position, .. }
+                | CompressionError::Truncated { position },
+            ..
+        } => Some(*position),
+        err => err.offset(),
+    };
+    match offset {
+        Some(offset) => format!("{}: {}", locate(fragments, base + offset), err),
+        None => err.to_string(),
+    }
+}
+
+/// Gets the position of `part` in `whole`, `part` must be borrowed from `whole`.
+fn position_in(whole: &[u8], part: &[u8]) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+/// Decodes a beacon acknowledgment, a single packet.
+fn dissect_beacon_acknowledgment(packet: &Packet, out: &mut String) -> Result<(), String> {
+    let (ack, _read) = BeaconAcknowledgment::try_from_bytes(&packet.bytes[1..])
+        .map_err(|err| format_error(&[packet], 0, &err))?;
+    emit!(out, "  Sender           {}", format_address(&ack.sender));
+    emit!(out, "  Recipient        {}", format_address(&ack.recipient));
+    emit!(out, "  Sweep            {}", format_nonce(ack.sweep));
+    for (tpi, rssi) in ack.rssi.iter().enumerate() {
+        match rssi {
+            Some(rssi) => emit!(out, "    level {:<2}       {} dBm", tpi, rssi),
+            None => emit!(out, "    level {:<2}       missed", tpi),
+        }
+    }
+    Ok(())
+}
+
+/// Takes the fragments following a lead packet, as announced by its headers, and reassembles
+/// the frame (without the physical headers).
+fn reassemble(
+    lead: &Packet,
+    packets: &mut impl Iterator<Item = Packet>,
+) -> Result<(Vec<Packet>, Vec<u8>), String> {
+    let (headers, _read) = RadioHeadersView::try_from_bytes(&lead.bytes[1..])
+        .map_err(|err| format_error(&[lead], 0, &err))?;
+    let nframes = headers.rec_n_frames.get_frames();
+    if nframes == 0 || nframes > MAX_FRAGMENTS {
+        return Err(format!(
+            "{}: Invalid number of physical frames {} (1 to {}).",
+            locate(&[lead], 1),
+            nframes,
+            MAX_FRAGMENTS
+        ));
+    }
+    let following: Vec<Packet> = packets.take(nframes as usize - 1).collect();
+    if following.len() + 1 < nframes as usize {
+        return Err(format!(
+            "Missing fragments: {} physical frames announced, {} captured.",
+            nframes,
+            following.len() + 1
+        ));
+    }
+    let msg: Vec<u8> = std::iter::once(lead)
+        .chain(following.iter())
+        .flat_map(|packet| packet.bytes.iter().skip(1).copied())
+        .collect();
+    Ok((following, msg))
+}
+
+/// Decodes a message or beacon frame, reassembled from the lead packet and its fragments.
+fn dissect_frame(
+    frame_type: FrameType,
+    lead: &Packet,
+    packets: &mut impl Iterator<Item = Packet>,
+    out: &mut String,
+) -> Result<(), String> {
+    let (following, msg) = reassemble(lead, packets)?;
+    let fragments: Vec<&Packet> = std::iter::once(lead).chain(following.iter()).collect();
+    let nframes = fragments.len();
+    if nframes > 1 {
+        let sources: Vec<&str> = following.iter().map(|p| p.source.as_str()).collect();
+        emit!(out, "  Fragments        {}", sources.join(", "));
+    }
+
+    let (frame, read) =
+        RadioFrameView::try_from_bytes(&msg).map_err(|err| format_error(&fragments, 0, &err))?;
+    let headers = &frame.headers;
+    emit!(
+        out,
+        "  Version          {} ({} encoding{})",
+        headers.version,
+        if headers.version == COMPACT_VERSION {
+            "compact"
+        } else {
+            "standard"
+        },
+        if headers.compression {
+            ", compression"
+        } else {
+            ""
+        }
+    );
+    emit!(
+        out,
+        "  Info header      {} recipients, {} frames",
+        headers.rec_n_frames.get_recipients(),
+        nframes
+    );
+    if nframes > 1 {
+        emit!(out, "  Data rate        {}", headers.data_rate());
+    }
+    emit!(
+        out,
+        "  Sender           {}",
+        format_address(&headers.sender)
+    );
+    emit!(out, "  Nonce            {}", format_nonce(headers.nonce));
+    match &headers.recipients {
+        RecipientsView::Direct(ah) => emit!(out, "  Recipient        {}", format_address(ah)),
+        RecipientsView::Group(ahs) => {
+            emit!(out, "  Recipients");
+            for (ah, pl) in ahs.clone() {
+                emit!(
+                    out,
+                    "    {:<22} {}",
+                    format_address(&ah),
+                    format_payload_flag(pl)
+                );
+            }
+        }
+    }
+    if headers.extensions.clone().next().is_some() {
+        emit!(out, "  Extensions");
+        for (kind, value) in headers.extensions.clone() {
+            emit!(out, "    kind {:<3}       {:02X?}", kind, value);
+        }
+    }
+    let acknowledgments = frame.acknowledgments();
+    if acknowledgments.len() > 0 {
+        emit!(out, "  Acknowledgments");
+        for (ah, nonce, drssi) in acknowledgments {
+            emit!(
+                out,
+                "    {:<14} drssi {:>4} dB, nonce {}",
+                format_address(&ah),
+                drssi,
+                format_nonce(nonce)
+            );
+        }
+    }
+    emit!(out, "  Payloads         {}", headers.payloads);
+    for (id, payload) in frame.payloads().enumerate() {
+        let id = id as u8;
+        let base = position_in(&msg, payload);
+        let compressed = frame.compressed().contains(id);
+        let content = match frame.decompressed_payload(id) {
+            Some(Ok(content)) => content,
+            Some(Err(err)) => return Err(format_error(&fragments, base, &err)),
+            None => continue,
+        };
+        if compressed {
+            emit!(
+                out,
+                "    #{:<2} {} bytes, compressed from {} bytes",
+                id,
+                payload.len(),
+                content.len()
+            );
+        } else {
+            emit!(out, "    #{:<2} {} bytes", id, payload.len());
+        }
+        if frame_type == FrameType::BroadcastCheckSignal && id == 0 {
+            let (beacon, _read) = BeaconPayload::try_from_bytes(&content)
+                .map_err(|err| format_error(&fragments, base, &err))?;
+            emit!(
+                out,
+                "        beacon level {} of {}, sweep {}",
+                beacon.tpi,
+                beacon.levels,
+                format_nonce(beacon.sweep)
+            );
+        } else {
+            emit!(out, "        {}", format_bytes(&content));
+        }
+    }
+    if read < msg.len() {
+        emit!(
+            out,
+            "  Trailing bytes   {} from {}",
+            msg.len() - read,
+            locate(&fragments, read)
+        );
+    }
+    Ok(())
+}
+
+/// Decodes a packet, and the following fragments of its frame.
+fn dissect(
+    lead: &Packet,
+    packets: &mut impl Iterator<Item = Packet>,
+    out: &mut String,
+) -> Result<(), String> {
+    let Some(&physical_header) = lead.bytes.first() else {
+        return Err(format!("{}: Empty packet.", lead.source));
+    };
+    let frame_type = FrameType::try_from(physical_header).map_err(|_| {
+        format!(
+            "{}, byte 0: Unknown frame type {}.",
+            lead.source, physical_header
+        )
+    })?;
+    emit!(out, "{}: {:?}", lead.source, frame_type);
+    match frame_type {
+        FrameType::BeaconAcknowledgment => dissect_beacon_acknowledgment(lead, out),
+        FrameType::Message | FrameType::BroadcastCheckSignal => {
+            dissect_frame(frame_type, lead, packets, out)
+        }
+    }
+}
+
+/// Decodes the captured packets, returns the output and the number of frames which could not
+/// be decoded.
+fn dissect_all(packets: Vec<Packet>) -> (String, usize) {
+    let mut out = String::new();
+    let mut failures = 0;
+    let mut packets = packets.into_iter();
+    while let Some(lead) = packets.next() {
+        if let Err(err) = dissect(&lead, &mut packets, &mut out) {
+            emit!(out, "  Error: {}", err);
+            failures += 1;
+        }
+        emit!(out, "");
+    }
+    (out, failures)
+}
+
+fn main() -> ExitCode {
+    let (binary, paths) = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut packets = Vec::new();
+    for path in paths {
+        let capture = match read_capture(&path) {
+            Ok(capture) => capture,
+            Err(err) => {
+                eprintln!("Failed to read {}: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        };
+        packets.append(&mut parse_capture(&path, &capture, binary));
+    }
+    if packets.is_empty() {
+        eprintln!("No packet in the captures.");
+        return ExitCode::FAILURE;
+    }
+
+    let (out, failures) = dissect_all(packets);
+    print!("{}", out);
+    if failures > 0 {
+        eprintln!("{} frames could not be decoded.", failures);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use radio_tipe_poc::frame::{InfoHeader, RadioFrameWithHeaders, RadioHeaders, RecipientHeader};
+
+    /// Payload of a physical frame, without its physical header, as split by the radio.
+    const PHYSICAL_PAYLOAD: usize = 253;
+
+    /// Formats a packet as logged by `esp32-lora-tests`.
+    fn esp32_line(bytes: &[u8]) -> String {
+        format!("I (4242) esp32_lora_tests: Received {:02X?}", bytes)
+    }
+
+    /// Splits a frame in packets, each one starting with the physical header.
+    fn packets_of(frame: &RadioFrameWithHeaders) -> Vec<Vec<u8>> {
+        frame
+            .to_bytes()
+            .chunks(PHYSICAL_PAYLOAD)
+            .map(|chunk| [&[FrameType::Message as u8][..], chunk].concat())
+            .collect()
+    }
+
+    fn frame(payload: Vec<u8>) -> RadioFrameWithHeaders {
+        let mut frame = RadioFrameWithHeaders {
+            headers: RadioHeaders {
+                version: radio_tipe_poc::frame::PROTOCOL_VERSION,
+                rec_n_frames: InfoHeader::new(1, 1),
+                recipients: RecipientHeader::Direct(AddressHeader::new(0x0002, true)),
+                payloads: 1,
+                sender: AddressHeader::new(0x0001, false),
+                nonce: (1_792_000_000 << 16) | 0xbeef,
+                compression: false,
+                extensions: Vec::new(),
+            },
+            acknowledgments: Vec::new(),
+            payloads: vec![payload],
+            compressed: PayloadFlag::default(),
+        };
+        let frames = frame.to_bytes().len().div_ceil(PHYSICAL_PAYLOAD) as u8;
+        frame.headers.rec_n_frames.set_frames(frames);
+        frame
+    }
+
+    #[test]
+    fn dissect_parse_hex_line() {
+        assert_eq!(
+            parse_hex_line(&esp32_line(&[0x00, 0x1A, 0xFF])),
+            Some(vec![0x00, 0x1A, 0xFF])
+        );
+        assert_eq!(parse_hex_line("00 1a ff"), Some(vec![0x00, 0x1A, 0xFF]));
+        assert_eq!(parse_hex_line("001AFF"), Some(vec![0x00, 0x1A, 0xFF]));
+        assert_eq!(
+            parse_hex_line("0x00, 0x1a, f"),
+            Some(vec![0x00, 0x1A, 0x0F])
+        );
+        // Log lines are ignored.
+        assert_eq!(
+            parse_hex_line("I (31) boot: ESP-IDF v4.4 2nd stage bootloader"),
+            None
+        );
+        assert_eq!(parse_hex_line("[INFO] Listening..."), None);
+        assert_eq!(parse_hex_line(""), None);
+        assert_eq!(parse_hex_line("001"), None);
+    }
+
+    #[test]
+    fn dissect_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_utc(951_782_400 + 3723), "2000-02-29 01:02:03 UTC");
+        assert_eq!(format_utc(1_792_000_000), "2026-10-14 17:46:40 UTC");
+    }
+
+    #[test]
+    fn dissect_fragmented_frame() {
+        let payload = vec![0xAB; 400];
+        let packets = packets_of(&frame(payload.clone()));
+        assert_eq!(packets.len(), 2);
+        let capture = [
+            "I (31) boot: ESP-IDF v4.4".to_owned(),
+            esp32_line(&packets[0]),
+            packets[1]
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ]
+        .join("\n");
+        let packets = parse_capture("capture.log", capture.as_bytes(), false);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].source, "capture.log:2");
+
+        let mut fragments = packets.into_iter();
+        let lead = fragments.next().unwrap();
+        let (following, msg) = reassemble(&lead, &mut fragments).unwrap();
+        assert_eq!(following.len(), 1);
+        assert_eq!(msg, frame(payload.clone()).to_bytes());
+
+        let packets = parse_capture("capture.log", capture.as_bytes(), false);
+        let (out, failures) = dissect_all(packets);
+        assert_eq!(failures, 0, "{}", out);
+        assert!(out.starts_with("capture.log:2: Message\n"), "{}", out);
+        assert!(
+            out.contains("  Fragments        capture.log:3\n"),
+            "{}",
+            out
+        );
+        assert!(out.contains("  Recipient        0x0002 (ack)\n"), "{}", out);
+        assert!(
+            out.contains("2026-10-14 17:46:40 UTC, random 0xbeef"),
+            "{}",
+            out
+        );
+        assert!(out.contains(&format!("    #0  400 bytes\n        {:02X?}", payload)));
+
+        // A missing fragment is reported.
+        let packets = parse_capture("capture.log", capture.as_bytes(), false)
+            .into_iter()
+            .take(1)
+            .collect();
+        let (out, failures) = dissect_all(packets);
+        assert_eq!(failures, 1);
+        assert!(out.contains("Missing fragments: 2 physical frames announced, 1 captured."));
+    }
+
+    #[test]
+    fn dissect_malformed_packet() {
+        let mut packet = packets_of(&frame(b"HELO".to_vec())).remove(0);
+        // The packet ends in the payload, which starts at the byte 20 of the packet (after the
+        // physical header, 16 bytes of headers, the number of acknowledgments and the length of
+        // the payload).
+        packet.truncate(22);
+        let (out, failures) = dissect_all(vec![Packet {
+            source: "capture.log:7".to_owned(),
+            bytes: packet,
+        }]);
+        assert_eq!(failures, 1);
+        assert!(out.contains("  Error: capture.log:7, byte 20: "), "{}", out);
+
+        let (out, failures) = dissect_all(vec![Packet {
+            source: "capture.log:8".to_owned(),
+            bytes: vec![0x0F, 0x10],
+        }]);
+        assert_eq!(failures, 1);
+        assert!(out.contains("  Error: capture.log:8, byte 0: Unknown frame type 15."));
+    }
+}
@@ -0,0 +1,340 @@
+//! Capture of the physical frames sent and received by the radio, e.g. for Wireshark.
+//!
+//! A [CaptureSink] set on the [LoRaRadio](crate::radio::LoRaRadio) receives every physical frame
+//! (fragments of frames, beacons and beacon acknowledgments) with its radio metadata, including
+//! the frames that the radio ignores or fails to decode.
+//!
+//! With the `std` feature, [PcapngWriter] writes them into a pcapng file using the LoRaTap link
+//! header (link type 270). The TIPE frames themselves are decoded in Wireshark by the Lua
+//! dissector in `wireshark/tipe.lua`, which also describes their format.
+//!
+//! ## Usage
+//! ```rust,ignore
+//! let file = std::fs::File::create("capture.pcapng")?;
+//! let channels = vec![vec![LoRaTapChannel::new(868_100_000, 125_000, 9)]];
+//! device.set_capture_sink(Some(Box::new(PcapngWriter::new(file, channels)?)));
+//! ```
+#[cfg(feature = "std")]
+use crate::prelude::*;
+use crate::sys;
+
+use core::time::Duration;
+
+/// Direction of a captured physical frame.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CaptureDirection {
+    /// The frame was received by the radio.
+    Received,
+    /// The frame was transmitted by the radio.
+    Transmitted,
+}
+
+/// A physical frame sent or received by the radio, with its radio metadata.
+#[derive(Clone, Debug)]
+pub struct CapturedFrame<'a> {
+    pub direction: CaptureDirection,
+    /// Time of the capture, since the UNIX epoch.
+    pub timestamp: Duration,
+    /// Index of the channel in the channels of the radio.
+    pub channel: usize,
+    /// Data rate of the channel, see [Channel::data_rate](crate::radio::Channel::data_rate).
+    pub data_rate: u8,
+    /// Transmission power (dBm) of a transmitted frame.
+    pub tx_power: Option<i8>,
+    /// RSSI (dBm) of a received frame.
+    pub rssi: Option<i16>,
+    /// SNR (dB) of a received frame, if the radio reports it.
+    pub snr: Option<i16>,
+    /// The physical frame, starting with its physical header (frame type).
+    pub bytes: &'a [u8],
+}
+
+impl<'a> CapturedFrame<'a> {
+    /// Builds the capture of a frame transmitted now.
+    pub fn transmitted(channel: usize, data_rate: u8, tx_power: i8, bytes: &'a [u8]) -> Self {
+        Self {
+            direction: CaptureDirection::Transmitted,
+            timestamp: sys::unix_time(),
+            channel,
+            data_rate,
+            tx_power: Some(tx_power),
+            rssi: None,
+            snr: None,
+            bytes,
+        }
+    }
+
+    /// Builds the capture of a frame received now.
+    pub fn received(
+        channel: usize,
+        data_rate: u8,
+        rssi: i16,
+        snr: Option<i16>,
+        bytes: &'a [u8],
+    ) -> Self {
+        Self {
+            direction: CaptureDirection::Received,
+            timestamp: sys::unix_time(),
+            channel,
+            data_rate,
+            tx_power: None,
+            rssi: Some(rssi),
+            snr,
+            bytes,
+        }
+    }
+}
+
+/// Destination of the captured physical frames, see [LoRaRadio](crate::radio::LoRaRadio).
+pub trait CaptureSink {
+    /// Records a physical frame.
+    ///
+    /// The radio does not wait for the sink, failures should be handled (or reported) by the
+    /// sink itself.
+    fn capture(&mut self, frame: &CapturedFrame);
+}
+
+/// LoRa parameters of a data rate of a channel, written in the LoRaTap headers.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LoRaTapChannel {
+    /// Center frequency (Hz).
+    pub frequency: u32,
+    /// Bandwidth (Hz), LoRaTap only supports 125, 250 and 500kHz.
+    pub bandwidth: u32,
+    /// Spreading factor (7 to 12).
+    pub spreading_factor: u8,
+}
+
+impl LoRaTapChannel {
+    pub fn new(frequency: u32, bandwidth: u32, spreading_factor: u8) -> Self {
+        Self {
+            frequency,
+            bandwidth,
+            spreading_factor,
+        }
+    }
+}
+
+/// Link type of the LoRaTap headers.
+pub const LINKTYPE_LORATAP: u16 = 270;
+/// Length of a LoRaTap (version 0) header.
+#[cfg(feature = "std")]
+const LORATAP_HEADER_LENGTH: usize = 15;
+/// Sync word of the private LoRa networks, the default of the SX127x.
+#[cfg(feature = "std")]
+const LORA_SYNC_WORD: u8 = 0x12;
+
+/// Builds the LoRaTap header of a captured frame.
+///
+/// The RSSI is encoded as `rssi + 139` (as the SX127x on the high frequency band) and the SNR in
+/// quarters of dB. Unknown values are left to 0.
+#[cfg(feature = "std")]
+fn loratap_header(channel: &LoRaTapChannel, frame: &CapturedFrame) -> [u8; LORATAP_HEADER_LENGTH] {
+    let mut header = [0u8; LORATAP_HEADER_LENGTH];
+    header[2..4].copy_from_slice(&(LORATAP_HEADER_LENGTH as u16).to_be_bytes());
+    header[4..8].copy_from_slice(&channel.frequency.to_be_bytes());
+    header[8] = (channel.bandwidth / 125_000) as u8;
+    header[9] = channel.spreading_factor;
+    if let Some(rssi) = frame.rssi {
+        header[10] = rssi.saturating_add(139).clamp(0, u8::MAX as i16) as u8;
+    }
+    if let Some(snr) = frame.snr {
+        header[13] = snr.saturating_mul(4).clamp(i8::MIN as i16, i8::MAX as i16) as i8 as u8;
+    }
+    header[14] = LORA_SYNC_WORD;
+    header
+}
+
+/// pcapng block types.
+#[cfg(feature = "std")]
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+#[cfg(feature = "std")]
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+#[cfg(feature = "std")]
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+
+/// pcapng options.
+#[cfg(feature = "std")]
+const OPT_ENDOFOPT: u16 = 0;
+#[cfg(feature = "std")]
+const OPT_COMMENT: u16 = 1;
+#[cfg(feature = "std")]
+const IF_NAME: u16 = 2;
+#[cfg(feature = "std")]
+const IF_TSRESOL: u16 = 9;
+#[cfg(feature = "std")]
+const EPB_FLAGS: u16 = 2;
+
+/// Writer of the captured physical frames into a pcapng stream (little endian, a single
+/// LoRaTap interface, microsecond timestamps).
+///
+/// The direction is written in the packet flags, the transmission power in the packet comment.
+#[cfg(feature = "std")]
+pub struct PcapngWriter<W: std::io::Write> {
+    writer: W,
+    /// LoRa parameters by channel then by data rate.
+    channels: Vec<Vec<LoRaTapChannel>>,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> PcapngWriter<W> {
+    /// Builds a writer and writes the headers of the capture.
+    ///
+    /// `channels` describes each data rate of each channel of the radio, in the same order. The
+    /// parameters of the missing ones are left to 0 in the LoRaTap headers.
+    pub fn new(mut writer: W, channels: Vec<Vec<LoRaTapChannel>>) -> std::io::Result<Self> {
+        let mut section = Vec::new();
+        section.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes());
+        section.extend_from_slice(&1u16.to_le_bytes());
+        section.extend_from_slice(&0u16.to_le_bytes());
+        // Unknown section length.
+        section.extend_from_slice(&(-1i64).to_le_bytes());
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &section)?;
+
+        let mut interface = Vec::new();
+        interface.extend_from_slice(&LINKTYPE_LORATAP.to_le_bytes());
+        interface.extend_from_slice(&0u16.to_le_bytes());
+        // No snapshot length limit.
+        interface.extend_from_slice(&0u32.to_le_bytes());
+        push_option(&mut interface, IF_NAME, b"lora");
+        push_option(&mut interface, IF_TSRESOL, &[6]);
+        push_option(&mut interface, OPT_ENDOFOPT, &[]);
+        write_block(&mut writer, INTERFACE_DESCRIPTION_BLOCK, &interface)?;
+        Ok(Self { writer, channels })
+    }
+
+    /// Writes a captured physical frame.
+    pub fn write_frame(&mut self, frame: &CapturedFrame) -> std::io::Result<()> {
+        let channel = self
+            .channels
+            .get(frame.channel)
+            .and_then(|data_rates| data_rates.get(frame.data_rate as usize))
+            .copied()
+            .unwrap_or_default();
+        let header = loratap_header(&channel, frame);
+        let len = (LORATAP_HEADER_LENGTH + frame.bytes.len()) as u32;
+        let timestamp = frame.timestamp.as_micros() as u64;
+
+        let mut packet = Vec::with_capacity(len as usize + 64);
+        // Interface 0.
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        packet.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        packet.extend_from_slice(&len.to_le_bytes());
+        packet.extend_from_slice(&len.to_le_bytes());
+        packet.extend_from_slice(&header);
+        packet.extend_from_slice(frame.bytes);
+        pad(&mut packet);
+        let flags: u32 = match frame.direction {
+            CaptureDirection::Received => 0b01,
+            CaptureDirection::Transmitted => 0b10,
+        };
+        push_option(&mut packet, EPB_FLAGS, &flags.to_le_bytes());
+        let mut comment = format!("channel {}, data rate {}", frame.channel, frame.data_rate);
+        if let Some(tx_power) = frame.tx_power {
+            comment.push_str(&format!(", tx power {} dBm", tx_power));
+        }
+        push_option(&mut packet, OPT_COMMENT, comment.as_bytes());
+        push_option(&mut packet, OPT_ENDOFOPT, &[]);
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &packet)
+    }
+
+    /// Gets the underlying writer back.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> CaptureSink for PcapngWriter<W> {
+    fn capture(&mut self, frame: &CapturedFrame) {
+        if let Err(err) = self.write_frame(frame).and_then(|()| self.writer.flush()) {
+            // TODO: Error silenced here!
+            log::warn!("Failed to write the captured frame: {}", err);
+        }
+    }
+}
+
+/// Pads a block body to 32 bits.
+#[cfg(feature = "std")]
+fn pad(body: &mut Vec<u8>) {
+    body.resize((body.len() + 3) & !3, 0);
+}
+
+#[cfg(feature = "std")]
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+/// Writes a block: its type, its total length, its (padded) body and its total length again.
+#[cfg(feature = "std")]
+fn write_block<W: std::io::Write>(writer: &mut W, kind: u32, body: &[u8]) -> std::io::Result<()> {
+    let len = (body.len() + 12) as u32;
+    writer.write_all(&kind.to_le_bytes())?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&len.to_le_bytes())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    /// Splits a pcapng stream into its blocks (type and body).
+    fn blocks(mut bytes: &[u8]) -> Vec<(u32, &[u8])> {
+        let mut blocks = Vec::new();
+        while !bytes.is_empty() {
+            let kind = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+            let len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+            assert_eq!(len % 4, 0);
+            assert_eq!(bytes[(len - 4)..len], bytes[4..8]);
+            blocks.push((kind, &bytes[8..(len - 4)]));
+            bytes = &bytes[len..];
+        }
+        blocks
+    }
+
+    #[test]
+    fn capture_pcapng() {
+        let channels = vec![vec![
+            LoRaTapChannel::new(868_100_000, 125_000, 9),
+            LoRaTapChannel::new(868_100_000, 250_000, 7),
+        ]];
+        let mut writer = PcapngWriter::new(Vec::new(), channels).unwrap();
+        let mut rx = CapturedFrame::received(0, 1, -80, Some(-3), &[0x10, 0xAB, 0xCD]);
+        rx.timestamp = Duration::from_micros(0x0000_0001_0000_0002);
+        writer.capture(&rx);
+        writer.capture(&CapturedFrame::transmitted(1, 0, 14, &[0x06]));
+
+        let bytes = writer.into_inner();
+        let blocks = blocks(&bytes);
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0].0, SECTION_HEADER_BLOCK);
+        assert_eq!(blocks[0].1[0..4], [0x4D, 0x3C, 0x2B, 0x1A]);
+        assert_eq!(blocks[1].0, INTERFACE_DESCRIPTION_BLOCK);
+        assert_eq!(blocks[1].1[0..2], LINKTYPE_LORATAP.to_le_bytes());
+
+        let (kind, rx) = blocks[2];
+        assert_eq!(kind, ENHANCED_PACKET_BLOCK);
+        assert_eq!(rx[4..12], [1, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(rx[12..16], 18u32.to_le_bytes());
+        let header = &rx[20..35];
+        assert_eq!(header[0..4], [0, 0, 0, 15]);
+        assert_eq!(header[4..8], 868_100_000u32.to_be_bytes());
+        // 250kHz, SF7, RSSI and SNR.
+        assert_eq!(header[8..14], [2, 7, 59, 0, 0, (-12i8) as u8]);
+        assert_eq!(rx[35..38], [0x10, 0xAB, 0xCD]);
+        // Padding then the flags option (inbound).
+        assert_eq!(rx[40..48], [2, 0, 4, 0, 1, 0, 0, 0]);
+
+        // Unknown channel, the LoRa parameters are left to 0.
+        let tx = blocks[3].1;
+        assert_eq!(tx[20..35][4..10], [0; 6]);
+        assert_eq!(tx[36..44], [2, 0, 4, 0, 2, 0, 0, 0]);
+        let comment = &tx[48..(48 + u16::from_le_bytes([tx[46], tx[47]]) as usize)];
+        assert_eq!(comment, b"channel 1, data rate 0, tx power 14 dBm");
+    }
+}
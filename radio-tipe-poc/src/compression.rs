@@ -0,0 +1,194 @@
+//! Small footprint LZSS compression of the payloads, see [crate::frame::RadioFrameWithHeaders].
+//!
+//! The compressed data is a sequence of groups of up to 8 tokens, each group starting with a
+//! flag byte whose bits (least significant first) tell the kind of its tokens:
+//!
+//! * `1`: a literal byte, copied as is;
+//! * `0`: a reference to previously decompressed data, on two bytes: the distance minus 1 (on
+//!   12 bits, up to [WINDOW_SIZE]) then the length minus 3 (on 4 bits, up to [MAX_MATCH]).
+//!
+//! Neither the compression nor the decompression need more memory than their output.
+use crate::prelude::*;
+
+/// Size of the window of the references (bytes).
+pub const WINDOW_SIZE: usize = 4096;
+/// Shortest reference, shorter repetitions are cheaper as literals.
+const MIN_MATCH: usize = 3;
+/// Longest reference.
+pub const MAX_MATCH: usize = MIN_MATCH + 0x0F;
+
+/// Compresses `input`.
+///
+/// The result might be longer than the input (by one byte every 8 bytes at most), see
+/// [compress_if_smaller].
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() + input.len() / 8 + 1);
+    let mut flags_index = 0;
+    let mut tokens = 8;
+    let mut cursor = 0;
+    while cursor < input.len() {
+        if tokens == 8 {
+            flags_index = output.len();
+            output.push(0);
+            tokens = 0;
+        }
+        let (distance, len) = longest_match(input, cursor);
+        if len >= MIN_MATCH {
+            let reference = (((distance - 1) as u16) << 4) | ((len - MIN_MATCH) as u16);
+            output.extend_from_slice(&reference.to_be_bytes());
+            cursor += len;
+        } else {
+            output[flags_index] |= 1 << tokens;
+            output.push(input[cursor]);
+            cursor += 1;
+        }
+        tokens += 1;
+    }
+    output
+}
+
+/// Compresses `input`, returns `None` if it does not save any byte.
+pub fn compress_if_smaller(input: &[u8]) -> Option<Vec<u8>> {
+    let compressed = compress(input);
+    (compressed.len() < input.len()).then_some(compressed)
+}
+
+/// Finds the longest repetition (distance and length) of the data at `cursor` in the window.
+fn longest_match(input: &[u8], cursor: usize) -> (usize, usize) {
+    let max_len = usize::min(MAX_MATCH, input.len() - cursor);
+    let mut best = (0, 0);
+    for start in cursor.saturating_sub(WINDOW_SIZE)..cursor {
+        // The repetition might overlap the data at the cursor, as in the decompression.
+        let len = (0..max_len)
+            .take_while(|i| input[start + i] == input[cursor + i])
+            .count();
+        if len > best.1 {
+            best = (cursor - start, len);
+            if len == max_len {
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// Decompresses data produced by [compress].
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let mut output = Vec::with_capacity(2 * input.len());
+    let mut cursor = 0;
+    while cursor < input.len() {
+        let flags = input[cursor];
+        cursor += 1;
+        for token in 0..8 {
+            if cursor >= input.len() {
+                break;
+            }
+            if flags & (1 << token) != 0 {
+                output.push(input[cursor]);
+                cursor += 1;
+                continue;
+            }
+            let Some(raw) = input.get(cursor..(cursor + 2)) else {
+                return Err(CompressionError::Truncated { position: cursor });
+            };
+            let reference = u16::from_be_bytes([raw[0], raw[1]]);
+            let distance = (reference >> 4) as usize + 1;
+            let len = (reference & 0x0F) as usize + MIN_MATCH;
+            if distance > output.len() {
+                return Err(CompressionError::InvalidReference {
+                    position: cursor,
+                    distance,
+                });
+            }
+            let start = output.len() - distance;
+            for i in 0..len {
+                output.push(output[start + i]);
+            }
+            cursor += 2;
+        }
+    }
+    Ok(output)
+}
+
+/// Represents an error of the decompression of a payload.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CompressionError {
+    /// A reference points before the start of the decompressed data.
+    #[error("Invalid reference at byte {position} (distance {distance}).")]
+    InvalidReference { position: usize, distance: usize },
+
+    /// The compressed data ends in the middle of a reference.
+    #[error("Compressed data truncated at byte {position}.")]
+    Truncated { position: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_round_trip() {
+        let text = "Enchante de pouvoir communiquer avec vous! \
+            Enchante de pouvoir communiquer avec vous aussi! \
+            Nous sommes ravis de pouvoir communiquer avec vous."
+            .as_bytes();
+        let compressed = compress_if_smaller(text).expect("text should compress");
+        assert!(
+            compressed.len() < text.len() * 3 / 4,
+            "{}",
+            compressed.len()
+        );
+        assert_eq!(decompress(&compressed).unwrap(), text);
+
+        // Long runs are references overlapping their own output.
+        let run = [b'a'; 100];
+        let compressed = compress(&run);
+        assert!(compressed.len() < 20);
+        assert_eq!(decompress(&compressed).unwrap(), run);
+
+        assert_eq!(compress(&[]), Vec::<u8>::new());
+        assert_eq!(decompress(&[]).unwrap(), Vec::<u8>::new());
+        // Incompressible data is not worth compressing.
+        assert_eq!(compress_if_smaller(b"HELO1"), None);
+        assert_eq!(decompress(&compress(b"HELO1")).unwrap(), b"HELO1");
+    }
+
+    #[test]
+    fn compression_invalid_data() {
+        // A reference before any literal.
+        assert_eq!(
+            decompress(&[0b0000_0000, 0x00, 0x00]),
+            Err(CompressionError::InvalidReference {
+                position: 1,
+                distance: 1
+            })
+        );
+        // A literal then a truncated reference.
+        assert_eq!(
+            decompress(&[0b0000_0001, b'a', 0x00]),
+            Err(CompressionError::Truncated { position: 2 })
+        );
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn compression_round_trip(
+                input in vec(prop_oneof![Just(b'a'), Just(b'b'), any::<u8>()], 0..600)
+            ) {
+                let compressed = compress(&input);
+                prop_assert!(compressed.len() <= input.len() + input.len().div_ceil(8));
+                prop_assert_eq!(decompress(&compressed)?, input);
+            }
+
+            #[test]
+            fn compression_decompress_is_total(input in vec(any::<u8>(), 0..256)) {
+                let _ = decompress(&input);
+            }
+        }
+    }
+}
@@ -51,6 +51,10 @@
 //!                 println!("Transmitting beacons (ATPC Update needed)...");
 //!                 self.device.transmit_beacon()?;
 //!                 self.device.start_reception()?;
+//!             } else if self.device.is_probe_needed() {
+//!                 // Initialize new neighbors without disturbing the others.
+//!                 self.device.transmit_probe()?;
+//!                 self.device.start_reception()?;
 //!             }
 //!         }
 //!     }
@@ -59,8 +63,11 @@
 //! ```
 
 use crate::frame::FrameNonce;
-use crate::{LoRaAddress, LoRaDestination};
-use std::sync::Arc;
+use crate::prelude::*;
+use crate::stats::Statistics;
+use crate::{LoRaAddress, LoRaDestination, LoRaGroup};
+use alloc::sync::Arc;
+use core::time::Duration;
 
 /// Wrapper for an error that might be indicated a full queue.
 #[derive(thiserror::Error, Debug)]
@@ -74,6 +81,56 @@ pub enum QueueError<T> {
     QueueFullError(#[source] T),
 }
 
+/// Priority of a queued payload.
+///
+/// Higher priority payloads are transmitted first and preempt the lower priority ones when the
+/// queue is full or when the duty cycle only allows a short transmission.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessagePriority {
+    /// Bulk data, the first to be deferred or dropped.
+    Bulk = 0,
+    /// Regular application data.
+    #[default]
+    Normal = 1,
+    /// Control data, like configuration or commands.
+    Control = 2,
+    /// Alarms, preempting every other payload.
+    Alarm = 3,
+}
+
+/// Handle of a queued payload, returned by [Device::queue].
+///
+/// It identifies the payload in the [TxClient] callbacks, even when several payloads are
+/// aggregated in a single frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MessageHandle(u32);
+
+impl MessageHandle {
+    /// Builds a message handle from its identifier.
+    pub(crate) fn new(id: u32) -> Self {
+        MessageHandle(id)
+    }
+
+    /// Gets the identifier of this handle.
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Options of a queued payload, see [Device::queue_with_options].
+#[derive(Clone, Debug, Default)]
+pub struct QueueOptions {
+    /// Should the recipients acknowledge the reception of this payload.
+    pub ack: bool,
+    /// Priority of this payload.
+    pub priority: MessagePriority,
+    /// Time-to-live of this payload.
+    ///
+    /// When it expires before the payload is transmitted, the payload is removed from the queue
+    /// (see [TxClient::transmission_expired]). None means the payload never expires.
+    pub ttl: Option<Duration>,
+}
+
 /// Device trait represents a unit system that can receive and send messages using
 /// some complex features like Adaptive-Rate-Power-Rate, Acknowledgment or Packet Aggregation.
 ///
@@ -99,13 +156,56 @@ pub trait Device<'a> {
     /// Retrieve the current registered address for this device.
     fn get_address(&self) -> LoRaAddress;
 
+    /// Subscribe this device to a multicast group.
+    ///
+    /// Frames addressed to this group will be handled as if they were addressed to this device.
+    /// Returns false if the device was already subscribed or if the group identifier is invalid.
+    fn join_group(&mut self, group: LoRaGroup) -> bool;
+
+    /// Unsubscribe this device from a multicast group.
+    ///
+    /// Returns false if the device was not subscribed to this group.
+    fn leave_group(&mut self, group: LoRaGroup) -> bool;
+
+    /// Retrieve the multicast groups this device is subscribed to.
+    fn get_groups(&self) -> Vec<LoRaGroup>;
+
+    /// Register a known member of a multicast group.
+    ///
+    /// Known members are expected to acknowledge the frames sent to their group (when an
+    /// acknowledgment is requested), and are used by the ATPC to select the transmission power.
+    /// Members are also learned automatically from their acknowledgments.
+    fn add_group_member(&mut self, group: LoRaGroup, member: LoRaAddress) -> bool;
+
+    /// Forget a known member of a multicast group.
+    fn remove_group_member(&mut self, group: LoRaGroup, member: LoRaAddress) -> bool;
+
+    /// Retrieve the known members of a multicast group.
+    fn get_group_members(&self, group: LoRaGroup) -> Vec<LoRaAddress>;
+
     /// Get transmission status.
     fn is_transmitting(&mut self) -> Result<bool, Self::DeviceError>;
 
     /// Get listening status.
     fn is_listening(&mut self) -> Result<bool, Self::DeviceError>;
 
-    /// Flush the packet queue and transmit it using its current state.
+    /// Get the transmission statistics (airtime, energy, deliveries...) since the last reset.
+    fn get_statistics(&self) -> Statistics;
+
+    /// Reset the transmission statistics.
+    fn reset_statistics(&mut self);
+
+    /// Get the number of frames waiting in the packet queue.
+    ///
+    /// The queue might need several frames when it exceeds the capacity of a single one.
+    /// It also counts the acknowledgments of beacons queued by the radio itself, so
+    /// [transmit](Device::transmit) should be called whenever this is not zero.
+    fn pending_frames(&self) -> usize;
+
+    /// Flush the next frame of the packet queue and transmit it using its current state.
+    ///
+    /// When the queue needs several frames, only the first one is transmitted, the following ones
+    /// are transmitted on the next calls (see [Device::pending_frames]).
     ///
     /// NO-OP if the queue is empty.
     fn transmit(&mut self) -> Result<FrameNonce, Self::DeviceError>;
@@ -137,20 +237,60 @@ pub trait Device<'a> {
 
     /// Add given payload as packet to the internal queue.
     ///
-    /// Returns [QueueError], on [QueueError::QueueFullError] queue need to be flush and transmit
-    /// before appending new packets.
+    /// A payload can be sent up to 48 recipients, the queue is transparently split into several
+    /// frames when it exceeds the capacity of a single frame (16 recipients and 16 payloads).
+    ///
+    /// The payload is queued with the default [MessagePriority], see [Device::queue_with_options]
+    /// to specify it.
+    ///
+    /// Returns the [MessageHandle] of the queued payload, or a [QueueError], on
+    /// [QueueError::QueueFullError] queue need to be flush and transmit before appending new packets.
     fn queue<'b>(
         &mut self,
         dest: LoRaDestination,
         payload: &'b [u8],
         ack: bool,
-    ) -> Result<(), QueueError<Self::DeviceError>>;
+    ) -> Result<MessageHandle, QueueError<Self::DeviceError>> {
+        self.queue_with_options(
+            dest,
+            payload,
+            QueueOptions {
+                ack,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Add given payload as packet to the internal queue, with the given options.
+    ///
+    /// Higher priority payloads are transmitted first. When the queue is full, the payload preempts
+    /// the lowest priority payloads of the queue (if they have a lower priority), those are deferred
+    /// to the next transmission, or dropped when too many payloads are deferred (see
+    /// [TxClient::transmission_dropped]).
+    ///
+    /// Returns the [MessageHandle] of the queued payload, or a [QueueError], on
+    /// [QueueError::QueueFullError] queue need to be flush and transmit before appending new packets.
+    fn queue_with_options(
+        &mut self,
+        dest: LoRaDestination,
+        payload: &[u8],
+        options: QueueOptions,
+    ) -> Result<MessageHandle, QueueError<Self::DeviceError>>;
 
     /// Informs the application that the ATPC/radio would like to send beacons.
     fn is_beacon_needed(&mut self) -> bool;
 
     /// Forces the radio to send ATPC beacons.
     fn transmit_beacon(&mut self) -> Result<(), QueueError<Self::DeviceError>>;
+
+    /// Informs the application that the ATPC would like to probe a neighbor.
+    fn is_probe_needed(&mut self) -> bool;
+
+    /// Sends ATPC beacons to the next neighbor the ATPC would like to probe (usually one waiting
+    /// for initialization), instead of broadcasting them to every neighbor.
+    ///
+    /// Returns the probed neighbor, `None` if no neighbor needed to be probed.
+    fn transmit_probe(&mut self) -> Result<Option<LoRaAddress>, QueueError<Self::DeviceError>>;
 }
 
 /// Transmission client, that acts like a callback on transmission of a message.
@@ -173,6 +313,54 @@ pub trait TxClient {
         nonce: FrameNonce,
         payload: Vec<u8>,
     ) -> Result<(), ()>;
+
+    /// A queued payload was dropped before its transmission, because too many payloads with a
+    /// higher priority were queued.
+    fn transmission_dropped(
+        &self,
+        handle: MessageHandle,
+        payload: Vec<u8>,
+        priority: MessagePriority,
+    ) -> Result<(), ()> {
+        let _ = (handle, payload, priority);
+        Ok(())
+    }
+
+    /// A queued payload expired before its transmission (see [QueueOptions::ttl]), it was removed
+    /// from the queue.
+    fn transmission_expired(&self, handle: MessageHandle, payload: Vec<u8>) -> Result<(), ()> {
+        let _ = (handle, payload);
+        Ok(())
+    }
+
+    /// A queued payload was transmitted in the frame with the given nonce.
+    ///
+    /// Note that a payload with many recipients might be transmitted in several frames.
+    fn message_sent(&self, handle: MessageHandle, nonce: FrameNonce) -> Result<(), ()> {
+        let _ = (handle, nonce);
+        Ok(())
+    }
+
+    /// A queued payload was acknowledged by the given recipient.
+    fn message_delivered(&self, handle: MessageHandle, recipient: LoRaAddress) -> Result<(), ()> {
+        let _ = (handle, recipient);
+        Ok(())
+    }
+
+    /// A queued payload was not acknowledged by the given recipient, while an acknowledgment
+    /// was required.
+    fn message_failed(&self, handle: MessageHandle, recipient: LoRaAddress) -> Result<(), ()> {
+        let _ = (handle, recipient);
+        Ok(())
+    }
+
+    /// The link with the given neighbor is considered down by the ATPC, after repeated missing
+    /// acknowledgments or a long silence. The neighbor has to be initialized again (or it was
+    /// forgotten) before transmitting to it efficiently.
+    fn link_down(&self, neighbor: LoRaAddress) -> Result<(), ()> {
+        let _ = neighbor;
+        Ok(())
+    }
 }
 
 impl<T> TxClient for Arc<T>
@@ -195,6 +383,35 @@ where
     ) -> Result<(), ()> {
         return T::transmission_failed(self.as_ref(), recipient, nonce, payload);
     }
+
+    fn transmission_dropped(
+        &self,
+        handle: MessageHandle,
+        payload: Vec<u8>,
+        priority: MessagePriority,
+    ) -> Result<(), ()> {
+        return T::transmission_dropped(self.as_ref(), handle, payload, priority);
+    }
+
+    fn transmission_expired(&self, handle: MessageHandle, payload: Vec<u8>) -> Result<(), ()> {
+        return T::transmission_expired(self.as_ref(), handle, payload);
+    }
+
+    fn message_sent(&self, handle: MessageHandle, nonce: FrameNonce) -> Result<(), ()> {
+        return T::message_sent(self.as_ref(), handle, nonce);
+    }
+
+    fn message_delivered(&self, handle: MessageHandle, recipient: LoRaAddress) -> Result<(), ()> {
+        return T::message_delivered(self.as_ref(), handle, recipient);
+    }
+
+    fn message_failed(&self, handle: MessageHandle, recipient: LoRaAddress) -> Result<(), ()> {
+        return T::message_failed(self.as_ref(), handle, recipient);
+    }
+
+    fn link_down(&self, neighbor: LoRaAddress) -> Result<(), ()> {
+        return T::link_down(self.as_ref(), neighbor);
+    }
 }
 
 /// Reception client, acts like a callback on reception of radio messages.
@@ -1,12 +1,32 @@
 //! Frame description, utilities and helpers.
+//!
+//! Every parser (the `try_from_bytes` functions and the views) is total: whatever bytes are
+//! received from the radio, it returns a component or a [FrameError], it never panics. The
+//! cargo-fuzz targets of `fuzz/` check it.
+//!
+//! With the `serde` feature, the owned frame components implement `Serialize` and `Deserialize`,
+//! e.g. to log frames as JSON or to write test fixtures. The compact headers ([AddressHeader],
+//! [PayloadFlag] and [InfoHeader]) are serialized as their raw value.
+use crate::compression::{self, CompressionError};
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Discriminant for a frame.
+///
+/// Nodes ignore the frames of an unknown type, so new types can be added without breaking the
+/// deployed nodes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FrameType {
     /// A *simple* frame with one or more payloads.
     Message = 0,
     /// A BEACON frame produced by an ATPC.
     /// This frame might be ignored by the recipient.
     BroadcastCheckSignal = 6,
+    /// An acknowledgment of a whole sweep of beacons, see [BeaconAcknowledgment].
+    BeaconAcknowledgment = 7,
 }
 
 /// Trait to calculate size on frame for every component on frame.
@@ -15,14 +35,49 @@ pub trait FrameSize {
     fn size(&self) -> usize;
 }
 
+/// Version of the standard frame format.
+///
+/// It is encoded in the high nibble of the first byte of the [RadioHeaders], frames with an
+/// unknown version are rejected with [FrameError::UnsupportedVersion].
+pub const PROTOCOL_VERSION: u8 = 1;
+/// Version of the compact frame format.
+///
+/// The nonce and the payload lengths are encoded as varints, the nonce of each acknowledgment as
+/// the (zigzag varint) difference with the nonce of the frame, and each RSSI delta on a single
+/// byte (clamped to `i8`).
+///
+/// The difference depends on the clocks of both peers, an acknowledged nonce too far from the
+/// nonce of the frame (or equal to it) is encoded as a zero difference followed by the nonce
+/// itself, so an acknowledgment never takes more than one byte over the standard encoding.
+pub const COMPACT_VERSION: u8 = 2;
+/// Flag of the [RadioHeaders] version byte announcing an extension area.
+const EXTENSIONS_FLAG: u8 = 0b0000_0001;
+/// Kind of the [Extension] announcing the data rate of the physical frames following the first
+/// one (see [Channel::data_rate](crate::radio::Channel::data_rate)), on a single byte.
+///
+/// The first physical frame always uses the default data rate. Without this extension the
+/// following ones use it too, so the frames at the default data rate keep their encoding.
+pub const DATA_RATE_EXTENSION: u8 = 0x01;
+/// Flag of the [RadioHeaders] version byte announcing compressed payloads.
+const COMPRESSION_FLAG: u8 = 0b0000_0010;
+/// Flag of a payload length announcing a compressed payload (without [COMPACT_VERSION]).
+const COMPRESSED_LENGTH_FLAG: u16 = 0b1000_0000_0000_0000;
+
 /// Type alias for a frame nonce.
 pub type FrameNonce = u64;
 /// The constant size of a frame nonce.
 const FRAME_NONCE_SIZE: usize = 8;
+/// Difference announcing an acknowledged nonce encoded as is, see [COMPACT_VERSION].
+const ABSOLUTE_NONCE: u64 = 0;
+/// The constant size of an acknowledgment (address, nonce and RSSI delta).
+const ACKNOWLEDGMENT_SIZE: usize = 2 + FRAME_NONCE_SIZE + 2;
 
 /// Radio header representation.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RadioHeaders {
+    /// Version of the frame format, see [PROTOCOL_VERSION].
+    pub version: u8,
     /// Number of Recipients and frames of this transmission.
     pub rec_n_frames: InfoHeader,
     /// Inner recipient headers, representing the recipient address and payload associations.
@@ -35,12 +90,37 @@ pub struct RadioHeaders {
     ///
     /// Nonce MUST follow a total order.
     pub nonce: FrameNonce,
+    /// Do the payload lengths carry a compression flag (see [RadioFrameWithHeaders::compressed])?
+    ///
+    /// The sender enables it per frame, the nodes without compression support reject the frame.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compression: bool,
+    /// Optional fields, encoded in a TLV extension area after the nonce.
+    ///
+    /// Nodes skip the extensions they do not know, so new fields can be added without a new
+    /// version of the frame format.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extensions: Vec<Extension>,
     // TODO - SECURITY : add frame signature (64 bytes for Ed25519)
     // pub signature: [u8; 64];
 }
 
+/// Optional field of the [RadioHeaders] (signature, routing, fragment sequence...).
+///
+/// The extension area starts with its length, then holds each extension as its kind, the length
+/// of its value and its value (up to 255 bytes in total).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Extension {
+    /// Kind of the extension.
+    pub kind: u8,
+    /// Value of the extension.
+    pub value: Vec<u8>,
+}
+
 /// Full representation of a Radio frame with headers and payloads.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RadioFrameWithHeaders {
     /// Frame headers
     pub headers: RadioHeaders,
@@ -51,17 +131,65 @@ pub struct RadioFrameWithHeaders {
     pub acknowledgments: Vec<(AddressHeader, FrameNonce, i16)>,
     /// Frame payloads
     pub payloads: Vec<Payload>,
+    /// Payloads compressed with [compression], only allowed when [RadioHeaders::compression] is
+    /// set.
+    ///
+    /// The recipients decompress them before forwarding them, see
+    /// [RadioFrameView::decompressed_payload].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compressed: PayloadFlag,
 }
 
 /// Type alias for a frame body (or frame).
 pub type RadioFrame = Vec<Payload>;
 
+/// Payload of a beacon (a [FrameType::BroadcastCheckSignal] frame).
+///
+/// Beacons are transmitted in sweeps, one beacon per transmission power level of the ATPC.
+/// This payload allows the recipients to associate the beacons of a same sweep.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BeaconPayload {
+    /// Nonce of the first beacon of the sweep, it identifies the sweep.
+    pub sweep: FrameNonce,
+    /// Index of the transmission power level of this beacon.
+    pub tpi: u8,
+    /// Number of transmission power levels (and beacons) of the sweep.
+    pub levels: u8,
+}
+
+/// Compact acknowledgment of a sweep of beacons (a [FrameType::BeaconAcknowledgment] frame).
+///
+/// Instead of acknowledging every beacon, a peer reports once the RSSI of each beacon it received
+/// from a sweep.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BeaconAcknowledgment {
+    /// Address of the peer acknowledging the beacons.
+    pub sender: AddressHeader,
+    /// Address of the peer that transmitted the beacons.
+    pub recipient: AddressHeader,
+    /// Nonce of the first beacon of the sweep.
+    pub sweep: FrameNonce,
+    /// RSSI (in dBm) of each beacon by transmission power level, `None` if the beacon was missed.
+    ///
+    /// The encoding covers -163dBm to 91dBm, beyond the range of the SX127x radios.
+    pub rssi: Vec<Option<i16>>,
+}
+
+/// Encoded value of a missed beacon in a [BeaconAcknowledgment].
+const BEACON_MISSING_RSSI: u8 = 0;
+/// Offset of the encoded RSSI of a beacon in a [BeaconAcknowledgment], a byte `rssi + 164`.
+const BEACON_RSSI_OFFSET: i16 = 164;
+
 /// Compact representation of recipient number and frame number.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub struct InfoHeader(u8);
 
 /// Compact representation of recipient (with payload association) and acknowledgment handling.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum RecipientHeader {
     /// Direct message, there is only one recipient and all of the message is for it.
     Direct(AddressHeader),
@@ -71,15 +199,32 @@ pub enum RecipientHeader {
 
 /// Compact representation of an LoRa address (and acknowledgment).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub struct AddressHeader(u16);
 
 /// Compact representation of recipient-payload association.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub struct PayloadFlag(u16);
 
 /// Type alias for a payload (a simple byte sequence).
 pub(crate) type Payload = Vec<u8>;
 
+impl TryFrom<u8> for FrameType {
+    type Error = FrameError;
+
+    fn try_from(frame_type: u8) -> Result<Self, Self::Error> {
+        match frame_type {
+            0 => Ok(FrameType::Message),
+            6 => Ok(FrameType::BroadcastCheckSignal),
+            7 => Ok(FrameType::BeaconAcknowledgment),
+            t => Err(FrameError::InvalidHeader {
+                context: Some(format!("Unknown frame type {}.", t)),
+            }),
+        }
+    }
+}
+
 impl InfoHeader {
     /// Builds an info header from scratch.
     pub fn new(recipients: u8, frames: u8) -> Self {
@@ -126,6 +271,14 @@ pub(crate) const GLOBAL_NO_ACKNOWLEDGMENT: u16 = 0b0111_1111_1111_1111;
 pub(crate) const ADDRESS_BITMASK: u16 = 0b0111_1111_1111_1111;
 /// Bit mask of the acknowlegment part of an [LoRaAddress](crate::LoRaAddress).
 pub(crate) const ACKNOWLEDGMENT_BITMASK: u16 = 0b1000_0000_0000_0000;
+/// Prefix of the addresses reserved for the multicast groups.
+///
+/// Every address starting with this prefix is a group address, except the global address.
+pub(crate) const GROUP_ADDRESS_PREFIX: u16 = 0b0111_0000_0000_0000;
+/// Bit mask of the group identifier part of a group address.
+pub(crate) const GROUP_ID_BITMASK: u16 = 0b0000_1111_1111_1111;
+/// Highest usable multicast group identifier, the next one would collide with the global address.
+pub const MAX_GROUP_ID: u16 = 0b0000_1111_1111_1110;
 
 impl AddressHeader {
     /// Builds an address header from scratch.
@@ -146,6 +299,13 @@ impl AddressHeader {
         }
     }
 
+    /// Builds a multicast group address header from scratch.
+    ///
+    /// Group identifier should be included in `0..=MAX_GROUP_ID`, higher bits are ignored.
+    pub fn new_group(group: u16, ack: bool) -> Self {
+        Self::new(GROUP_ADDRESS_PREFIX | (group & GROUP_ID_BITMASK), ack)
+    }
+
     /// Sets the acknowlegment bit for an address.
     pub fn set_acknowledgment(&mut self, ack: bool) -> Self {
         let mut inner = self.0 & ADDRESS_BITMASK;
@@ -185,6 +345,26 @@ impl AddressHeader {
     pub fn is_global(&self) -> bool {
         (self.get_address() & ADDRESS_BITMASK) == GLOBAL_NO_ACKNOWLEDGMENT
     }
+
+    /// Is this header's address a multicast group address.
+    pub fn is_group(&self) -> bool {
+        !self.is_global() && (self.get_address() & GROUP_ADDRESS_PREFIX) == GROUP_ADDRESS_PREFIX
+    }
+
+    /// Is this header's address the address of a single peer, neither the global address nor a
+    /// multicast group address.
+    pub fn is_unicast(&self) -> bool {
+        (self.get_address() & GROUP_ADDRESS_PREFIX) != GROUP_ADDRESS_PREFIX
+    }
+
+    /// Gets the multicast group identifier of this address header, if it is a group address.
+    pub fn get_group(&self) -> Option<u16> {
+        if self.is_group() {
+            Some(self.get_address() & GROUP_ID_BITMASK)
+        } else {
+            None
+        }
+    }
 }
 
 impl PayloadFlag {
@@ -204,6 +384,16 @@ impl PayloadFlag {
         self.0 |= 1 << id;
     }
 
+    /// Does the payload flag hold the given message id?
+    pub fn contains(&self, id: u8) -> bool {
+        id < 16 && (self.0 & (1 << id)) > 0
+    }
+
+    /// Is the payload flag empty?
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
     /// Converts the payload flag to a list of message indexes.
     pub fn to_message_ids(&self) -> Vec<u8> {
         let mut ids = Vec::new();
@@ -241,244 +431,1036 @@ impl Into<u16> for AddressHeader {
     fn into(self) -> u16 {
         self.0
     }
-}
+}
+
+impl From<u8> for InfoHeader {
+    fn from(inner: u8) -> Self {
+        InfoHeader(inner)
+    }
+}
+
+impl Into<u8> for InfoHeader {
+    fn into(self) -> u8 {
+        self.0
+    }
+}
+
+impl RecipientHeader {
+    /// Builds the byte/network representation of this recipient header.
+    ///
+    /// Panics if it cannot be encoded, see [encode_into](RecipientHeader::encode_into).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.size()];
+        self.encode_into(&mut bytes)
+            .expect("Failed to encode the recipient header!");
+        bytes
+    }
+
+    /// Writes the byte/network representation of this recipient header into `buf`.
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, FrameError> {
+        let mut writer = FrameWriter::new(buf, self.size())?;
+        self.write_to(&mut writer);
+        Ok(writer.cursor)
+    }
+
+    fn write_to(&self, writer: &mut FrameWriter) {
+        match self {
+            RecipientHeader::Direct(addr) => {
+                writer.put(&[1]);
+                writer.put_u16((*addr).into());
+            }
+            RecipientHeader::Group(addrs) => {
+                writer.put(&[addrs.len() as u8]);
+                for (a, pf) in addrs {
+                    writer.put_u16((*a).into());
+                    writer.put_u16((*pf).into());
+                }
+            }
+        }
+    }
+
+    /// Builds from a byte/network representation a new recipient header.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<(Self, usize), FrameError> {
+        let (view, read) = RecipientsView::try_from_bytes(bytes)?;
+        Ok((view.to_header(), read))
+    }
+}
+
+impl RadioHeaders {
+    /// Builds the byte/network representation of these radio headers.
+    ///
+    /// Panics if it cannot be encoded, see [encode_into](RadioHeaders::encode_into).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.size()];
+        self.encode_into(&mut bytes)
+            .expect("Failed to encode the radio headers!");
+        bytes
+    }
+
+    /// Writes the byte/network representation of these radio headers into `buf`.
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, FrameError> {
+        self.check_encodable()?;
+        let mut writer = FrameWriter::new(buf, self.size())?;
+        self.write_to(&mut writer);
+        Ok(writer.cursor)
+    }
+
+    /// Checks that the version and the extensions fit in their encoding.
+    fn check_encodable(&self) -> Result<(), FrameError> {
+        if self.version != PROTOCOL_VERSION && self.version != COMPACT_VERSION {
+            return Err(FrameError::UnsupportedVersion {
+                version: self.version,
+            });
+        }
+        let area = self.extensions_size();
+        if area > u8::MAX as usize || self.extensions.iter().any(|e| e.value.len() > 0xFF) {
+            return Err(FrameError::InvalidHeader {
+                context: Some(format!("Extension area is too big ({} bytes).", area)),
+            });
+        }
+        Ok(())
+    }
+
+    fn write_to(&self, writer: &mut FrameWriter) {
+        let mut flags = 0;
+        if !self.extensions.is_empty() {
+            flags |= EXTENSIONS_FLAG;
+        }
+        if self.compression {
+            flags |= COMPRESSION_FLAG;
+        }
+        writer.put(&[(self.version << 4) | flags]);
+        writer.put(&[self.rec_n_frames.into()]);
+        self.recipients.write_to(writer);
+        writer.put_u16(self.sender.into());
+        writer.put(&[self.payloads]);
+        if self.is_compact() {
+            writer.put_varint(self.nonce);
+        } else {
+            writer.put(&self.nonce.to_be_bytes());
+        }
+        if !self.extensions.is_empty() {
+            writer.put(&[self.extensions_size() as u8]);
+            for ext in &self.extensions {
+                writer.put(&[ext.kind, ext.value.len() as u8]);
+                writer.put(&ext.value);
+            }
+        }
+    }
+
+    /// Are these headers, and the rest of their frame, using the compact encoding (see
+    /// [COMPACT_VERSION])?
+    pub fn is_compact(&self) -> bool {
+        self.version == COMPACT_VERSION
+    }
+
+    /// Gets the encoded size of the given acknowledgments (without their number) in a frame
+    /// with these headers.
+    pub fn acknowledgments_size(
+        &self,
+        acknowledgments: &[(AddressHeader, FrameNonce, i16)],
+    ) -> usize {
+        if self.is_compact() {
+            acknowledgments
+                .iter()
+                .map(|(_ah, nonce, _drssi)| {
+                    let nonce_size = relative_nonce(self.nonce, *nonce)
+                        .map_or(1 + FRAME_NONCE_SIZE, varint_size);
+                    2 + nonce_size + 1
+                })
+                .sum()
+        } else {
+            acknowledgments.len() * ACKNOWLEDGMENT_SIZE
+        }
+    }
+
+    /// Gets the encoded size of the given payloads (with their length) in a frame with these
+    /// headers.
+    pub fn payloads_size(&self, payloads: &[Payload]) -> usize {
+        if self.is_compact() {
+            // The compression flag is the lowest bit of the length.
+            let shift = self.compression as u32;
+            payloads
+                .iter()
+                .map(|pl| varint_size((pl.len() as u64) << shift) + pl.len())
+                .sum()
+        } else {
+            payloads.iter().map(|pl| pl.size()).sum()
+        }
+    }
+
+    /// Gets the encoding of the acknowledgments and payloads following these headers.
+    fn body_encoding(&self) -> BodyEncoding {
+        BodyEncoding {
+            compact: self.is_compact(),
+            compression: self.compression,
+            nonce: self.nonce,
+        }
+    }
+
+    /// Gets the size of the TLV extension area, without its length.
+    fn extensions_size(&self) -> usize {
+        self.extensions.iter().map(|e| 2 + e.value.len()).sum()
+    }
+
+    /// Gets the value of the first extension of the given kind.
+    pub fn extension(&self, kind: u8) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .find(|e| e.kind == kind)
+            .map(|e| &e.value[..])
+    }
+
+    /// Gets the data rate of the physical frames following the first one, see
+    /// [DATA_RATE_EXTENSION].
+    pub fn data_rate(&self) -> u8 {
+        self.extension(DATA_RATE_EXTENSION)
+            .and_then(|value| value.first().copied())
+            .unwrap_or(0)
+    }
+
+    /// Sets the data rate of the physical frames following the first one, see
+    /// [DATA_RATE_EXTENSION]. The default data rate (0) is not announced.
+    pub fn set_data_rate(&mut self, data_rate: u8) {
+        self.extensions.retain(|e| e.kind != DATA_RATE_EXTENSION);
+        if data_rate != 0 {
+            self.extensions.push(Extension {
+                kind: DATA_RATE_EXTENSION,
+                value: vec![data_rate],
+            });
+        }
+    }
+
+    /// Builds the radio headers from a byte/network representation.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<(Self, usize), FrameError> {
+        let (view, read) = RadioHeadersView::try_from_bytes(bytes)?;
+        Ok((view.to_headers(), read))
+    }
+}
+
+impl RadioFrameWithHeaders {
+    /// Builds the byte/network representation of this entire frame with its headers.
+    ///
+    /// Panics if it cannot be encoded, see [encode_into](RadioFrameWithHeaders::encode_into).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        assert!(
+            self.headers.payloads == self.payloads.len() as u8,
+            "Number of payload is invalid, not equal number in header and in frame."
+        );
+        let mut bytes = vec![0; self.size()];
+        self.encode_into(&mut bytes)
+            .expect("Failed to encode the frame!");
+        bytes
+    }
+
+    /// Writes the byte/network representation of this entire frame with its headers into `buf`,
+    /// without any allocation.
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, FrameError> {
+        self.headers.check_encodable()?;
+        if self.headers.payloads != self.payloads.len() as u8 {
+            return Err(FrameError::InvalidHeader {
+                context: Some(format!(
+                    "Number of payloads is invalid ({} in header, {} in frame).",
+                    self.headers.payloads,
+                    self.payloads.len()
+                )),
+            });
+        }
+        self.check_compression()?;
+        let mut writer = FrameWriter::new(buf, self.size())?;
+        // Headers
+        self.headers.write_to(&mut writer);
+
+        // Acknowledgment
+        let encoding = self.headers.body_encoding();
+        writer.put(&[self.acknowledgments.len() as u8]);
+        for ack in &self.acknowledgments {
+            writer.put_acknowledgment(ack, encoding);
+        }
+
+        // Payloads
+        for (id, pl) in self.payloads.iter().enumerate() {
+            writer.put_payload(pl, self.compressed.contains(id as u8), encoding);
+        }
+        Ok(writer.cursor)
+    }
+
+    /// Gets a copy of the payload with the given message id, decompressed if needed.
+    pub fn decompressed_payload(&self, id: u8) -> Option<Result<Vec<u8>, FrameError>> {
+        let payload = self.payloads.get(id as usize)?;
+        if !self.compressed.contains(id) {
+            return Some(Ok(payload.clone()));
+        }
+        Some(
+            compression::decompress(payload)
+                .map_err(|source| FrameError::InvalidPayload { id, source }),
+        )
+    }
+
+    /// Checks that the compressed payloads can be announced.
+    fn check_compression(&self) -> Result<(), FrameError> {
+        let payloads = 1u32
+            .checked_shl(self.payloads.len() as u32)
+            .map_or(u32::MAX, |bit| bit - 1);
+        let compressed = self.compressed.0 as u32;
+        if compressed != 0 && (!self.headers.compression || compressed & !payloads != 0) {
+            return Err(FrameError::InvalidHeader {
+                context: Some(format!(
+                    "Compressed payloads {:?} not announced by the headers.",
+                    self.compressed.to_message_ids()
+                )),
+            });
+        }
+        let max_len = (!COMPRESSED_LENGTH_FLAG) as usize;
+        if self.headers.compression
+            && !self.headers.is_compact()
+            && self.payloads.iter().any(|pl| pl.len() > max_len)
+        {
+            return Err(FrameError::InvalidHeader {
+                context: Some(format!(
+                    "Payloads longer than {} bytes cannot be announced compressed.",
+                    max_len
+                )),
+            });
+        }
+        Ok(())
+    }
+
+    /// Builds a radio frame and its headers from its byte/network representation.
+    ///
+    /// See [RadioFrameView] to read a frame without copying it.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<(Self, usize), FrameError> {
+        let (view, read) = RadioFrameView::try_from_bytes(bytes)?;
+        Ok((view.to_frame(), read))
+    }
+}
+
+/// Borrowed representation of a [RecipientHeader], see [RadioFrameView].
+#[derive(Clone, Debug)]
+pub enum RecipientsView<'a> {
+    /// Direct message, there is only one recipient and all of the message is for it.
+    Direct(AddressHeader),
+    /// Group message, with the recipients and their payload associations.
+    Group(GroupRecipients<'a>),
+}
+
+/// Iterator over the recipients (with payload association) of a borrowed group recipient header.
+#[derive(Clone, Debug)]
+pub struct GroupRecipients<'a>(&'a [u8]);
+
+/// Iterator over the acknowledgments of a [RadioFrameView].
+#[derive(Clone, Debug)]
+pub struct AcknowledgmentsView<'a> {
+    reader: FrameReader<'a>,
+    encoding: BodyEncoding,
+    remaining: usize,
+}
+
+/// Iterator over the extensions (kind and value) of a [RadioHeadersView].
+#[derive(Clone, Debug)]
+pub struct ExtensionsView<'a>(&'a [u8]);
+
+/// Iterator over the payloads of a [RadioFrameView].
+#[derive(Clone, Debug)]
+pub struct PayloadsView<'a> {
+    reader: FrameReader<'a>,
+    encoding: BodyEncoding,
+}
+
+/// Encoding of the acknowledgments and payloads of a frame, given by its headers.
+#[derive(Copy, Clone, Debug)]
+struct BodyEncoding {
+    /// Is the frame using the compact encoding (see [COMPACT_VERSION])?
+    compact: bool,
+    /// Do the payload lengths carry a compression flag?
+    compression: bool,
+    /// Nonce of the frame, the reference of the acknowledged nonces in the compact encoding.
+    nonce: FrameNonce,
+}
+
+/// Borrowed representation of [RadioHeaders], see [RadioFrameView].
+#[derive(Clone, Debug)]
+pub struct RadioHeadersView<'a> {
+    /// Version of the frame format, see [PROTOCOL_VERSION].
+    pub version: u8,
+    /// Number of Recipients and frames of this transmission.
+    pub rec_n_frames: InfoHeader,
+    /// Inner recipient headers, representing the recipient address and payload associations.
+    pub recipients: RecipientsView<'a>,
+    /// Number of payloads (limited to 16).
+    pub payloads: u8,
+    /// Sender address
+    pub sender: AddressHeader,
+    /// A cryptographic nonce
+    pub nonce: FrameNonce,
+    /// Do the payload lengths carry a compression flag?
+    pub compression: bool,
+    /// Optional fields of the headers.
+    pub extensions: ExtensionsView<'a>,
+}
+
+/// Borrowed representation of a [RadioFrameWithHeaders], over its byte/network representation.
+///
+/// The whole frame is validated when the view is built, then the recipients, acknowledgments
+/// and payloads are read in place: neither the parsing nor the accessors allocate.
+#[derive(Clone, Debug)]
+pub struct RadioFrameView<'a> {
+    /// Frame headers
+    pub headers: RadioHeadersView<'a>,
+    /// Encoded acknowledgments.
+    acknowledgments: &'a [u8],
+    /// Number of acknowledgments.
+    ack_count: usize,
+    /// Encoded payloads (with their length).
+    payloads: &'a [u8],
+    /// Compressed payloads.
+    compressed: PayloadFlag,
+}
+
+impl<'a> RecipientsView<'a> {
+    /// Builds a recipient header view over its byte/network representation.
+    pub fn try_from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), FrameError> {
+        let mut reader = FrameReader::new(bytes);
+        let view = Self::read(&mut reader)?;
+        Ok((view, reader.cursor))
+    }
+
+    fn read(reader: &mut FrameReader<'a>) -> Result<Self, FrameError> {
+        let start = reader.cursor;
+        match reader.u8("recipient header")? {
+            0 => Err(reader.malformed(start, "Recipient header with 0 recipient.".to_owned())),
+            1 => Ok(RecipientsView::Direct(AddressHeader::from(
+                reader.u16("Direct recipient")?,
+            ))),
+            nrec @ 2..=16 => Ok(RecipientsView::Group(GroupRecipients(
+                reader.take(4 * nrec as usize, "Group recipients")?,
+            ))),
+            n => Err(reader.malformed(
+                start,
+                format!("Recipient header with too many recipients ({}).", n),
+            )),
+        }
+    }
+
+    /// Is the given address header one of the recipients?
+    pub fn contains(&self, addr: &AddressHeader) -> bool {
+        match self {
+            RecipientsView::Direct(ah) => ah == addr,
+            RecipientsView::Group(ahs) => ahs.clone().any(|(ah, _)| ah == *addr),
+        }
+    }
+
+    /// Copies the view into an owned recipient header.
+    pub fn to_header(&self) -> RecipientHeader {
+        match self {
+            RecipientsView::Direct(ah) => RecipientHeader::Direct(*ah),
+            RecipientsView::Group(ahs) => RecipientHeader::Group(ahs.clone().collect()),
+        }
+    }
+}
+
+impl<'a> Iterator for GroupRecipients<'a> {
+    type Item = (AddressHeader, PayloadFlag);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (recipient, rest) = self.0.split_first_chunk::<4>()?;
+        self.0 = rest;
+        Some((
+            AddressHeader::from(u16::from_be_bytes([recipient[0], recipient[1]])),
+            PayloadFlag::from(u16::from_be_bytes([recipient[2], recipient[3]])),
+        ))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len() / 4, Some(self.0.len() / 4))
+    }
+}
+
+impl<'a> ExactSizeIterator for GroupRecipients<'a> {}
+
+impl<'a> Iterator for AcknowledgmentsView<'a> {
+    type Item = (AddressHeader, FrameNonce, i16);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.reader.acknowledgment(self.encoding).ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for AcknowledgmentsView<'a> {}
+
+impl<'a> Iterator for ExtensionsView<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&[kind, len], rest) = self.0.split_first_chunk::<2>()?;
+        let value = rest.get(..len as usize)?;
+        self.0 = &rest[len as usize..];
+        Some((kind, value))
+    }
+}
+
+impl<'a> Iterator for PayloadsView<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.rest().is_empty() {
+            return None;
+        }
+        self.reader
+            .payload(self.encoding)
+            .ok()
+            .map(|(payload, _compressed)| payload)
+    }
+}
+
+impl<'a> RadioHeadersView<'a> {
+    /// Builds a radio headers view over their byte/network representation.
+    pub fn try_from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), FrameError> {
+        let mut reader = FrameReader::new(bytes);
+        let view = Self::read(&mut reader)?;
+        Ok((view, reader.cursor))
+    }
+
+    fn read(reader: &mut FrameReader<'a>) -> Result<Self, FrameError> {
+        let start = reader.cursor;
+        let version_flags = reader.u8("version")?;
+        let version = version_flags >> 4;
+        if version != PROTOCOL_VERSION && version != COMPACT_VERSION {
+            return Err(FrameError::UnsupportedVersion { version });
+        }
+        let flags = version_flags & 0x0F;
+        if flags & !(EXTENSIONS_FLAG | COMPRESSION_FLAG) != 0 {
+            return Err(reader.malformed(start, format!("Unknown header flags {:#06b}.", flags)));
+        }
+        let rec_n_frames = InfoHeader::from(reader.u8("info header")?);
+        let recipients = RecipientsView::read(reader)?;
+        let sender = AddressHeader::from(reader.u16("source address")?);
+        let payloads = reader.u8("number of payloads")?;
+        let nonce = if version == COMPACT_VERSION {
+            reader.varint("nonce")?
+        } else {
+            reader.u64("nonce")?
+        };
+        let extensions = if flags & EXTENSIONS_FLAG != 0 {
+            let len = reader.u8("extension area length")? as usize;
+            if len == 0 {
+                return Err(reader.malformed(reader.cursor - 1, "Empty extension area.".to_owned()));
+            }
+            let area_start = reader.cursor;
+            let area = reader.take(len, "extension area")?;
+            let mut extensions = reader.sub_reader(area, area_start);
+            while !extensions.rest().is_empty() {
+                extensions.u8("extension kind")?;
+                let len = extensions.u8("extension length")? as usize;
+                extensions.take(len, "extension value")?;
+            }
+            area
+        } else {
+            &[]
+        };
+        Ok(RadioHeadersView {
+            version,
+            rec_n_frames,
+            recipients,
+            payloads,
+            sender,
+            nonce,
+            compression: flags & COMPRESSION_FLAG != 0,
+            extensions: ExtensionsView(extensions),
+        })
+    }
+
+    /// Gets the data rate of the physical frames following the first one, see
+    /// [DATA_RATE_EXTENSION].
+    pub fn data_rate(&self) -> u8 {
+        self.extensions
+            .clone()
+            .find(|(kind, _)| *kind == DATA_RATE_EXTENSION)
+            .and_then(|(_, value)| value.first().copied())
+            .unwrap_or(0)
+    }
+
+    /// Gets the encoding of the acknowledgments and payloads following these headers.
+    fn body_encoding(&self) -> BodyEncoding {
+        BodyEncoding {
+            compact: self.version == COMPACT_VERSION,
+            compression: self.compression,
+            nonce: self.nonce,
+        }
+    }
+
+    /// Copies the view into owned radio headers.
+    pub fn to_headers(&self) -> RadioHeaders {
+        RadioHeaders {
+            version: self.version,
+            rec_n_frames: self.rec_n_frames,
+            recipients: self.recipients.to_header(),
+            payloads: self.payloads,
+            sender: self.sender,
+            nonce: self.nonce,
+            compression: self.compression,
+            extensions: self
+                .extensions
+                .clone()
+                .map(|(kind, value)| Extension {
+                    kind,
+                    value: value.to_vec(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> RadioFrameView<'a> {
+    /// Builds a frame view over its byte/network representation, validating the whole frame.
+    pub fn try_from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), FrameError> {
+        let mut reader = FrameReader::new(bytes);
+        let headers = RadioHeadersView::read(&mut reader)?;
+        let encoding = headers.body_encoding();
+        let ack_count = reader.u8("number of acknowledgments")? as usize;
+        let start = reader.cursor;
+        for _i in 0..ack_count {
+            reader.acknowledgment(encoding)?;
+        }
+        let acknowledgments = &bytes[start..reader.cursor];
+        let start = reader.cursor;
+        let mut compressed = PayloadFlag::default();
+        for id in 0..headers.payloads {
+            let payload_start = reader.cursor;
+            if reader.payload(encoding)?.1 {
+                if id >= 16 {
+                    return Err(reader.malformed(
+                        payload_start,
+                        format!("Compressed payload {} out of range.", id),
+                    ));
+                }
+                compressed.push(id);
+            }
+        }
+        let payloads = &bytes[start..reader.cursor];
+        Ok((
+            RadioFrameView {
+                headers,
+                acknowledgments,
+                ack_count,
+                payloads,
+                compressed,
+            },
+            reader.cursor,
+        ))
+    }
+
+    /// Gets the acknowledgments carried by the frame.
+    pub fn acknowledgments(&self) -> AcknowledgmentsView<'a> {
+        AcknowledgmentsView {
+            reader: FrameReader::new(self.acknowledgments),
+            encoding: self.headers.body_encoding(),
+            remaining: self.ack_count,
+        }
+    }
+
+    /// Gets the payloads of the frame.
+    pub fn payloads(&self) -> PayloadsView<'a> {
+        PayloadsView {
+            reader: FrameReader::new(self.payloads),
+            encoding: self.headers.body_encoding(),
+        }
+    }
+
+    /// Gets the payload with the given message id.
+    pub fn payload(&self, id: u8) -> Option<&'a [u8]> {
+        self.payloads().nth(id as usize)
+    }
+
+    /// Gets the compressed payloads of the frame.
+    pub fn compressed(&self) -> PayloadFlag {
+        self.compressed
+    }
+
+    /// Gets a copy of the payload with the given message id, decompressed if needed.
+    pub fn decompressed_payload(&self, id: u8) -> Option<Result<Vec<u8>, FrameError>> {
+        let payload = self.payload(id)?;
+        if !self.compressed.contains(id) {
+            return Some(Ok(payload.to_vec()));
+        }
+        Some(
+            compression::decompress(payload)
+                .map_err(|source| FrameError::InvalidPayload { id, source }),
+        )
+    }
+
+    /// Copies the view into an owned frame.
+    pub fn to_frame(&self) -> RadioFrameWithHeaders {
+        RadioFrameWithHeaders {
+            headers: self.headers.to_headers(),
+            acknowledgments: self.acknowledgments().collect(),
+            payloads: self.payloads().map(<[u8]>::to_vec).collect(),
+            compressed: self.compressed,
+        }
+    }
+}
+
+/// Reader over the byte/network representation of a frame component.
+#[derive(Clone, Debug)]
+struct FrameReader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+    /// Position of `bytes` in the whole component, for the error offsets.
+    offset: usize,
+}
+
+impl<'a> FrameReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            cursor: 0,
+            offset: 0,
+        }
+    }
+
+    /// Builds a reader over a part of the bytes of this reader, which starts at byte `start`.
+    fn sub_reader(&self, bytes: &'a [u8], start: usize) -> Self {
+        Self {
+            bytes,
+            cursor: 0,
+            offset: self.offset + start,
+        }
+    }
+
+    /// Builds the error of invalid bytes at byte `at` of this reader.
+    fn malformed(&self, at: usize, context: String) -> FrameError {
+        FrameError::Malformed {
+            offset: self.offset + at,
+            context,
+        }
+    }
+
+    /// Gets the bytes that were not read yet.
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.cursor..]
+    }
+
+    /// Reads the next `n` bytes, `what` names them in the error.
+    fn take(&mut self, n: usize, what: &str) -> Result<&'a [u8], FrameError> {
+        let Some(bytes) = self
+            .cursor
+            .checked_add(n)
+            .and_then(|end| self.bytes.get(self.cursor..end))
+        else {
+            return Err(self.malformed(
+                self.cursor,
+                format!(
+                    "Fail to read {} ({} bytes needed, {} available)!",
+                    what,
+                    n,
+                    self.bytes.len() - self.cursor
+                ),
+            ));
+        };
+        self.cursor += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self, what: &str) -> Result<u8, FrameError> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn u16(&mut self, what: &str) -> Result<u16, FrameError> {
+        let bytes = self.take(2, what)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u64(&mut self, what: &str) -> Result<u64, FrameError> {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(self.take(8, what)?);
+        Ok(u64::from_be_bytes(raw))
+    }
+
+    /// Reads a LEB128 varint, rejecting the overlong encodings.
+    fn varint(&mut self, what: &str) -> Result<u64, FrameError> {
+        let start = self.cursor;
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8(what)?;
+            let bits = (byte & 0x7F) as u64;
+            // The last byte of an overlong encoding is 0, the tenth byte only holds one bit.
+            if (byte == 0 && shift > 0) || (shift == 63 && byte > 1) {
+                break;
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.malformed(start, format!("Invalid varint {}!", what)))
+    }
+
+    fn acknowledgment(
+        &mut self,
+        encoding: BodyEncoding,
+    ) -> Result<(AddressHeader, FrameNonce, i16), FrameError> {
+        let ah = AddressHeader::from(self.u16("acknowledgment address")?);
+        if encoding.compact {
+            // Only the encoding written by the nodes is accepted, so a frame is re-encoded as is.
+            let start = self.cursor;
+            let nonce = match self.varint("acknowledgment nonce")? {
+                ABSOLUTE_NONCE => {
+                    let nonce = self.u64("acknowledgment nonce")?;
+                    if relative_nonce(encoding.nonce, nonce).is_some() {
+                        return Err(self.malformed(
+                            start,
+                            format!(
+                                "Acknowledgment nonce {:#x} encoded as is instead of its difference.",
+                                nonce
+                            ),
+                        ));
+                    }
+                    nonce
+                }
+                delta if varint_size(delta) > 1 + FRAME_NONCE_SIZE => {
+                    return Err(self.malformed(
+                        start,
+                        "Acknowledgment nonce difference too long, the nonce should be encoded as is."
+                            .to_owned(),
+                    ));
+                }
+                delta => encoding.nonce.wrapping_sub(unzigzag(delta) as u64),
+            };
+            let drssi = self.u8("acknowledgment RSSI delta")? as i8;
+            Ok((ah, nonce, drssi as i16))
+        } else {
+            let nonce = self.u64("acknowledgment nonce")?;
+            let drssi = self.u16("acknowledgment RSSI delta")? as i16;
+            Ok((ah, nonce, drssi))
+        }
+    }
+
+    /// Reads a payload and its compression flag.
+    fn payload(&mut self, encoding: BodyEncoding) -> Result<(&'a [u8], bool), FrameError> {
+        let start = self.cursor;
+        let (len, compressed) = if encoding.compact {
+            let mut len = self.varint("payload length")?;
+            let mut compressed = false;
+            if encoding.compression {
+                compressed = len & 1 != 0;
+                len >>= 1;
+            }
+            if len > u16::MAX as u64 {
+                return Err(self.malformed(start, format!("Payload is too long ({} bytes).", len)));
+            }
+            (len as usize, compressed)
+        } else {
+            let mut len = self.u16("payload length")?;
+            let mut compressed = false;
+            if encoding.compression {
+                compressed = len & COMPRESSED_LENGTH_FLAG != 0;
+                len &= !COMPRESSED_LENGTH_FLAG;
+            }
+            (len as usize, compressed)
+        };
+        Ok((self.take(len, "payload")?, compressed))
+    }
+}
+
+/// Gets the size of the LEB128 varint encoding of `value`.
+fn varint_size(value: u64) -> usize {
+    (64 - (value | 1).leading_zeros() as usize).div_ceil(7)
+}
+
+/// Maps a signed integer to an unsigned one, the small magnitudes to the small values.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Gets the compact encoding of an acknowledged nonce, the zigzag of its difference with the
+/// nonce of the frame, `None` if it is encoded as is (see [COMPACT_VERSION]).
+fn relative_nonce(frame_nonce: FrameNonce, nonce: FrameNonce) -> Option<u64> {
+    let delta = zigzag(frame_nonce.wrapping_sub(nonce) as i64);
+    (delta != ABSOLUTE_NONCE && varint_size(delta) <= 1 + FRAME_NONCE_SIZE).then_some(delta)
+}
+
+/// Inverse of [zigzag].
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Writer of the byte/network representation of a frame component into a caller-supplied buffer.
+struct FrameWriter<'b> {
+    buf: &'b mut [u8],
+    cursor: usize,
+}
+
+impl<'b> FrameWriter<'b> {
+    /// Builds a writer for a component of `size` bytes, fails if the buffer is too small.
+    fn new(buf: &'b mut [u8], size: usize) -> Result<Self, FrameError> {
+        if buf.len() < size {
+            return Err(FrameError::BufferTooSmall {
+                size,
+                available: buf.len(),
+            });
+        }
+        Ok(Self { buf, cursor: 0 })
+    }
+
+    fn put(&mut self, bytes: &[u8]) {
+        self.buf[self.cursor..(self.cursor + bytes.len())].copy_from_slice(bytes);
+        self.cursor += bytes.len();
+    }
 
-impl From<u8> for InfoHeader {
-    fn from(inner: u8) -> Self {
-        InfoHeader(inner)
+    fn put_u16(&mut self, value: u16) {
+        self.put(&value.to_be_bytes());
     }
-}
 
-impl Into<u8> for InfoHeader {
-    fn into(self) -> u8 {
-        self.0
+    fn put_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.put(&[(value as u8) | 0x80]);
+            value >>= 7;
+        }
+        self.put(&[value as u8]);
     }
-}
 
-impl<'a> RecipientHeader {
-    /// Builds the byte/network representation of this recipient header.
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        match self {
-            RecipientHeader::Direct(addr) => {
-                bytes.push(1_u8.to_be());
-                let addr_raw: u16 = (*addr).into();
-                bytes.append(&mut addr_raw.to_be_bytes().to_vec());
-            }
-            RecipientHeader::Group(addrs) => {
-                bytes.push((addrs.len() as u8).to_be());
-                for (a, pf) in addrs {
-                    let a_raw: u16 = (*a).into();
-                    let pf_raw: u16 = (*pf).into();
-                    bytes.append(&mut a_raw.to_be_bytes().to_vec());
-                    bytes.append(&mut pf_raw.to_be_bytes().to_vec());
+    fn put_acknowledgment(
+        &mut self,
+        (ah, nonce, drssi): &(AddressHeader, FrameNonce, i16),
+        encoding: BodyEncoding,
+    ) {
+        self.put_u16((*ah).into());
+        if encoding.compact {
+            match relative_nonce(encoding.nonce, *nonce) {
+                Some(delta) => self.put_varint(delta),
+                None => {
+                    self.put_varint(ABSOLUTE_NONCE);
+                    self.put(&nonce.to_be_bytes());
                 }
             }
+            let drssi = (*drssi).clamp(i8::MIN as i16, i8::MAX as i16) as i8;
+            self.put(&drssi.to_be_bytes());
+        } else {
+            self.put(&nonce.to_be_bytes());
+            self.put(&drssi.to_be_bytes());
         }
-        bytes
     }
 
-    /// Builds from a byte/network representation a new recipient header.
-    pub fn try_from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), FrameError> {
-        if bytes.len() < 1 {
-            return Err(FrameError::InvalidHeader {
-                context: Some(format!("Recipient header is too small (0 byte).")),
-            });
-        };
-        let nrec = u8::from_be(bytes[0]);
-        match nrec {
-            0 => Err(FrameError::InvalidHeader {
-                context: Some(format!("Recipient header with 0 recipient.")),
-            }),
-            1 => {
-                if bytes.len() < 3 {
-                    return Err(FrameError::InvalidHeader {
-                        context: Some(format!("Recipient header is too small for a Direct trame.")),
-                    });
-                };
-                let mut addr_raw = [0u8; 2];
-                addr_raw.copy_from_slice(&bytes[1..3]);
-                let addr = AddressHeader::from(u16::from_be_bytes(addr_raw));
-                Ok((RecipientHeader::Direct(addr), 3))
-            }
-            2..=16 => {
-                if bytes.len() < 1 + 4 * nrec as usize {
-                    return Err(FrameError::InvalidHeader{ context: Some(format!("Recipient header is too small for a Group trame ({} recipients and only {} bytes).", bytes[0], bytes.len()))});
-                };
-                let mut addrs = Vec::new();
-                for i in 0..(nrec as usize) {
-                    let mut addr_raw = [0u8; 2];
-                    addr_raw
-                        .copy_from_slice(&bytes[((1 + i * 4) as usize)..((3 + i * 4) as usize)]);
-                    let addr = AddressHeader::from(u16::from_be_bytes(addr_raw));
-                    let mut pf_raw = [0u8; 2];
-                    pf_raw.copy_from_slice(&bytes[((3 + i * 4) as usize)..((5 + i * 4) as usize)]);
-                    let pf = PayloadFlag::from(u16::from_be_bytes(pf_raw));
-                    addrs.push((addr, pf));
-                }
-                Ok((
-                    RecipientHeader::Group(addrs),
-                    5 + ((bytes[0] as usize) - 1) * 4,
-                ))
-            }
-            n => Err(FrameError::InvalidHeader {
-                context: Some(format!(
-                    "Recipient header with too many recipients ({}).",
-                    n
-                )),
-            }),
+    fn put_payload(&mut self, payload: &[u8], compressed: bool, encoding: BodyEncoding) {
+        if encoding.compact && encoding.compression {
+            self.put_varint(((payload.len() as u64) << 1) | compressed as u64);
+        } else if encoding.compact {
+            self.put_varint(payload.len() as u64);
+        } else if encoding.compression && compressed {
+            self.put_u16(payload.len() as u16 | COMPRESSED_LENGTH_FLAG);
+        } else {
+            self.put_u16(payload.len() as u16);
         }
+        self.put(payload);
     }
 }
 
-impl RadioHeaders {
-    /// Builds the byte/network representation of these radio headers.
+impl BeaconPayload {
+    /// Builds the byte/network representation of this beacon payload.
+    ///
+    /// Panics if it cannot be encoded, see [encode_into](BeaconPayload::encode_into).
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        let rnt_raw: u8 = self.rec_n_frames.into();
-        bytes.push(rnt_raw.to_be());
-        bytes.append(&mut self.recipients.to_bytes());
-        let src_raw: u16 = self.sender.into();
-        bytes.append(&mut src_raw.to_be_bytes().to_vec());
-        bytes.push(self.payloads.to_be());
-        let nonce_raw: u64 = self.nonce.into();
-        bytes.append(&mut nonce_raw.to_be_bytes().to_vec());
+        let mut bytes = vec![0; self.size()];
+        self.encode_into(&mut bytes)
+            .expect("Failed to encode the beacon payload!");
         bytes
     }
 
-    /// Builds the radio headers from a byte/network representation.
-    pub fn try_from_bytes<'a>(bytes: &'a [u8]) -> Result<(Self, usize), FrameError> {
-        if bytes.len() < 1 {
-            return Err(FrameError::InvalidHeader {
-                context: Some(format!("Radio header too small! (0 bytes)")),
-            });
-        };
-        let rec_n_frames = InfoHeader::from(u8::from_be(bytes[0]));
-        let (recipients, read) = RecipientHeader::try_from_bytes(&bytes[1..])?;
-        if bytes.len() < read + 3 {
-            return Err(FrameError::InvalidHeader {
-                context: Some(format!("Badly formatted frame, missing source address!")),
-            });
-        };
-        let mut src_raw = [0u8; 2];
-        src_raw.copy_from_slice(&bytes[(read + 1)..(read + 3)]);
-        let sender = AddressHeader::from(u16::from_be_bytes(src_raw));
-        if bytes.len() < read + 4 {
-            return Err(FrameError::InvalidHeader {
-                context: Some(format!(
-                    "Badly formatted frame, missing number of payloads!"
-                )),
-            });
-        };
-        let payloads = u8::from_be(bytes[read + 3]);
+    /// Writes the byte/network representation of this beacon payload into `buf`.
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, FrameError> {
+        let mut writer = FrameWriter::new(buf, self.size())?;
+        writer.put(&self.sweep.to_be_bytes());
+        writer.put(&[self.tpi, self.levels]);
+        Ok(writer.cursor)
+    }
 
-        if bytes.len() < read + 4 + FRAME_NONCE_SIZE {
-            return Err(FrameError::InvalidHeader {
-                context: Some(format!("Badly formatted frame, missing nonce!")),
-            });
-        };
-        let mut nonce_raw = [0u8; FRAME_NONCE_SIZE];
-        nonce_raw.copy_from_slice(&bytes[(read + 4)..(read + 4 + FRAME_NONCE_SIZE)]);
-        let nonce = u64::from_be_bytes(nonce_raw);
-        Ok((
-            RadioHeaders {
-                rec_n_frames,
-                recipients,
-                payloads,
-                sender,
-                nonce,
-            },
-            read + 4 + FRAME_NONCE_SIZE,
-        ))
+    /// Builds a beacon payload from its byte/network representation.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<(Self, usize), FrameError> {
+        let mut reader = FrameReader::new(bytes);
+        let sweep = reader.u64("beacon sweep")?;
+        let tpi = reader.u8("beacon power level")?;
+        let levels = reader.u8("beacon levels")?;
+        if tpi >= levels {
+            return Err(reader.malformed(
+                reader.cursor - 2,
+                format!(
+                    "Beacon power level {} is out of the {} levels of the sweep.",
+                    tpi, levels
+                ),
+            ));
+        }
+        Ok((BeaconPayload { sweep, tpi, levels }, reader.cursor))
     }
 }
 
-impl RadioFrameWithHeaders {
-    /// Builds the byte/network representation of this entire frame with its headers.
+impl BeaconAcknowledgment {
+    /// Builds the byte/network representation of this beacon acknowledgment.
+    ///
+    /// Panics if it cannot be encoded, see [encode_into](BeaconAcknowledgment::encode_into).
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = self.headers.to_bytes();
-        // Headers
-        assert!(
-            self.headers.payloads == self.payloads.len() as u8,
-            "Number of payload is invalid, not equal number in header and in frame."
-        );
-
-        // Acknowledgment
-        let ack_size: u8 = self.acknowledgments.len() as u8;
-        bytes.push(ack_size.to_be());
-        for (ah, nonce, drssi) in &self.acknowledgments {
-            let ah_raw: u16 = ah.clone().into();
-            bytes.append(&mut ah_raw.to_be_bytes().to_vec());
-            let nonce_raw: u64 = nonce.clone().into();
-            bytes.append(&mut nonce_raw.to_be_bytes().to_vec());
-            bytes.append(&mut drssi.to_be_bytes().to_vec());
-        }
-
-        // Payloads
-        for pl in &self.payloads {
-            bytes.append(&mut (pl.len() as u16).to_be_bytes().to_vec());
-            bytes.append(&mut pl.into_iter().map(|b| b.to_be()).collect());
-        }
+        let mut bytes = vec![0; self.size()];
+        self.encode_into(&mut bytes)
+            .expect("Failed to encode the beacon acknowledgment!");
         bytes
     }
 
-    /// Builds a radio frame and its headers from its byte/network representation.
-    pub fn try_from_bytes<'a>(bytes: &'a [u8]) -> Result<(Self, usize), FrameError> {
-        let (headers, read) = RadioHeaders::try_from_bytes(bytes)?;
-        let mut cursor = read;
-        let mut payloads = Vec::new();
-        let mut acknowledgments = Vec::new();
-        let ack_size = u8::from_be(bytes[cursor]);
-        cursor += 1;
-        for _i in 0..(ack_size as usize) {
-            if bytes.len() < cursor + 2 + FRAME_NONCE_SIZE {
-                // TODO: Remove hardcoded constant
-                return Err(FrameError::InvalidHeader {
-                    context: Some(format!("Fail to read acknowledgment at byte {}!", cursor)),
-                });
-            };
-            let mut ah_raw = [0u8; 2];
-            ah_raw.copy_from_slice(&bytes[(cursor)..(cursor + 2)]);
-            let ah = AddressHeader::from(u16::from_be_bytes(ah_raw));
-            let mut nonce_raw = [0u8; FRAME_NONCE_SIZE];
-            nonce_raw.copy_from_slice(&bytes[(cursor + 2)..(cursor + 2 + FRAME_NONCE_SIZE)]);
-            let nonce = u64::from_be_bytes(nonce_raw);
-            let mut drssi_raw = [0u8; 2];
-            drssi_raw.copy_from_slice(
-                &bytes[(cursor + 2 + FRAME_NONCE_SIZE)..(cursor + 2 + FRAME_NONCE_SIZE + 2)],
-            );
-            let drssi = i16::from_be_bytes(drssi_raw);
-            acknowledgments.push((ah, nonce, drssi));
-            cursor += 2 + FRAME_NONCE_SIZE + 2;
-        }
-        for _i in 0..(headers.payloads as usize) {
-            if bytes.len() < cursor {
-                return Err(FrameError::InvalidHeader {
-                    context: Some(format!("Fail to read payload length at byte {}!", cursor)),
-                });
-            };
-            let mut len_raw = [0u8; 2];
-            len_raw.copy_from_slice(&bytes[cursor..(cursor + 2)]);
-            let len = u16::from_be_bytes(len_raw) as usize;
-            if bytes.len() < cursor + len {
-                return Err(FrameError::InvalidHeader {
-                    context: Some(format!("Fail to read payload at byte {}!", cursor + len)),
-                });
-            };
-            let payload: Vec<u8> = bytes[(cursor + 2)..(cursor + 2 + len)]
-                .iter()
-                .map(|b| u8::from_be(*b))
-                .collect();
-            cursor = cursor + 2 + len;
-            payloads.push(payload);
+    /// Writes the byte/network representation of this beacon acknowledgment into `buf`.
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, FrameError> {
+        let mut writer = FrameWriter::new(buf, self.size())?;
+        writer.put_u16(self.sender.into());
+        writer.put_u16(self.recipient.into());
+        writer.put(&self.sweep.to_be_bytes());
+        writer.put(&[self.rssi.len() as u8]);
+        for rssi in &self.rssi {
+            let rssi = rssi
+                .map(|r| (r + BEACON_RSSI_OFFSET).clamp(1, u8::MAX as i16) as u8)
+                .unwrap_or(BEACON_MISSING_RSSI);
+            writer.put(&[rssi]);
         }
+        Ok(writer.cursor)
+    }
+
+    /// Builds a beacon acknowledgment from its byte/network representation.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<(Self, usize), FrameError> {
+        let mut reader = FrameReader::new(bytes);
+        let sender = AddressHeader::from(reader.u16("beacon acknowledgment sender")?);
+        let recipient = AddressHeader::from(reader.u16("beacon acknowledgment recipient")?);
+        let sweep = reader.u64("beacon acknowledgment sweep")?;
+        let levels = reader.u8("beacon acknowledgment levels")? as usize;
+        let rssi = reader
+            .take(levels, "beacon acknowledgment RSSI")?
+            .iter()
+            .map(|b| match *b {
+                BEACON_MISSING_RSSI => None,
+                r => Some(r as i16 - BEACON_RSSI_OFFSET),
+            })
+            .collect();
         Ok((
-            RadioFrameWithHeaders {
-                headers,
-                acknowledgments,
-                payloads,
+            BeaconAcknowledgment {
+                sender,
+                recipient,
+                sweep,
+                rssi,
             },
-            cursor,
+            reader.cursor,
         ))
     }
 }
@@ -491,11 +1473,46 @@ pub enum FrameError {
     #[error("Invalid header. Context: {}", .context.as_ref().unwrap_or(&"<none>".to_owned()))]
     InvalidHeader { context: Option<String> },
 
+    /// The byte/network representation of a frame component is invalid at the given byte.
+    #[error("Malformed frame component at byte {offset}: {context}")]
+    Malformed { offset: usize, context: String },
+
+    /// The frame uses a version of the frame format that this node does not support.
+    #[error(
+        "Unsupported frame format version {version} (supported: {} and {}).",
+        PROTOCOL_VERSION,
+        COMPACT_VERSION
+    )]
+    UnsupportedVersion { version: u8 },
+
+    /// A compressed payload cannot be decompressed.
+    #[error("Invalid compressed payload {id}: {source}")]
+    InvalidPayload {
+        id: u8,
+        #[source]
+        source: CompressionError,
+    },
+
+    /// The buffer is too small for the byte/network representation of a frame component.
+    #[error("Buffer too small for the frame component ({available}B, {size}B needed).")]
+    BufferTooSmall { size: usize, available: usize },
+
     /// Unknown frame error.
     #[error("Unknown frame error. Context: {}", context)]
     Unknown { context: String },
 }
 
+impl FrameError {
+    /// Gets the byte of the parsed representation where the error was detected, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            FrameError::Malformed { offset, .. } => Some(*offset),
+            FrameError::UnsupportedVersion { .. } => Some(0),
+            _ => None,
+        }
+    }
+}
+
 impl FrameSize for u8 {
     fn size(&self) -> usize {
         1
@@ -540,23 +1557,49 @@ impl FrameSize for Vec<Payload> {
 
 impl FrameSize for Vec<(AddressHeader, FrameNonce, i16)> {
     fn size(&self) -> usize {
-        self.len() * (2 + FRAME_NONCE_SIZE + 2)
+        self.len() * ACKNOWLEDGMENT_SIZE
     }
 }
 
 impl FrameSize for RadioHeaders {
     fn size(&self) -> usize {
-        self.rec_n_frames.size()
+        let extensions = if self.extensions.is_empty() {
+            0
+        } else {
+            1 + self.extensions_size()
+        };
+        let nonce = if self.is_compact() {
+            varint_size(self.nonce)
+        } else {
+            FRAME_NONCE_SIZE
+        };
+        1 + self.rec_n_frames.size()
             + self.recipients.size()
             + self.payloads.size()
             + self.sender.size()
-            + FRAME_NONCE_SIZE
+            + nonce
+            + extensions
     }
 }
 
 impl FrameSize for RadioFrameWithHeaders {
     fn size(&self) -> usize {
-        self.headers.size() + self.payloads.size() + self.acknowledgments.size()
+        self.headers.size()
+            + 1
+            + self.headers.acknowledgments_size(&self.acknowledgments)
+            + self.headers.payloads_size(&self.payloads)
+    }
+}
+
+impl FrameSize for BeaconPayload {
+    fn size(&self) -> usize {
+        FRAME_NONCE_SIZE + 2
+    }
+}
+
+impl FrameSize for BeaconAcknowledgment {
+    fn size(&self) -> usize {
+        self.sender.size() + self.recipient.size() + FRAME_NONCE_SIZE + 1 + self.rssi.len()
     }
 }
 
@@ -857,6 +1900,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn frame_encode_group_address_header() {
+        let ah1 = AddressHeader::new_group(0, false);
+        let ah2 = AddressHeader::new_group(MAX_GROUP_ID, true);
+        let ah3 = AddressHeader::new(0b0000_0000_0000_0001, false);
+        let ahg1 = AddressHeader::new_global(true);
+
+        assert_eq!(ah1.0, 0b0111_0000_0000_0000);
+        assert_eq!(ah2.0, 0b1111_1111_1111_1110);
+        assert!(ah1.is_group(), "ah1 is not recognized as a group address!");
+        assert!(ah2.is_group(), "ah2 is not recognized as a group address!");
+        assert!(!ah3.is_group(), "ah3 is recognized as a group address!");
+        assert!(!ahg1.is_group(), "ahg1 is recognized as a group address!");
+        assert!(!ah1.is_global(), "ah1 is recognized as a global address!");
+        assert!(!ah2.is_global(), "ah2 is recognized as a global address!");
+        assert!(
+            ah3.is_unicast(),
+            "ah3 is not recognized as a unicast address!"
+        );
+        assert!(!ah1.is_unicast(), "ah1 is recognized as a unicast address!");
+        assert!(
+            !ahg1.is_unicast(),
+            "ahg1 is recognized as a unicast address!"
+        );
+
+        assert_eq!(ah1.get_group(), Some(0));
+        assert_eq!(ah2.get_group(), Some(MAX_GROUP_ID));
+        assert_eq!(ah3.get_group(), None);
+        assert_eq!(ahg1.get_group(), None);
+        assert!(ah2.get_acknowledgment(), "ah2 requires an acknowledgment!");
+    }
+
     #[test]
     fn frame_encode_payload_flag() {
         let pf1 = PayloadFlag::new(&[1, 4, 6, 9, 15]);
@@ -992,23 +2067,27 @@ mod tests {
         let sh1 = AddressHeader::new(0b00000000_00000001, false);
         let rh1 = RecipientHeader::Direct(ah1);
         let h1 = RadioHeaders {
+            version: PROTOCOL_VERSION,
             rec_n_frames: ih1,
             recipients: rh1,
             payloads: 1,
             sender: sh1,
             nonce: 0x0102030405060708,
+            compression: false,
+            extensions: Vec::new(),
         };
         let hb1 = h1.to_bytes();
         //assert_eq!(1, 0, "hb1: {:?}", hb1);
-        assert_eq!(hb1[00], 0b0001_0001); // InfoHeader part
-        assert_eq!(hb1[01], 0b00000001); // start of the RecipientHeader (number of recipients)
-        assert_eq!(hb1[02], 0b00000000); // RecipientHeader > start of the recipient address
-        assert_eq!(hb1[03], 0b00000010); // RecipientHeader > end of the recipient address
-        assert_eq!(hb1[04], 0b00000000); // start of the sender address
-        assert_eq!(hb1[05], 0b00000001); // end of the sender address
-        assert_eq!(hb1[06], 0b00000001); // Number of payload
+        assert_eq!(hb1[00], 0b0001_0000); // Version, without extension
+        assert_eq!(hb1[01], 0b0001_0001); // InfoHeader part
+        assert_eq!(hb1[02], 0b00000001); // start of the RecipientHeader (number of recipients)
+        assert_eq!(hb1[03], 0b00000000); // RecipientHeader > start of the recipient address
+        assert_eq!(hb1[04], 0b00000010); // RecipientHeader > end of the recipient address
+        assert_eq!(hb1[05], 0b00000000); // start of the sender address
+        assert_eq!(hb1[06], 0b00000001); // end of the sender address
+        assert_eq!(hb1[07], 0b00000001); // Number of payload
         let mut nonce_raw = [0u8; 8];
-        nonce_raw.copy_from_slice(&hb1[7..15]);
+        nonce_raw.copy_from_slice(&hb1[8..16]);
         assert_eq!(u64::from_be_bytes(nonce_raw), 0x0102030405060708); // nonce
     }
 
@@ -1019,11 +2098,14 @@ mod tests {
         let sh1 = AddressHeader::new(0b00000000_00000001, false);
         let rh1 = RecipientHeader::Direct(ah1);
         let h1 = RadioHeaders {
+            version: PROTOCOL_VERSION,
             rec_n_frames: ih1,
             recipients: rh1,
             payloads: 1,
             sender: sh1,
             nonce: 0x0102030405060708,
+            compression: false,
+            extensions: Vec::new(),
         };
         let hb1 = h1.to_bytes();
 
@@ -1045,34 +2127,39 @@ mod tests {
         let sh1 = AddressHeader::new(0b00000000_00000001, false);
         let rh1 = RecipientHeader::Direct(ah1);
         let h1 = RadioHeaders {
+            version: PROTOCOL_VERSION,
             rec_n_frames: ih1,
             recipients: rh1,
             payloads: 1,
             sender: sh1,
             nonce: 0x0102030405060708,
+            compression: false,
+            extensions: Vec::new(),
         };
         let pl1: Vec<u8> = "HELO!".as_bytes().to_owned();
         let rf1 = RadioFrameWithHeaders {
             headers: h1,
             acknowledgments: vec![],
             payloads: vec![pl1.clone()],
+            compressed: PayloadFlag::default(),
         };
         let rfb1 = rf1.to_bytes();
         //assert_eq!(1, 0, "hb1: {:?}", hb1);
-        assert_eq!(rfb1[00], 0b0001_0001); // InfoHeader part
-        assert_eq!(rfb1[01], 0b00000001); // start of the RecipientHeader (number of recipients)
-        assert_eq!(rfb1[02], 0b00000000); // RecipientHeader > start of the recipient address
-        assert_eq!(rfb1[03], 0b00000010); // RecipientHeader > end of the recipient address
-        assert_eq!(rfb1[04], 0b00000000); // start of the sender address
-        assert_eq!(rfb1[05], 0b00000001); // end of the sender address
-        assert_eq!(rfb1[06], 0b00000001); // Number of payload
+        assert_eq!(rfb1[00], 0b0001_0000); // Version, without extension
+        assert_eq!(rfb1[01], 0b0001_0001); // InfoHeader part
+        assert_eq!(rfb1[02], 0b00000001); // start of the RecipientHeader (number of recipients)
+        assert_eq!(rfb1[03], 0b00000000); // RecipientHeader > start of the recipient address
+        assert_eq!(rfb1[04], 0b00000010); // RecipientHeader > end of the recipient address
+        assert_eq!(rfb1[05], 0b00000000); // start of the sender address
+        assert_eq!(rfb1[06], 0b00000001); // end of the sender address
+        assert_eq!(rfb1[07], 0b00000001); // Number of payload
         let mut nonce_raw = [0u8; 8];
-        nonce_raw.copy_from_slice(&rfb1[7..15]);
+        nonce_raw.copy_from_slice(&rfb1[8..16]);
         assert_eq!(u64::from_be_bytes(nonce_raw), 0x0102030405060708); // nonce
-        assert_eq!(rfb1[15], 0); // Acknowledgment size (0)
-        assert_eq!(rfb1[16], 0); // Length of the first payload (part1)
-        assert_eq!(rfb1[17], 5); // Length of the first payload (part2) -- here 5
-        let pld1: Vec<u8> = rfb1[18..(18 + 5)].iter().map(|b| u8::from_be(*b)).collect();
+        assert_eq!(rfb1[16], 0); // Acknowledgment size (0)
+        assert_eq!(rfb1[17], 0); // Length of the first payload (part1)
+        assert_eq!(rfb1[18], 5); // Length of the first payload (part2) -- here 5
+        let pld1: Vec<u8> = rfb1[19..(19 + 5)].iter().map(|b| u8::from_be(*b)).collect();
         assert_eq!(pld1, pl1);
     }
 
@@ -1083,17 +2170,21 @@ mod tests {
         let sh1 = AddressHeader::new(0b00000000_00000001, false);
         let rh1 = RecipientHeader::Direct(ah1);
         let h1 = RadioHeaders {
+            version: PROTOCOL_VERSION,
             rec_n_frames: ih1,
             recipients: rh1,
             payloads: 1,
             sender: sh1,
             nonce: 0x0102030405060708,
+            compression: false,
+            extensions: Vec::new(),
         };
         let pl1: Vec<u8> = "HELO!".as_bytes().to_owned();
         let rf1 = RadioFrameWithHeaders {
             headers: h1.clone(),
             acknowledgments: vec![],
             payloads: vec![pl1.clone()],
+            compressed: PayloadFlag::default(),
         };
         let rfb1 = rf1.to_bytes();
 
@@ -1124,52 +2215,57 @@ mod tests {
         let sh1 = AddressHeader::new(0b00000000_00000001, false);
         let rh1 = RecipientHeader::Direct(ah1);
         let h1 = RadioHeaders {
+            version: PROTOCOL_VERSION,
             rec_n_frames: ih1,
             recipients: rh1,
             payloads: 1,
             sender: sh1,
             nonce: 0x0102030405060708,
+            compression: false,
+            extensions: Vec::new(),
         };
         let pl1: Vec<u8> = "HELO!".as_bytes().to_owned();
         let rf1 = RadioFrameWithHeaders {
             headers: h1,
             acknowledgments: vec![(ah2, nonce1, drssi1), (ah3, nonce2, drssi2)],
             payloads: vec![pl1.clone()],
+            compressed: PayloadFlag::default(),
         };
         let rfb1 = rf1.to_bytes();
         //assert_eq!(1, 0, "hb1: {:?}", hb1);
-        assert_eq!(rfb1[00], 0b0001_0001); // InfoHeader part
-        assert_eq!(rfb1[01], 0b00000001); // start of the RecipientHeader (number of recipients)
-        assert_eq!(rfb1[02], 0b00000000); // RecipientHeader > start of the recipient address
-        assert_eq!(rfb1[03], 0b00000010); // RecipientHeader > end of the recipient address
-        assert_eq!(rfb1[04], 0b00000000); // start of the sender address
-        assert_eq!(rfb1[05], 0b00000001); // end of the sender address
-        assert_eq!(rfb1[06], 0b00000001); // Number of payload
+        assert_eq!(rfb1[00], 0b0001_0000); // Version, without extension
+        assert_eq!(rfb1[01], 0b0001_0001); // InfoHeader part
+        assert_eq!(rfb1[02], 0b00000001); // start of the RecipientHeader (number of recipients)
+        assert_eq!(rfb1[03], 0b00000000); // RecipientHeader > start of the recipient address
+        assert_eq!(rfb1[04], 0b00000010); // RecipientHeader > end of the recipient address
+        assert_eq!(rfb1[05], 0b00000000); // start of the sender address
+        assert_eq!(rfb1[06], 0b00000001); // end of the sender address
+        assert_eq!(rfb1[07], 0b00000001); // Number of payload
         let mut nonce_raw = [0u8; 8];
-        nonce_raw.copy_from_slice(&rfb1[7..15]);
+        nonce_raw.copy_from_slice(&rfb1[8..16]);
         assert_eq!(u64::from_be_bytes(nonce_raw), 0x0102030405060708); // nonce
-        assert_eq!(rfb1[15], 2); // Acknowledgment size (0)
+        assert_eq!(rfb1[16], 2); // Acknowledgment size (0)
         let mut ah2_raw = [0u8; 2];
-        ah2_raw.copy_from_slice(&rfb1[16..18]);
+        ah2_raw.copy_from_slice(&rfb1[17..19]);
         assert_eq!(u16::from_be_bytes(ah2_raw), ah2.0); // address 2
         let mut nonce1_raw = [0u8; 8];
-        nonce1_raw.copy_from_slice(&rfb1[18..26]);
+        nonce1_raw.copy_from_slice(&rfb1[19..27]);
         assert_eq!(u64::from_be_bytes(nonce1_raw), nonce1); // nonce 1
         let mut drssi1_raw = [0u8; 2];
-        drssi1_raw.copy_from_slice(&rfb1[26..28]);
+        drssi1_raw.copy_from_slice(&rfb1[27..29]);
         assert_eq!(i16::from_be_bytes(drssi1_raw), drssi1); // drssi 1
         let mut ah3_raw = [0u8; 2];
-        ah3_raw.copy_from_slice(&rfb1[28..30]);
+        ah3_raw.copy_from_slice(&rfb1[29..31]);
         assert_eq!(u16::from_be_bytes(ah3_raw), ah3.0); // address 3
         let mut nonce2_raw = [0u8; 8];
-        nonce2_raw.copy_from_slice(&rfb1[30..38]);
+        nonce2_raw.copy_from_slice(&rfb1[31..39]);
         assert_eq!(u64::from_be_bytes(nonce2_raw), nonce2); // nonce 2
         let mut drssi2_raw = [0u8; 2];
-        drssi2_raw.copy_from_slice(&rfb1[38..40]);
+        drssi2_raw.copy_from_slice(&rfb1[39..41]);
         assert_eq!(i16::from_be_bytes(drssi2_raw), drssi2); // drssi 2
-        assert_eq!(rfb1[40], 0); // Length of the first payload (part1)
-        assert_eq!(rfb1[41], 5); // Length of the first payload (part2) -- here 5
-        let pld1: Vec<u8> = rfb1[42..(42 + 5)].iter().map(|b| u8::from_be(*b)).collect();
+        assert_eq!(rfb1[41], 0); // Length of the first payload (part1)
+        assert_eq!(rfb1[42], 5); // Length of the first payload (part2) -- here 5
+        let pld1: Vec<u8> = rfb1[43..(43 + 5)].iter().map(|b| u8::from_be(*b)).collect();
         assert_eq!(pld1, pl1);
     }
 
@@ -1186,17 +2282,21 @@ mod tests {
         let sh1 = AddressHeader::new(0b00000000_00000001, false);
         let rh1 = RecipientHeader::Direct(ah1);
         let h1 = RadioHeaders {
+            version: PROTOCOL_VERSION,
             rec_n_frames: ih1,
             recipients: rh1,
             payloads: 1,
             sender: sh1,
             nonce: 0x0102030405060708,
+            compression: false,
+            extensions: Vec::new(),
         };
         let pl1: Vec<u8> = "HELO!".as_bytes().to_owned();
         let rf1 = RadioFrameWithHeaders {
             headers: h1.clone(),
             acknowledgments: vec![(ah2, nonce1, drssi1), (ah3, nonce2, drssi2)],
             payloads: vec![pl1.clone()],
+            compressed: PayloadFlag::default(),
         };
         let rfb1 = rf1.to_bytes();
 
@@ -1219,4 +2319,719 @@ mod tests {
         assert_eq!(rfd1.acknowledgments[1].2, drssi2);
         assert_eq!(rfd1.payloads[0], pl1);
     }
+
+    #[test]
+    fn frame_encode_beacon_payload() {
+        let bp1 = BeaconPayload {
+            sweep: 0x0102030405060708,
+            tpi: 2,
+            levels: 5,
+        };
+        let bytes = bp1.to_bytes();
+        assert_eq!(bytes.len(), bp1.size());
+        let (bpd1, read) = BeaconPayload::try_from_bytes(&bytes).expect("Failed to parse beacon");
+        assert_eq!(read, bytes.len());
+        assert_eq!(bpd1, bp1);
+
+        let mut invalid = bytes.clone();
+        invalid[FRAME_NONCE_SIZE] = 5;
+        assert!(BeaconPayload::try_from_bytes(&invalid).is_err());
+        assert!(BeaconPayload::try_from_bytes(&bytes[..FRAME_NONCE_SIZE]).is_err());
+    }
+
+    #[test]
+    fn frame_encode_beacon_acknowledgment() {
+        let ba1 = BeaconAcknowledgment {
+            sender: AddressHeader::new(0x0042, false),
+            recipient: AddressHeader::new(0x0001, false),
+            sweep: 0xdead_beef_cafe,
+            rssi: vec![None, Some(-120), Some(-98), Some(-67), Some(3)],
+        };
+        let bytes = ba1.to_bytes();
+        assert_eq!(bytes.len(), ba1.size());
+        let (bad1, read) = BeaconAcknowledgment::try_from_bytes(&bytes)
+            .expect("Failed to parse beacon acknowledgment");
+        assert_eq!(read, bytes.len());
+        assert_eq!(bad1, ba1);
+
+        // The weakest beacons of a SX127x are not confused with missed beacons.
+        let ba2 = BeaconAcknowledgment {
+            rssi: vec![Some(-130), Some(-137), None],
+            ..ba1.clone()
+        };
+        let bytes2 = ba2.to_bytes();
+        assert_eq!(bytes2[bytes2.len() - 3..], [34, 27, 0]);
+        let (bad2, _) = BeaconAcknowledgment::try_from_bytes(&bytes2)
+            .expect("Failed to parse beacon acknowledgment");
+        assert_eq!(bad2, ba2);
+        // The value reserved to missed beacons is never produced by a received beacon.
+        let ba3 = BeaconAcknowledgment {
+            rssi: vec![Some(-200)],
+            ..ba1.clone()
+        };
+        let (bad3, _) = BeaconAcknowledgment::try_from_bytes(&ba3.to_bytes())
+            .expect("Failed to parse beacon acknowledgment");
+        assert_eq!(bad3.rssi, vec![Some(1 - BEACON_RSSI_OFFSET)]);
+        assert!(BeaconAcknowledgment::try_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    fn group_frame() -> RadioFrameWithHeaders {
+        let ah1 = AddressHeader::new(0x0002, true);
+        let ah2 = AddressHeader::new(0x0003, false);
+        RadioFrameWithHeaders {
+            headers: RadioHeaders {
+                version: PROTOCOL_VERSION,
+                rec_n_frames: InfoHeader::new(2, 1),
+                recipients: RecipientHeader::Group(vec![
+                    (ah1, PayloadFlag::new(&[0, 2])),
+                    (ah2, PayloadFlag::new(&[1])),
+                ]),
+                payloads: 3,
+                sender: AddressHeader::new(0x0001, false),
+                nonce: 0x0102030405060708,
+                compression: false,
+                extensions: Vec::new(),
+            },
+            acknowledgments: vec![(AddressHeader::new(0x0004, false), 0xcdead, -12)],
+            payloads: vec![b"HELO!".to_vec(), Vec::new(), b"WORLD".to_vec()],
+            compressed: PayloadFlag::default(),
+        }
+    }
+
+    #[test]
+    fn frame_view_radio_frame() {
+        let rf1 = group_frame();
+        let rfb1 = rf1.to_bytes();
+        let (view, read) = RadioFrameView::try_from_bytes(&rfb1).expect("Failed to view frame");
+        assert_eq!(read, rfb1.len());
+        assert_eq!(view.headers.sender, rf1.headers.sender);
+        assert_eq!(view.headers.nonce, rf1.headers.nonce);
+        assert!(view
+            .headers
+            .recipients
+            .contains(&AddressHeader::new(0x0003, false)));
+        assert!(!view
+            .headers
+            .recipients
+            .contains(&AddressHeader::new(0x0003, true)));
+        match &view.headers.recipients {
+            RecipientsView::Group(ahs) => assert_eq!(ahs.len(), 2),
+            _ => panic!("expected a group of recipients"),
+        }
+        assert_eq!(
+            view.acknowledgments().collect::<Vec<_>>(),
+            rf1.acknowledgments
+        );
+        // Payloads are borrowed from the received bytes.
+        let pl2 = view.payload(2).expect("missing payload");
+        assert_eq!(pl2, b"WORLD");
+        assert!(rfb1.as_ptr_range().contains(&pl2.as_ptr()));
+        assert_eq!(view.payload(1), Some(&[][..]));
+        assert_eq!(view.payload(3), None);
+        assert_eq!(view.payloads().count(), 3);
+        assert_eq!(view.to_frame().to_bytes(), rfb1);
+    }
+
+    #[test]
+    fn frame_encode_into() {
+        let rf1 = group_frame();
+        let mut buf = [0u8; 256];
+        let written = rf1.encode_into(&mut buf).expect("Failed to encode frame");
+        assert_eq!(written, rf1.size());
+        assert_eq!(&buf[..written], &rf1.to_bytes()[..]);
+        match rf1.encode_into(&mut buf[..written - 1]) {
+            Err(FrameError::BufferTooSmall { size, available }) => {
+                assert_eq!((size, available), (written, written - 1))
+            }
+            res => panic!("expected a too small buffer, got {:?}", res),
+        }
+        let mut rf2 = rf1.clone();
+        rf2.headers.payloads = 2;
+        assert!(rf2.encode_into(&mut buf).is_err());
+    }
+
+    #[test]
+    fn frame_view_truncated_frame() {
+        let rfb1 = group_frame().to_bytes();
+        for len in 0..rfb1.len() {
+            assert!(
+                RadioFrameView::try_from_bytes(&rfb1[..len]).is_err(),
+                "a frame truncated to {} bytes was accepted",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn frame_extensions() {
+        let mut rf1 = group_frame();
+        rf1.headers.extensions = vec![
+            Extension {
+                kind: 0x42,
+                value: b"unknown".to_vec(),
+            },
+            Extension {
+                kind: 0x01,
+                value: Vec::new(),
+            },
+        ];
+        let rfb1 = rf1.to_bytes();
+        assert_eq!(rfb1.len(), group_frame().size() + 1 + 9 + 2);
+        assert_eq!(rfb1[0], (PROTOCOL_VERSION << 4) | EXTENSIONS_FLAG);
+        let (view, read) = RadioFrameView::try_from_bytes(&rfb1).expect("Failed to view frame");
+        assert_eq!(read, rfb1.len());
+        assert_eq!(
+            view.headers.extensions.clone().collect::<Vec<_>>(),
+            vec![(0x42, &b"unknown"[..]), (0x01, &[][..])]
+        );
+        // The extensions are skipped to reach the acknowledgments and the payloads.
+        assert_eq!(view.payload(2), Some(&b"WORLD"[..]));
+        let rfd1 = view.to_frame();
+        assert_eq!(rfd1.headers.extensions, rf1.headers.extensions);
+        assert_eq!(rfd1.headers.extension(0x42), Some(&b"unknown"[..]));
+        assert_eq!(rfd1.headers.extension(0x43), None);
+
+        // An extension overflowing the extension area is rejected.
+        let start = group_frame().headers.size() + 1;
+        let mut invalid = rfb1.clone();
+        invalid[start + 1] = 8;
+        assert!(RadioFrameView::try_from_bytes(&invalid).is_err());
+
+        let mut rf2 = group_frame();
+        rf2.headers.extensions = vec![Extension {
+            kind: 0x42,
+            value: vec![0; 256],
+        }];
+        assert!(rf2.encode_into(&mut [0u8; 512]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to encode the frame!")]
+    fn frame_to_bytes_invalid() {
+        let mut rf1 = group_frame();
+        rf1.headers.extensions = vec![Extension {
+            kind: 0x42,
+            value: vec![0; 256],
+        }];
+        rf1.to_bytes();
+    }
+
+    #[test]
+    fn frame_compact_skewed_clocks() {
+        let ts: u64 = 1_792_000_000;
+        let nonce = (ts << 16) | 0xbeef;
+        let mut rf1 = group_frame();
+        rf1.headers.nonce = nonce;
+        rf1.headers.version = COMPACT_VERSION;
+        let acks = [
+            // Synchronized clocks, only the random part differs.
+            ((ts << 16) | 0x1234, 6),
+            // A clock one hour late.
+            (((ts - 3600) << 16) | 0x1234, 8),
+            // A clock never set.
+            (0x1234, 10),
+            // Nonces far from the frame nonce, or equal to it, are encoded as is.
+            (nonce.wrapping_add(1 << 62), 2 + 9 + 1),
+            (nonce.wrapping_add(1 << 63), 2 + 1 + FRAME_NONCE_SIZE + 1),
+            (nonce, 2 + 1 + FRAME_NONCE_SIZE + 1),
+        ];
+        for (acked, size) in acks {
+            let ack = (AddressHeader::new(0x0004, false), acked, -3);
+            assert_eq!(
+                rf1.headers.acknowledgments_size(&[ack]),
+                size,
+                "size of the acknowledgment of {:#x}",
+                acked
+            );
+            assert!(size <= ACKNOWLEDGMENT_SIZE);
+        }
+        rf1.acknowledgments = acks
+            .iter()
+            .map(|(acked, _)| (AddressHeader::new(0x0004, false), *acked, -3))
+            .collect();
+        let rfb1 = rf1.to_bytes();
+        assert_eq!(rfb1.len(), rf1.size());
+        let (view, read) = RadioFrameView::try_from_bytes(&rfb1).expect("Failed to view frame");
+        assert_eq!(read, rfb1.len());
+        assert_eq!(
+            view.acknowledgments().collect::<Vec<_>>(),
+            rf1.acknowledgments
+        );
+    }
+
+    #[test]
+    fn frame_compact_non_canonical_acknowledgments() {
+        let mut rf1 = group_frame();
+        rf1.headers.version = COMPACT_VERSION;
+        let nonce = rf1.headers.nonce;
+        rf1.acknowledgments = vec![(AddressHeader::new(0x0004, false), nonce - 5, -3)];
+        let rfb1 = rf1.to_bytes();
+        // The difference (zigzag of 5) follows the number of acknowledgments and the address.
+        let at = rf1.headers.size() + 1 + 2;
+        assert_eq!(rfb1[at], 10);
+        let with_nonce = |encoded: &[u8]| [&rfb1[..at], encoded, &rfb1[(at + 1)..]].concat();
+        let assert_malformed = |bytes: &[u8]| match RadioFrameView::try_from_bytes(bytes) {
+            Err(FrameError::Malformed { offset, .. }) => assert_eq!(offset, at),
+            res => panic!("expected a malformed acknowledgment, got {:?}", res),
+        };
+
+        // A nonce encoded as is, while its difference is short.
+        let absolute = [&[ABSOLUTE_NONCE as u8][..], &(nonce - 5).to_be_bytes()].concat();
+        assert_malformed(&with_nonce(&absolute));
+        // A difference on 10 bytes, while the nonce should be encoded as is.
+        let mut long = [0xFF; 10];
+        long[9] = 0x01;
+        assert_malformed(&with_nonce(&long));
+
+        // The nonce encoded as is when its difference is too long is accepted.
+        rf1.acknowledgments[0].1 = nonce.wrapping_add(1 << 63);
+        let rfb2 = rf1.to_bytes();
+        assert_eq!(rfb2[at], ABSOLUTE_NONCE as u8);
+        let (rfd2, _) = RadioFrameWithHeaders::try_from_bytes(&rfb2).expect("Failed to parse");
+        assert_eq!(rfd2.to_bytes(), rfb2);
+    }
+
+    #[test]
+    fn frame_data_rate_extension() {
+        let mut rf1 = group_frame();
+        assert_eq!(rf1.headers.data_rate(), 0);
+        // The default data rate is not announced.
+        rf1.headers.set_data_rate(0);
+        assert_eq!(rf1.to_bytes(), group_frame().to_bytes());
+
+        rf1.headers.set_data_rate(3);
+        rf1.headers.set_data_rate(2);
+        assert_eq!(rf1.headers.extensions.len(), 1);
+        let rfb1 = rf1.to_bytes();
+        assert_eq!(rfb1.len(), group_frame().size() + 1 + 3);
+        let (view, _read) = RadioFrameView::try_from_bytes(&rfb1).expect("Failed to view frame");
+        assert_eq!(view.headers.data_rate(), 2);
+        assert_eq!(view.to_frame().headers.data_rate(), 2);
+
+        rf1.headers.set_data_rate(0);
+        assert!(rf1.headers.extensions.is_empty());
+    }
+
+    #[test]
+    fn frame_unsupported_version() {
+        let mut rfb1 = group_frame().to_bytes();
+        rfb1[0] = 0x0F << 4;
+        match RadioFrameView::try_from_bytes(&rfb1) {
+            Err(FrameError::UnsupportedVersion { version }) => assert_eq!(version, 0x0F),
+            res => panic!("expected an unsupported version, got {:?}", res),
+        }
+        assert_eq!(
+            FrameError::UnsupportedVersion { version: 0x0F }.to_string(),
+            format!(
+                "Unsupported frame format version 15 (supported: {} and {}).",
+                PROTOCOL_VERSION, COMPACT_VERSION
+            )
+        );
+        // Only the versions that can be parsed are encoded.
+        let mut rf1 = group_frame();
+        rf1.headers.version = 3;
+        assert!(matches!(
+            rf1.encode_into(&mut [0u8; 512]),
+            Err(FrameError::UnsupportedVersion { version: 3 })
+        ));
+        rfb1[0] = (PROTOCOL_VERSION << 4) | 0b0000_0100;
+        assert!(RadioFrameView::try_from_bytes(&rfb1).is_err());
+        assert_eq!(
+            FrameType::try_from(7).ok(),
+            Some(FrameType::BeaconAcknowledgment)
+        );
+        assert!(FrameType::try_from(15).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn frame_serde() {
+        let mut rf1 = group_frame();
+        rf1.headers.extensions = vec![Extension {
+            kind: 1,
+            value: vec![0xAB],
+        }];
+        let json = serde_json::to_string(&rf1).expect("Failed to serialize frame");
+        let rf2: RadioFrameWithHeaders =
+            serde_json::from_str(&json).expect("Failed to deserialize frame");
+        assert_eq!(rf2.to_bytes(), rf1.to_bytes());
+
+        // The compact headers are their raw value, the optional fields can be omitted.
+        let headers: RadioHeaders = serde_json::from_str(
+            r#"{
+                "version": 1,
+                "rec_n_frames": 17,
+                "recipients": { "Direct": 32770 },
+                "payloads": 1,
+                "sender": 1,
+                "nonce": 258
+            }"#,
+        )
+        .expect("Failed to deserialize headers");
+        assert_eq!(headers.rec_n_frames, InfoHeader::new(1, 1));
+        match headers.recipients {
+            RecipientHeader::Direct(ah) => assert_eq!(ah, AddressHeader::new(0x0002, true)),
+            RecipientHeader::Group(_) => panic!("expected a direct recipient"),
+        }
+        assert!(!headers.compression);
+        assert!(headers.extensions.is_empty());
+
+        let ack = BeaconAcknowledgment {
+            sender: AddressHeader::new(0x0002, false),
+            recipient: AddressHeader::new(0x0001, false),
+            sweep: 42,
+            rssi: vec![Some(-90), None],
+        };
+        let json = serde_json::to_string(&ack).unwrap();
+        assert_eq!(
+            json,
+            r#"{"sender":2,"recipient":1,"sweep":42,"rssi":[-90,null]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<BeaconAcknowledgment>(&json).unwrap(),
+            ack
+        );
+    }
+
+    #[test]
+    fn frame_error_offsets() {
+        let rf1 = group_frame();
+        let rfb1 = rf1.to_bytes();
+        // Truncated in the last payload (5 bytes), the error points at its start.
+        let err = RadioFrameView::try_from_bytes(&rfb1[..(rfb1.len() - 1)]).unwrap_err();
+        assert_eq!(err.offset(), Some(rfb1.len() - 5));
+        // Invalid recipient count, right after the version and info header.
+        let mut rfb2 = rfb1.clone();
+        rfb2[2] = 0;
+        assert_eq!(
+            RadioFrameView::try_from_bytes(&rfb2).unwrap_err().offset(),
+            Some(2)
+        );
+        // In the extension area, the offsets are the ones of the whole headers.
+        let mut rf3 = rf1.clone();
+        rf3.headers.extensions = vec![Extension {
+            kind: 1,
+            value: vec![0xAB; 3],
+        }];
+        let mut rfb3 = rf3.to_bytes();
+        // Area length, kind, length (too long) then the value.
+        let area = rf3.headers.size() - 6;
+        rfb3[area + 2] = 4;
+        match RadioFrameView::try_from_bytes(&rfb3) {
+            Err(FrameError::Malformed { offset, .. }) => assert_eq!(offset, area + 3),
+            res => panic!("expected a malformed extension, got {:?}", res),
+        }
+        let beacon = BeaconPayload {
+            sweep: 1,
+            tpi: 3,
+            levels: 3,
+        };
+        assert_eq!(
+            BeaconPayload::try_from_bytes(&beacon.to_bytes())
+                .unwrap_err()
+                .offset(),
+            Some(8)
+        );
+    }
+
+    #[test]
+    fn frame_compact_encoding() {
+        let nonce = 0x0000_6543_2100_beef;
+        let mut rf1 = group_frame();
+        rf1.headers.nonce = nonce;
+        rf1.acknowledgments = vec![
+            // Acknowledged frames are a bit older than the frame acknowledging them.
+            (
+                AddressHeader::new(0x0004, false),
+                nonce - (12 << 16) + 0x1234,
+                -12,
+            ),
+            (
+                AddressHeader::new(0x0005, false),
+                nonce - (3 << 16) - 0x0042,
+                42,
+            ),
+            // A peer with a clock ahead.
+            (AddressHeader::new(0x0006, false), nonce + (7 << 16), 0),
+        ];
+        let mut rf2 = rf1.clone();
+        rf2.headers.version = COMPACT_VERSION;
+        assert!(rf2.headers.is_compact());
+
+        let rfb1 = rf1.to_bytes();
+        let rfb2 = rf2.to_bytes();
+        assert_eq!(rfb2.len(), rf2.size());
+        assert_eq!(rfb2[0] >> 4, COMPACT_VERSION);
+        // Nonce: 7 bytes instead of 8, acknowledgments: 6 bytes instead of 12, payload lengths:
+        // 1 byte instead of 2.
+        assert_eq!(rf1.headers.size() - rf2.headers.size(), 1);
+        assert_eq!(
+            rf1.headers.acknowledgments_size(&rf1.acknowledgments),
+            3 * ACKNOWLEDGMENT_SIZE
+        );
+        assert_eq!(
+            rf2.headers.acknowledgments_size(&rf2.acknowledgments),
+            3 * 6
+        );
+        assert_eq!(rfb1.len() - rfb2.len(), 1 + 3 * 6 + 3);
+
+        let (view, read) = RadioFrameView::try_from_bytes(&rfb2).expect("Failed to view frame");
+        assert_eq!(read, rfb2.len());
+        assert_eq!(view.headers.version, COMPACT_VERSION);
+        assert_eq!(view.headers.nonce, nonce);
+        assert_eq!(view.acknowledgments().len(), 3);
+        assert_eq!(
+            view.acknowledgments().collect::<Vec<_>>(),
+            rf2.acknowledgments
+        );
+        assert_eq!(view.payload(2), Some(&b"WORLD"[..]));
+        assert_eq!(view.to_frame().to_bytes(), rfb2);
+
+        // RSSI deltas are clamped to a byte.
+        rf2.acknowledgments = vec![(AddressHeader::new(0x0004, false), nonce, -300)];
+        let (rfd2, _) = RadioFrameWithHeaders::try_from_bytes(&rf2.to_bytes())
+            .expect("Failed to parse radio frame");
+        assert_eq!(rfd2.acknowledgments[0].2, i8::MIN as i16);
+
+        // Many more acknowledgments fit in the lead physical frame.
+        let acks: Vec<_> = (0..30)
+            .map(|i| {
+                (
+                    AddressHeader::new(i, false),
+                    nonce - (i as u64) * 0x1_0000,
+                    0,
+                )
+            })
+            .collect();
+        assert!(rf1.headers.acknowledgments_size(&acks) > 253);
+        assert!(rf2.headers.acknowledgments_size(&acks) < 253);
+    }
+
+    #[test]
+    fn frame_varint() {
+        for value in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, u32::MAX as u64, u64::MAX] {
+            let mut buf = [0u8; 10];
+            let mut writer = FrameWriter::new(&mut buf, 10).unwrap();
+            writer.put_varint(value);
+            let size = writer.cursor;
+            assert_eq!(size, varint_size(value), "size of {}", value);
+            let mut reader = FrameReader::new(&buf[..size]);
+            assert_eq!(reader.varint("value").unwrap(), value);
+            assert_eq!(reader.cursor, size);
+        }
+        for value in [0, 1, -1, i64::MIN, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(value)), value);
+        }
+        assert_eq!(zigzag(-1), 1);
+        // Overlong, overflowing and truncated encodings.
+        for bytes in [&[0x80, 0x00][..], &[0xFF; 10][..], &[0x80][..]] {
+            assert!(FrameReader::new(bytes).varint("value").is_err());
+        }
+    }
+
+    #[test]
+    fn frame_compressed_payloads() {
+        let text = b"Enchante de pouvoir communiquer avec vous! Enchante de communiquer avec vous!";
+        let compressed = compression::compress_if_smaller(text).expect("text should compress");
+        for version in [PROTOCOL_VERSION, COMPACT_VERSION] {
+            let mut rf1 = group_frame();
+            rf1.headers.version = version;
+            rf1.headers.compression = true;
+            rf1.payloads[2] = compressed.clone();
+            rf1.compressed = PayloadFlag::new(&[2]);
+            let rfb1 = rf1.to_bytes();
+            assert_eq!(rfb1.len(), rf1.size());
+            assert_eq!(rfb1[0] & COMPRESSION_FLAG, COMPRESSION_FLAG);
+
+            let (view, _) = RadioFrameView::try_from_bytes(&rfb1).expect("Failed to view frame");
+            assert!(view.headers.compression);
+            assert_eq!(view.compressed(), PayloadFlag::new(&[2]));
+            assert_eq!(view.payload(2), Some(&compressed[..]));
+            assert_eq!(
+                view.decompressed_payload(2).unwrap().unwrap(),
+                text.to_vec()
+            );
+            assert_eq!(
+                view.decompressed_payload(0).unwrap().unwrap(),
+                b"HELO!".to_vec()
+            );
+            assert!(view.decompressed_payload(3).is_none());
+            assert_eq!(view.to_frame().to_bytes(), rfb1);
+        }
+
+        // Compressed payloads must be announced by the headers.
+        let mut rf2 = group_frame();
+        rf2.compressed = PayloadFlag::new(&[0]);
+        assert!(rf2.encode_into(&mut [0u8; 256]).is_err());
+        rf2.headers.compression = true;
+        rf2.compressed = PayloadFlag::new(&[3]);
+        assert!(rf2.encode_into(&mut [0u8; 256]).is_err());
+
+        // A corrupted compressed payload is reported.
+        rf2.compressed = PayloadFlag::new(&[0]);
+        rf2.payloads[0] = vec![0, 0, 0];
+        let rfb2 = rf2.to_bytes();
+        let (view, _) = RadioFrameView::try_from_bytes(&rfb2).expect("Failed to view frame");
+        assert!(matches!(
+            view.decompressed_payload(0),
+            Some(Err(FrameError::InvalidPayload { id: 0, .. }))
+        ));
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::collection::vec;
+        use proptest::option;
+        use proptest::prelude::*;
+
+        fn address() -> impl Strategy<Value = AddressHeader> {
+            any::<u16>().prop_map(AddressHeader::from)
+        }
+
+        fn recipients() -> impl Strategy<Value = RecipientHeader> {
+            prop_oneof![
+                address().prop_map(RecipientHeader::Direct),
+                vec(
+                    (address(), any::<u16>().prop_map(PayloadFlag::from)),
+                    2..=16
+                )
+                .prop_map(RecipientHeader::Group),
+            ]
+        }
+
+        fn extensions() -> impl Strategy<Value = Vec<Extension>> {
+            vec(
+                (any::<u8>(), vec(any::<u8>(), 0..16))
+                    .prop_map(|(kind, value)| Extension { kind, value }),
+                0..4,
+            )
+        }
+
+        fn radio_frame() -> impl Strategy<Value = RadioFrameWithHeaders> {
+            (
+                any::<u8>(),
+                recipients(),
+                address(),
+                any::<u64>(),
+                prop_oneof![Just(PROTOCOL_VERSION), Just(COMPACT_VERSION)],
+                vec(
+                    (address(), any::<u64>(), (i8::MIN as i16)..=(i8::MAX as i16)),
+                    0..8,
+                ),
+                vec(vec(any::<u8>(), 0..64), 0..=16),
+                extensions(),
+                any::<bool>(),
+                any::<u16>(),
+            )
+                .prop_map(
+                    |(
+                        info,
+                        recipients,
+                        sender,
+                        nonce,
+                        version,
+                        acknowledgments,
+                        payloads,
+                        extensions,
+                        compression,
+                        compressed,
+                    )| {
+                        // Only the existing payloads can be compressed, if announced.
+                        let compressed = if compression {
+                            compressed & ((1u32 << payloads.len()) - 1) as u16
+                        } else {
+                            0
+                        };
+                        RadioFrameWithHeaders {
+                            headers: RadioHeaders {
+                                version,
+                                rec_n_frames: InfoHeader::from(info),
+                                recipients,
+                                payloads: payloads.len() as u8,
+                                sender,
+                                nonce,
+                                compression,
+                                extensions,
+                            },
+                            acknowledgments,
+                            payloads,
+                            compressed: PayloadFlag::from(compressed),
+                        }
+                    },
+                )
+        }
+
+        fn beacon_payload() -> impl Strategy<Value = BeaconPayload> {
+            (1..=u8::MAX).prop_flat_map(|levels| {
+                (any::<u64>(), 0..levels).prop_map(move |(sweep, tpi)| BeaconPayload {
+                    sweep,
+                    tpi,
+                    levels,
+                })
+            })
+        }
+
+        fn beacon_acknowledgment() -> impl Strategy<Value = BeaconAcknowledgment> {
+            (
+                address(),
+                address(),
+                any::<u64>(),
+                vec(
+                    option::of((1 - BEACON_RSSI_OFFSET)..=(u8::MAX as i16 - BEACON_RSSI_OFFSET)),
+                    0..32,
+                ),
+            )
+                .prop_map(|(sender, recipient, sweep, rssi)| BeaconAcknowledgment {
+                    sender,
+                    recipient,
+                    sweep,
+                    rssi,
+                })
+        }
+
+        proptest! {
+            #[test]
+            fn frame_round_trip(rf in radio_frame(), trailing in vec(any::<u8>(), 0..8)) {
+                let mut bytes = rf.to_bytes();
+                let size = bytes.len();
+                prop_assert_eq!(size, rf.size());
+                bytes.extend_from_slice(&trailing);
+                let (rfd, read) = RadioFrameWithHeaders::try_from_bytes(&bytes)?;
+                prop_assert_eq!(read, size);
+                prop_assert_eq!(&rfd.acknowledgments, &rf.acknowledgments);
+                prop_assert_eq!(&rfd.payloads, &rf.payloads);
+                prop_assert_eq!(&rfd.headers.extensions, &rf.headers.extensions);
+                prop_assert_eq!(rfd.compressed, rf.compressed);
+                prop_assert_eq!(rfd.to_bytes(), &bytes[..size]);
+            }
+
+            #[test]
+            fn frame_beacon_round_trip(bp in beacon_payload(), ba in beacon_acknowledgment()) {
+                let (bpd, read) = BeaconPayload::try_from_bytes(&bp.to_bytes())?;
+                prop_assert_eq!(read, bp.size());
+                prop_assert_eq!(bpd, bp);
+                let (bad, read) = BeaconAcknowledgment::try_from_bytes(&ba.to_bytes())?;
+                prop_assert_eq!(read, ba.size());
+                prop_assert_eq!(bad, ba);
+            }
+
+            #[test]
+            fn frame_parsers_are_total(bytes in vec(any::<u8>(), 0..512)) {
+                // Whatever the input, the parsers return an error or a component that encodes
+                // back to the bytes it was read from.
+                if let Ok((rf, read)) = RadioFrameWithHeaders::try_from_bytes(&bytes) {
+                    prop_assert_eq!(rf.to_bytes(), &bytes[..read]);
+                }
+                if let Ok((rh, read)) = RadioHeaders::try_from_bytes(&bytes) {
+                    prop_assert_eq!(rh.to_bytes(), &bytes[..read]);
+                }
+                if let Ok((bp, read)) = BeaconPayload::try_from_bytes(&bytes) {
+                    prop_assert_eq!(bp.to_bytes(), &bytes[..read]);
+                }
+                if let Ok((ba, read)) = BeaconAcknowledgment::try_from_bytes(&bytes) {
+                    prop_assert_eq!(ba.to_bytes(), &bytes[..read]);
+                }
+            }
+        }
+    }
 }
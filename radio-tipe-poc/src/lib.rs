@@ -13,8 +13,11 @@
 //! - This library has only been tested on ESP32-DevKitC and RFM95W modules.
 //! - This library relies lightly on `rust-radio-sx127x`, therefore you will need
 //!   a LoRa radio based on the SX127x radio.
-//! - This library uses the standard library, something that might not be available on most
-//!   embedded platforms.
+//! - This library uses the standard library by default, something that might not be available
+//!   on most embedded platforms. Without the `std` feature it only requires `alloc`, the
+//!   platform then provides the clocks and the random numbers (see [crate::sys]). This build is
+//!   checked on a bare-metal target with
+//!   `cargo build --no-default-features --target thumbv7em-none-eabihf`.
 //!
 //! ## Caution
 //!
@@ -25,11 +28,33 @@
 //!
 //! ## Usage
 //! Some examples are available at modules [crate::device] and [crate::radio].
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+pub mod adr;
 pub mod atpc;
+pub mod capture;
+pub mod compression;
 pub mod device;
 pub mod frame;
+pub mod message;
 pub mod radio;
+pub mod stats;
+pub mod sys;
+pub mod trace;
+
+/// Items of the standard prelude coming from `alloc`, also available without the standard
+/// library.
+mod prelude {
+    pub(crate) use alloc::borrow::ToOwned;
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::string::String;
+    pub(crate) use alloc::vec::Vec;
+    pub(crate) use alloc::{format, vec};
+}
+
+use prelude::*;
 
 /// Representation of the recipients for a particular message that will be
 /// send or has been received by the LoRa radio.
@@ -40,6 +65,11 @@ pub enum LoRaDestination {
     Global,
     /// This message is intended for a group of peers.
     Group(Vec<LoRaAddress>),
+    /// This message is intended for every subscriber of a multicast group.
+    ///
+    /// Contrary to [LoRaDestination::Group], the sender does not need to enumerate the members
+    /// of the group, nodes subscribe themselves to the groups they are interested in.
+    Multicast(LoRaGroup),
     /// This message is intended for a single peer of the network.
     Unique(LoRaAddress),
 }
@@ -49,4 +79,13 @@ pub enum LoRaDestination {
 /// Some might be more familiar with the similar MAC addresses. Indeed it actually
 /// is the physical name of the device and only helps establish link-to-link
 /// transmissions.
+///
+/// Note that the addresses `0x7000` to `0x7FFF` are reserved for the multicast groups
+/// and the global address, they are rejected as the address of a radio or of a peer.
 pub type LoRaAddress = u16;
+
+/// Simple alias for the representation of a multicast group identifier.
+///
+/// A group identifier is included in `0..=0x0FFE` (see [MAX_GROUP_ID](crate::frame::MAX_GROUP_ID))
+/// and is mapped on the network to a reserved [LoRaAddress].
+pub type LoRaGroup = u16;
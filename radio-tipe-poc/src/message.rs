@@ -0,0 +1,289 @@
+//! Typed application messages over the raw payloads of the [Device](crate::device::Device).
+//!
+//! Each message type has a small type tag, a message is encoded in a payload as its tag followed
+//! by its [postcard](https://docs.rs/postcard) encoding. On reception, a [MessageRegistry]
+//! decodes the payloads into the messages of the application (usually an enum of its message
+//! types), and a [MessageRxClient] forwards them to a [TypedRxClient]. The payloads that cannot
+//! be decoded (unknown tag, malformed message) are reported to the client instead of dropped.
+//!
+//! ## Usage
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Temperature { celsius: f32 }
+//!
+//! impl Message for Temperature {
+//!     const TAG: u8 = 1;
+//! }
+//!
+//! enum AppMessage { Temperature(Temperature), Text(Text) }
+//!
+//! let mut registry = MessageRegistry::new();
+//! registry.register(AppMessage::Temperature)?;
+//! registry.register(AppMessage::Text)?;
+//! device.set_receive_client(Box::new(MessageRxClient::new(registry, client)));
+//!
+//! device.queue(LoRaDestination::Unique(0x0002), &encode(&Temperature { celsius: 21.5 })?, true)?;
+//! ```
+use crate::device::RxClient;
+use crate::frame::FrameNonce;
+use crate::prelude::*;
+use crate::LoRaAddress;
+
+use alloc::collections::BTreeMap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A typed application message.
+pub trait Message: Serialize + DeserializeOwned {
+    /// Type tag of the message, unique among the messages of a [MessageRegistry].
+    const TAG: u8;
+}
+
+/// Encodes a message into a payload, its tag followed by its postcard encoding.
+pub fn encode<M: Message>(message: &M) -> Result<Vec<u8>, MessageError> {
+    let mut payload = vec![M::TAG];
+    let encoded = postcard::to_allocvec(message)
+        .map_err(|error| MessageError::EncodingError { tag: M::TAG, error })?;
+    payload.extend_from_slice(&encoded);
+    Ok(payload)
+}
+
+/// Decoder of a registered message type, wrapping it into the messages of the application.
+type Decoder<T> = Box<dyn Fn(&[u8]) -> Result<T, postcard::Error>>;
+
+/// Registry of the message types of an application, decoding the received payloads into `T`.
+pub struct MessageRegistry<T> {
+    decoders: BTreeMap<u8, Decoder<T>>,
+}
+
+impl<T: 'static> MessageRegistry<T> {
+    /// Builds an empty registry.
+    pub fn new() -> Self {
+        Self {
+            decoders: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the message type `M`, wrapped into `T` by `wrap` (e.g. an enum variant).
+    ///
+    /// Fails if another message type already uses the tag of `M`.
+    pub fn register<M: Message + 'static>(&mut self, wrap: fn(M) -> T) -> Result<(), MessageError> {
+        if self.decoders.contains_key(&M::TAG) {
+            return Err(MessageError::DuplicateTag { tag: M::TAG });
+        }
+        self.decoders.insert(
+            M::TAG,
+            Box::new(move |bytes| {
+                let (message, rest) = postcard::take_from_bytes::<M>(bytes)?;
+                if !rest.is_empty() {
+                    return Err(postcard::Error::DeserializeBadEncoding);
+                }
+                Ok(wrap(message))
+            }),
+        );
+        Ok(())
+    }
+
+    /// Is a message type registered with the given tag?
+    pub fn contains(&self, tag: u8) -> bool {
+        self.decoders.contains_key(&tag)
+    }
+
+    /// Decodes a received payload.
+    pub fn decode(&self, payload: &[u8]) -> Result<T, MessageError> {
+        let (&tag, bytes) = payload.split_first().ok_or(MessageError::MissingTag)?;
+        let decoder = self
+            .decoders
+            .get(&tag)
+            .ok_or(MessageError::UnknownTag { tag })?;
+        decoder(bytes).map_err(|error| MessageError::MalformedMessage { tag, error })
+    }
+}
+
+impl<T: 'static> Default for MessageRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reception client of typed messages, see [MessageRxClient].
+pub trait TypedRxClient<T> {
+    /// Device has received the given message.
+    fn receive_message(&self, sender: LoRaAddress, message: T, nonce: FrameNonce)
+        -> Result<(), ()>;
+
+    /// Device has received a payload that is not a registered message (unknown tag or malformed
+    /// message).
+    fn receive_invalid(
+        &self,
+        sender: LoRaAddress,
+        payload: Vec<u8>,
+        nonce: FrameNonce,
+        error: MessageError,
+    ) -> Result<(), ()>;
+}
+
+/// Reception client decoding the received payloads with a [MessageRegistry] and forwarding them
+/// to a [TypedRxClient].
+pub struct MessageRxClient<T, C: TypedRxClient<T>> {
+    registry: MessageRegistry<T>,
+    client: C,
+}
+
+impl<T: 'static, C: TypedRxClient<T>> MessageRxClient<T, C> {
+    pub fn new(registry: MessageRegistry<T>, client: C) -> Self {
+        Self { registry, client }
+    }
+}
+
+impl<T: 'static, C: TypedRxClient<T>> RxClient for MessageRxClient<T, C> {
+    fn receive(&self, sender: LoRaAddress, payload: Vec<u8>, nonce: FrameNonce) -> Result<(), ()> {
+        match self.registry.decode(&payload) {
+            Ok(message) => self.client.receive_message(sender, message, nonce),
+            Err(error) => self.client.receive_invalid(sender, payload, nonce, error),
+        }
+    }
+}
+
+/// Represents an error of the encoding or decoding of a typed message.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum MessageError {
+    /// The payload is empty, it has no type tag.
+    #[error("Empty payload, missing message tag.")]
+    MissingTag,
+
+    /// No message type is registered with the tag of the payload.
+    #[error("Unknown message tag {tag}.")]
+    UnknownTag { tag: u8 },
+
+    /// The payload cannot be decoded as the message type of its tag.
+    #[error("Malformed message (tag {tag}): {error}")]
+    MalformedMessage { tag: u8, error: postcard::Error },
+
+    /// The message cannot be encoded.
+    #[error("Failed to encode message (tag {tag}): {error}")]
+    EncodingError { tag: u8, error: postcard::Error },
+
+    /// Another message type is already registered with this tag.
+    #[error("Message tag {tag} is already registered.")]
+    DuplicateTag { tag: u8 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Temperature {
+        celsius: f32,
+    }
+
+    impl Message for Temperature {
+        const TAG: u8 = 1;
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Text(String);
+
+    impl Message for Text {
+        const TAG: u8 = 2;
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum AppMessage {
+        Temperature(Temperature),
+        Text(Text),
+    }
+
+    fn registry() -> MessageRegistry<AppMessage> {
+        let mut registry = MessageRegistry::new();
+        registry.register(AppMessage::Temperature).unwrap();
+        registry.register(AppMessage::Text).unwrap();
+        registry
+    }
+
+    #[test]
+    fn message_registry_decode() {
+        let registry = registry();
+        let payload = encode(&Temperature { celsius: 21.5 }).unwrap();
+        // Tag then the 4 bytes of the f32.
+        assert_eq!(payload.len(), 5);
+        assert_eq!(
+            registry.decode(&payload),
+            Ok(AppMessage::Temperature(Temperature { celsius: 21.5 }))
+        );
+        let payload = encode(&Text("Bonjour".to_owned())).unwrap();
+        assert_eq!(
+            registry.decode(&payload),
+            Ok(AppMessage::Text(Text("Bonjour".to_owned())))
+        );
+
+        assert_eq!(registry.decode(&[]), Err(MessageError::MissingTag));
+        assert_eq!(
+            registry.decode(&[9, 0]),
+            Err(MessageError::UnknownTag { tag: 9 })
+        );
+        assert!(matches!(
+            registry.decode(&[Temperature::TAG, 0, 0]),
+            Err(MessageError::MalformedMessage { tag: 1, .. })
+        ));
+        // Trailing bytes are not silently ignored.
+        let mut payload = encode(&Temperature { celsius: 1.0 }).unwrap();
+        payload.push(0);
+        assert!(registry.decode(&payload).is_err());
+
+        let mut registry = registry;
+        assert_eq!(
+            registry.register(AppMessage::Text),
+            Err(MessageError::DuplicateTag { tag: 2 })
+        );
+    }
+
+    #[derive(Default)]
+    struct Client {
+        messages: RefCell<Vec<AppMessage>>,
+        invalid: RefCell<Vec<(Vec<u8>, MessageError)>>,
+    }
+
+    impl TypedRxClient<AppMessage> for &Client {
+        fn receive_message(
+            &self,
+            _sender: LoRaAddress,
+            message: AppMessage,
+            _nonce: FrameNonce,
+        ) -> Result<(), ()> {
+            self.messages.borrow_mut().push(message);
+            Ok(())
+        }
+
+        fn receive_invalid(
+            &self,
+            _sender: LoRaAddress,
+            payload: Vec<u8>,
+            _nonce: FrameNonce,
+            error: MessageError,
+        ) -> Result<(), ()> {
+            self.invalid.borrow_mut().push((payload, error));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn message_rx_client() {
+        let client = Client::default();
+        let rx_client = MessageRxClient::new(registry(), &client);
+        let payload = encode(&Text("HELO".to_owned())).unwrap();
+        rx_client.receive(0x0002, payload, 1).unwrap();
+        rx_client.receive(0x0002, vec![7, 1, 2], 2).unwrap();
+        assert_eq!(
+            *client.messages.borrow(),
+            vec![AppMessage::Text(Text("HELO".to_owned()))]
+        );
+        assert_eq!(
+            *client.invalid.borrow(),
+            vec![(vec![7, 1, 2], MessageError::UnknownTag { tag: 7 })]
+        );
+    }
+}
@@ -45,8 +45,21 @@
 //!            sf: SpreadingFactor::Sf9,
 //!            ..Default::default()
 //!        });
+//!        // Faster variants, used for the neighbors with a good link (see the adr module).
+//!        let data_rates = [(SpreadingFactor::Sf8, -10.0), (SpreadingFactor::Sf7, -7.5)]
+//!            .into_iter()
+//!            .map(|(sf, min_snr)| radio_tipe_poc::radio::DataRate {
+//!                radio_channel: Channel::LoRa(LoRaChannel {
+//!                    freq: freq.into(),
+//!                    sf,
+//!                    ..Default::default()
+//!                }),
+//!                min_snr,
+//!            })
+//!            .collect();
 //!        radio_tipe_poc::radio::Channel {
 //!            radio_channel,
+//!            data_rates,
 //!            delay: delay_params.clone(),
 //!        }
 //!    }).collect();
@@ -63,25 +76,32 @@
 //!
 //! You can now use the [Device] implementation to actually run the protocol. Enjoy!
 
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::time::Duration;
 use embedded_hal::blocking::delay::{DelayMs, DelayUs};
-use log::info;
+use log::{debug, info, warn};
 use radio::{Interrupts, Power, Receive, ReceiveInfo, State, Transmit};
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::io::{Cursor, Write};
-use std::marker::PhantomData;
-use std::time::{Duration, Instant, SystemTime};
 
 use ringbuf::HeapRb;
 use ringbuf::Rb;
 
-use crate::atpc::ATPC;
-use crate::device::{Device, QueueError, RxClient, TxClient};
+use crate::adr::DataRateAdapter;
+use crate::atpc::{plan_split, GroupPolicy, ATPC};
+use crate::capture::{CaptureSink, CapturedFrame};
+use crate::compression;
+use crate::device::{
+    Device, MessageHandle, MessagePriority, QueueError, QueueOptions, RxClient, TxClient,
+};
 use crate::frame::{
-    self, AddressHeader, FrameNonce, FrameSize, FrameType, RadioFrameWithHeaders, RadioHeaders,
-    RecipientHeader,
+    self, AddressHeader, BeaconAcknowledgment, BeaconPayload, FrameNonce, FrameSize, FrameType,
+    RadioFrameView, RadioFrameWithHeaders, RadioHeadersView, RecipientHeader, RecipientsView,
 };
-use crate::{LoRaAddress, LoRaDestination};
+use crate::prelude::*;
+use crate::stats::{PaCurrentTable, Statistics};
+use crate::sys::{self, HashMap, Instant};
+use crate::trace::{TraceRecord, TRACE_TARGET};
+use crate::{LoRaAddress, LoRaDestination, LoRaGroup};
 
 /// Maximum length of a frame.
 ///
@@ -91,6 +111,39 @@ const MAX_FRAME_LENGTH: usize = MAX_LORA_PAYLOAD * 5;
 ///
 /// Currently, 254-1. One bit is reserved for the [FrameType] discriminant.
 const MAX_LORA_PAYLOAD: usize = 253;
+/// Maximum number of distinct recipients in a single frame.
+///
+/// This is a limitation of the [RecipientHeader](frame::RecipientHeader) encoding.
+const MAX_RECIPIENTS_PER_FRAME: usize = 16;
+/// Maximum number of payloads in a single frame.
+///
+/// This is a limitation of the [PayloadFlag](frame::PayloadFlag) encoding (a 16-bit bitmap).
+const MAX_PAYLOADS_PER_FRAME: usize = 16;
+/// Maximum number of frames the transmission queue can hold.
+///
+/// When the queued messages exceed the capacity of a single frame, they are transparently split
+/// into several frames, up to this limit.
+const MAX_QUEUED_FRAMES: usize = 3;
+/// Maximum number of messages that can be deferred (preempted by higher priority messages).
+///
+/// Above this limit, the lowest priority messages are dropped.
+const MAX_DEFERRED_MESSAGES: usize = 16;
+/// Maximum number of recipients for a single queued message.
+const MAX_QUEUED_RECIPIENTS: usize = MAX_RECIPIENTS_PER_FRAME * MAX_QUEUED_FRAMES;
+/// Maximum number of beacon acknowledgments waiting for transmission.
+///
+/// Above this limit, the oldest ones are dropped.
+const MAX_PENDING_BEACON_ACKNOWLEDGMENTS: usize = 8;
+/// Delay after the last received beacon of a sweep before acknowledging it, even if some
+/// beacons of the sweep were missed.
+///
+/// Beacons of a sweep are transmitted every 600ms.
+const BEACON_SWEEP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Estimated time on air of a physical frame, used to compare the energy of transmissions.
+///
+/// The protocol assumes a 400ms time on air (followed by 200ms of space).
+const FRAME_TIME_ON_AIR: Duration = Duration::from_millis(400);
 /// Maximum number of checks to do on a channel.
 ///
 /// If after [MAX_ATTEMPT_FREE_CHANNEL] is still not free, the [LoRaRadio] will report
@@ -127,11 +180,47 @@ where
     C: Debug,
 {
     /// The physical radio channel representation.
+    ///
+    /// This is the default data rate of the channel.
     pub radio_channel: C,
+    /// Faster variants of the physical radio channel (another spreading factor or bandwidth on
+    /// the same frequency), from the slowest to the fastest.
+    ///
+    /// They are selected by destination by the [DataRateAdapter], and must be the same on every
+    /// peer of the network.
+    pub data_rates: Vec<DataRate<C>>,
     /// The associated delay parameters to respect regulations.
     pub delay: DelayParams,
 }
 
+/// Variant of a [Channel] with another data rate.
+#[derive(Debug, Clone)]
+pub struct DataRate<C>
+where
+    C: Debug,
+{
+    /// The physical radio channel representation.
+    pub radio_channel: C,
+    /// Demodulation floor (in dB) of this data rate, the minimal SNR required to receive a frame.
+    pub min_snr: f32,
+}
+
+impl<C: Debug> Channel<C> {
+    /// Gets the physical radio channel of a data rate (see [DataRateAdapter]).
+    ///
+    /// Falls back to the default data rate if this channel has no such variant.
+    pub fn data_rate(&self, dr: u8) -> &C {
+        match dr {
+            0 => &self.radio_channel,
+            dr => self
+                .data_rates
+                .get(dr as usize - 1)
+                .map(|dr| &dr.radio_channel)
+                .unwrap_or(&self.radio_channel),
+        }
+    }
+}
+
 /// Type alias of the radio internal state.
 type RadioState = radio_sx127x::device::State;
 
@@ -183,18 +272,46 @@ where
     rssi_target: i16,
     /// The ATPC to use.
     atpc: A,
+    /// The selection of the data rate by destination.
+    adr: DataRateAdapter,
+    /// Transmission statistics.
+    stats: Statistics,
+    /// Current drawn by the radio module, to estimate the energy of the transmissions.
+    pa_table: PaCurrentTable,
+    /// Selection of the transmission power of the frames addressed to several neighbors.
+    group_policy: GroupPolicy,
+    /// Version of the frame format of the transmitted frames.
+    frame_version: u8,
+    /// Are the payloads of the transmitted frames compressed (when it saves bytes)?
+    compression: bool,
     /// The (optional) reception client to which the radio acknowledges receptions.
     rx_client: Option<Box<dyn RxClient>>,
     /// The (optional) transmission client to which the radio acknowledges transmissions.
     tx_client: Option<Box<dyn TxClient>>,
+    /// The (optional) sink of every physical frame sent and received.
+    capture_sink: Option<Box<dyn CaptureSink>>,
     /// Internal queue of messages to transmit.
     tx_buffer: Vec<LoRaMessage>,
+    /// Internal list of messages preempted by higher priority messages, they will be queued
+    /// again after the next transmission.
+    tx_deferred: Vec<LoRaMessage>,
     /// Internal queue of acknowledgment to transmit.
     tx_buf_acknowledgments: Vec<(AddressHeader, FrameNonce, i16)>,
-    /// Internal intermediate frame to transmit.
-    tx_frame: Option<frame::RadioFrameWithHeaders>,
+    /// Internal intermediate frames to transmit, in transmission order.
+    ///
+    /// Only the first frame carries the acknowledgments.
+    tx_frames: Vec<QueuedFrame>,
     /// Internal history of transmissions (to allow retransmissions).
     tx_history: HeapRb<frame::RadioFrameWithHeaders>,
+    /// Internal history of the message handles of each transmitted frame.
+    ///
+    /// Each item represents the nonce of the frame and the handles of its payloads, in the order
+    /// of the payloads in the frame.
+    tx_handles: HeapRb<(FrameNonce, Vec<MessageHandle>)>,
+    /// Identifier of the next message handle.
+    next_handle: u32,
+    /// Internal history of the transmission power of each transmitted frame.
+    tx_powers: HeapRb<(FrameNonce, i8)>,
     /// Internal queue of pending acknowledgment to transmit.
     pending_rx_acknowledgments: Vec<(AddressHeader, FrameNonce, i16)>,
     /// Internal list of awaiting acknowledgments.
@@ -210,6 +327,16 @@ where
     ///
     /// It defines what frames the radio will listen to.
     address: LoRaAddress,
+    /// The multicast groups this radio is subscribed to.
+    ///
+    /// The radio will also listen to the frames addressed to these groups.
+    groups: Vec<LoRaGroup>,
+    /// The known members of the multicast groups we send frames to.
+    group_members: HashMap<LoRaGroup, Vec<LoRaAddress>>,
+    /// The sweeps of beacons being received, by beacon transmitter.
+    rx_beacon_sweeps: HashMap<LoRaAddress, BeaconSweep>,
+    /// Internal queue of beacon acknowledgments to transmit.
+    pending_beacon_acknowledgments: Vec<BeaconAcknowledgment>,
     phantom: PhantomData<E>,
 }
 
@@ -221,6 +348,9 @@ where
     E: Debug,
 {
     /// Initialize a new LoRa radio as device.
+    ///
+    /// The address must be the address of a single peer, the addresses reserved to the multicast
+    /// groups and the global address are rejected.
     pub fn new(
         radio: T,
         channels: &'a [Channel<C>],
@@ -231,6 +361,11 @@ where
         address: LoRaAddress,
     ) -> Self {
         assert!(channels.len() > 0, "No channel declared!");
+        assert!(
+            AddressHeader::new(address, false).is_unicast(),
+            "Reserved address {:#06x} used as the radio address!",
+            address
+        );
         let usages = channels
             .iter()
             .map(|_ch| (Instant::now(), Duration::ZERO))
@@ -239,57 +374,139 @@ where
             radio,
             channels,
             atpc,
+            adr: DataRateAdapter::default(),
+            stats: Statistics::default(),
+            pa_table: PaCurrentTable::default(),
+            group_policy: GroupPolicy::default(),
+            frame_version: frame::PROTOCOL_VERSION,
+            compression: false,
             rssi_target,
             rx_client,
             tx_client,
+            capture_sink: None,
             address,
+            groups: Vec::new(),
+            group_members: HashMap::new(),
             tx_buffer: Vec::new(),
+            tx_deferred: Vec::new(),
             tx_buf_acknowledgments: Vec::new(),
-            tx_frame: None,
+            tx_frames: Vec::new(),
             channel_usages: usages,
             tx_history: HeapRb::new(60), // Tx history is limited to 60 frames, a fair limit if we consider each frame need a second to be transmit and
             // we only need this history to retransmit a packet. Acknowledgment of a packet expired after 60s.
+            tx_handles: HeapRb::new(60), // Same reason
+            next_handle: 1,
+            tx_powers: HeapRb::new(60), // Same reason
             pending_rx_acknowledgments: Vec::new(),
             pending_tx_acknowledgments: HeapRb::new(60), // Same reason
+            rx_beacon_sweeps: HashMap::new(),
+            pending_beacon_acknowledgments: Vec::new(),
             phantom: PhantomData,
         }
     }
 
+    /// Sets the current drawn by the radio module, used to estimate the energy of the
+    /// transmissions (see [Statistics]).
+    pub fn set_pa_current_table(&mut self, table: PaCurrentTable) {
+        self.pa_table = table;
+    }
+
+    /// Sets the selection of the transmission power of the frames addressed to several
+    /// neighbors (see [GroupPolicy]).
+    pub fn set_group_policy(&mut self, policy: GroupPolicy) {
+        self.group_policy = policy;
+    }
+
+    /// Sets the version of the frame format of the transmitted frames, either
+    /// [PROTOCOL_VERSION](frame::PROTOCOL_VERSION) (the default) or the compact
+    /// [COMPACT_VERSION](frame::COMPACT_VERSION).
+    ///
+    /// Frames of both versions are always received.
+    pub fn set_frame_version(&mut self, version: u8) {
+        assert!(
+            version == frame::PROTOCOL_VERSION || version == frame::COMPACT_VERSION,
+            "Unsupported frame version {}!",
+            version
+        );
+        self.frame_version = version;
+    }
+
+    /// Enables the compression of the payloads of the transmitted frames (see
+    /// [compression](crate::compression)), disabled by default.
+    ///
+    /// Only the payloads that it shrinks are compressed, the recipients decompress them before
+    /// forwarding them to their [RxClient]. All the nodes must support it.
+    pub fn set_compression(&mut self, enabled: bool) {
+        self.compression = enabled;
+    }
+
+    /// Sets the sink of every physical frame sent and received by the radio (see
+    /// [capture](crate::capture)), none by default.
+    pub fn set_capture_sink(&mut self, sink: Option<Box<dyn CaptureSink>>) {
+        self.capture_sink = sink;
+    }
+
+    /// Records a physical frame into the capture sink, if any.
+    fn capture(&mut self, frame: CapturedFrame) {
+        if let Some(sink) = self.capture_sink.as_mut() {
+            sink.capture(&frame);
+        }
+    }
+
+    /// Gets the selection of the data rate by destination, for instance to tune its margin.
+    pub fn data_rate_adapter(&mut self) -> &mut DataRateAdapter {
+        &mut self.adr
+    }
+
     /// Builds an internal frame representation based on a buffer of messages and a buffer
     /// of acknowledgments.
     ///
-    /// This function might return an error if the frame exceeds the [MAX_FRAME_LENGTH] length.
+    /// This function might return an error if the frame exceeds the [MAX_FRAME_LENGTH] length,
+    /// or holds more than [MAX_RECIPIENTS_PER_FRAME] recipients or [MAX_PAYLOADS_PER_FRAME] payloads.
     fn build_frame(
         &self,
         buffer: &Vec<LoRaMessage>,
         tx_buf_acknowledgments: &Vec<(AddressHeader, FrameNonce, i16)>,
     ) -> Result<frame::RadioFrameWithHeaders, RadioError<E>> {
+        if buffer.len() > MAX_PAYLOADS_PER_FRAME {
+            return Err(RadioError::TooManyPayloadsError {
+                count: buffer.len(),
+            });
+        }
         let mut recipients: HashMap<frame::AddressHeader, frame::PayloadFlag> = HashMap::new();
         let mut payloads: Vec<frame::Payload> = Vec::new();
+        let mut compressed = frame::PayloadFlag::default();
         // Builds the payload list and associated recipient list.
         for (id, msg) in buffer.iter().enumerate() {
             for rec in &msg.dest {
-                if let Some(prev) = recipients.get_mut(&(*rec).into()) {
+                if let Some(prev) = recipients.get_mut(&AddressHeader::from(*rec)) {
                     prev.push(id as u8);
                 } else {
                     recipients.insert((*rec).into(), frame::PayloadFlag::new(&[id as u8]));
                 }
             }
-            payloads.push(msg.payload.clone());
+            let shrunk = if self.compression && !msg.beacon {
+                compression::compress_if_smaller(&msg.payload)
+            } else {
+                None
+            };
+            if let Some(payload) = shrunk {
+                compressed.push(id as u8);
+                payloads.push(payload);
+            } else {
+                payloads.push(msg.payload.clone());
+            }
         }
         // Builds the acknowledgment list and the associated recipient list.
         for (ah, _nonce, _drssi) in tx_buf_acknowledgments {
-            if let None = recipients.get_mut(&(*ah).into()) {
-                recipients.insert((*ah).into(), frame::PayloadFlag::new(&[]));
-            }
+            recipients
+                .entry(*ah)
+                .or_insert_with(|| frame::PayloadFlag::new(&[]));
         }
-        let ts = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("SystemTime is before UNIX_EPOCH?!")
-            .as_secs();
+        let ts = sys::unix_time().as_secs();
         let mut rp = [0u8; 2];
         // Error silenced here!
-        let _ = getrandom::getrandom(&mut rp);
+        let _ = sys::fill_random(&mut rp);
         let nonce = (ts << 16) + ((rp[1] as u64) << 8) + (rp[0] as u64);
 
         // Builds the frame based on the number of recipients.
@@ -299,13 +516,16 @@ where
             }),
             1 => {
                 let headers = frame::RadioHeaders {
+                    version: self.frame_version,
                     rec_n_frames: frame::InfoHeader::new(1, 0),
                     recipients: frame::RecipientHeader::Direct(recipients.iter().map(|(dest, _pf)| *dest).next().expect("First recipient does not exist while there is one recipient registered!")),
                     payloads: payloads.len() as u8,
                     sender: self.address.into(),
                     nonce,
+                    compression: !compressed.is_empty(),
+                    extensions: Vec::new(),
                 };
-                let ffsize = headers.size() + tx_buf_acknowledgments.size();
+                let ffsize = headers.size() + headers.acknowledgments_size(tx_buf_acknowledgments);
                 if ffsize > MAX_LORA_PAYLOAD {
                     return Err(RadioError::TooBigFirstFrameError { size: ffsize });
                 }
@@ -313,24 +533,29 @@ where
                     headers,
                     acknowledgments: tx_buf_acknowledgments.clone(),
                     payloads,
+                    compressed,
                 };
                 let len = frame.size();
-                if dbg!(len) > MAX_FRAME_LENGTH {
+                if len > MAX_FRAME_LENGTH {
                     return Err(RadioError::TooBigFrameError { size: len });
                 }
-                let frames = dbg!((frame.size() / MAX_LORA_PAYLOAD) as u8 + 1);
-                dbg!(frame.headers.rec_n_frames.set_frames(frames));
+                let frames = (frame.size() / MAX_LORA_PAYLOAD) as u8 + 1;
+                debug!("Frame of {} bytes in {} physical frames.", len, frames);
+                frame.headers.rec_n_frames.set_frames(frames);
                 Ok(frame)
             }
             2..=16 => {
                 let headers = frame::RadioHeaders {
+                    version: self.frame_version,
                     rec_n_frames: frame::InfoHeader::new(1, 0),
                     recipients: frame::RecipientHeader::Group(recipients.into_iter().collect()),
                     payloads: payloads.len() as u8,
                     sender: self.address.into(),
                     nonce,
+                    compression: !compressed.is_empty(),
+                    extensions: Vec::new(),
                 };
-                let ffsize = headers.size() + tx_buf_acknowledgments.size();
+                let ffsize = headers.size() + headers.acknowledgments_size(tx_buf_acknowledgments);
                 if ffsize > MAX_LORA_PAYLOAD {
                     return Err(RadioError::TooBigFirstFrameError { size: ffsize });
                 }
@@ -338,6 +563,7 @@ where
                     headers,
                     acknowledgments: tx_buf_acknowledgments.clone(),
                     payloads,
+                    compressed,
                 };
                 let len = frame.size();
                 if len > MAX_FRAME_LENGTH {
@@ -347,9 +573,332 @@ where
                 frame.headers.rec_n_frames.set_frames(frames);
                 Ok(frame)
             }
-            n => Err(RadioError::InvalidRecipentsError {
-                context: format!("Too many recipients ({}, max: 16)!", n),
-            }),
+            n => Err(RadioError::TooManyRecipientsError { count: n }),
+        }
+    }
+
+    /// Builds the internal frames needed to transmit a buffer of messages and a buffer of
+    /// acknowledgments.
+    ///
+    /// Messages are packed in order, a new frame is started each time the current one would
+    /// exceed the capacity of a single frame (see [LoRaRadio::build_frame]). A message with more
+    /// than [MAX_RECIPIENTS_PER_FRAME] recipients is sent in several frames, each one to a part of
+    /// its recipients. Acknowledgments are always carried by the first frame.
+    fn build_frames(
+        &self,
+        buffer: &[LoRaMessage],
+        tx_buf_acknowledgments: &Vec<(AddressHeader, FrameNonce, i16)>,
+    ) -> Result<Vec<QueuedFrame>, RadioError<E>> {
+        let chunks: Vec<LoRaMessage> = buffer
+            .iter()
+            .flat_map(|msg| {
+                if msg.dest.is_empty() {
+                    // Kept as is, so the missing recipients are reported.
+                    return vec![msg.clone()];
+                }
+                msg.dest
+                    .chunks(MAX_RECIPIENTS_PER_FRAME)
+                    .map(|dest| LoRaMessage {
+                        dest: dest.to_vec(),
+                        ..msg.clone()
+                    })
+                    .collect::<Vec<LoRaMessage>>()
+            })
+            .collect();
+        let no_acknowledgment = Vec::new();
+
+        let mut frames = Vec::new();
+        let mut current: Vec<LoRaMessage> = Vec::new();
+        let mut current_frame = None;
+        for chunk in chunks {
+            let acknowledgments = if frames.is_empty() {
+                tx_buf_acknowledgments
+            } else {
+                &no_acknowledgment
+            };
+            let mut candidate = current.clone();
+            candidate.push(chunk.clone());
+            match self.build_frame(&candidate, acknowledgments) {
+                Ok(frame) => {
+                    current = candidate;
+                    current_frame = Some(frame);
+                }
+                Err(
+                    RadioError::TooBigFrameError { .. }
+                    | RadioError::TooBigFirstFrameError { .. }
+                    | RadioError::TooManyRecipientsError { .. }
+                    | RadioError::TooManyPayloadsError { .. },
+                ) if current_frame.is_some() => {
+                    // This chunk does not fit in the current frame, starts a new one.
+                    frames.push(QueuedFrame {
+                        frame: current_frame.take().unwrap(),
+                        messages: current,
+                    });
+                    current = vec![chunk];
+                    current_frame = Some(self.build_frame(&current, &no_acknowledgment)?);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if let Some(frame) = current_frame {
+            frames.push(QueuedFrame {
+                frame,
+                messages: current,
+            });
+        } else if frames.is_empty() && !tx_buf_acknowledgments.is_empty() {
+            // Acknowledgment only frame.
+            frames.push(QueuedFrame {
+                frame: self.build_frame(&Vec::new(), tx_buf_acknowledgments)?,
+                messages: Vec::new(),
+            });
+        }
+        if frames.len() > MAX_QUEUED_FRAMES {
+            return Err(RadioError::TooManyFramesError {
+                count: frames.len(),
+            });
+        }
+        Ok(frames)
+    }
+
+    /// Rebuilds the first queued frame so it only needs the given number of physical frames.
+    ///
+    /// The highest priority messages are kept in the frame (as well as the acknowledgments),
+    /// the other ones are returned. Returns None if no message fits.
+    fn reduce_first_frame(
+        &self,
+        max_frames: usize,
+    ) -> Option<(frame::RadioFrameWithHeaders, Vec<LoRaMessage>)> {
+        let messages = &self.tx_frames.first()?.messages;
+        for k in (0..messages.len()).rev() {
+            let kept = messages[..k].to_vec();
+            if let Ok(frame) = self.build_frame(&kept, &self.tx_buf_acknowledgments) {
+                if (frame.headers.rec_n_frames.get_frames() as usize) <= max_frames {
+                    return Some((frame, messages[k..].to_vec()));
+                }
+            }
+        }
+        None
+    }
+
+    /// Gets the recipients of a frame, with the multicast groups expanded into their members.
+    fn frame_recipients(&self, frame: &frame::RadioFrameWithHeaders) -> Vec<LoRaAddress> {
+        match &frame.headers.recipients {
+            RecipientHeader::Direct(ah) => self.expand_recipients(&[*ah]),
+            RecipientHeader::Group(ahs) => {
+                let ahs: Vec<AddressHeader> = ahs.iter().map(|(ah, _)| *ah).collect();
+                self.expand_recipients(&ahs)
+            }
+        }
+    }
+
+    /// Gets the transmission power of a transmission to the given recipients (see
+    /// [frame_recipients](LoRaRadio::frame_recipients)), and the recipients that need exactly this
+    /// transmission power.
+    ///
+    /// Without any known recipient (a multicast group without known member), the default
+    /// transmission power of the ATPC is used.
+    fn recipients_tx_power(&mut self, addrs: &[LoRaAddress]) -> (i8, Vec<LoRaAddress>) {
+        if addrs.is_empty() {
+            return (self.atpc.get_default_tx_power(), Vec::new());
+        }
+        self.atpc
+            .get_group_tx_power(addrs.to_vec(), &self.group_policy)
+    }
+
+    /// Estimates the energy (mJ) needed to transmit a frame to every one of its recipients.
+    fn frame_energy(&mut self, frame: &frame::RadioFrameWithHeaders) -> f64 {
+        let addrs = self.frame_recipients(frame);
+        let (tx_power, _) = if addrs.is_empty() {
+            (self.atpc.get_default_tx_power(), Vec::new())
+        } else {
+            self.atpc.get_min_tx_power(addrs)
+        };
+        (frame.headers.rec_n_frames.get_frames() as f64)
+            * self.pa_table.energy_mj(tx_power, FRAME_TIME_ON_AIR)
+    }
+
+    /// Splits the recipients of the first frame in two transmissions at different transmission
+    /// powers, when it saves energy (see [GroupPolicy::Split]).
+    ///
+    /// The recipients of each message are split by the transmission power they need, a message
+    /// with recipients on both sides is transmitted once per side (as the chunks of
+    /// [build_frames](Self::build_frames), the copies share the handle of the message). A
+    /// multicast group is never split, it needs the transmission power of its farthest known
+    /// member. The part holding the highest priority message is transmitted first (with the
+    /// acknowledgments).
+    /// Returns the frame to transmit now, its messages and the messages left for the next
+    /// transmission.
+    fn split_first_frame(
+        &mut self,
+        frame: &frame::RadioFrameWithHeaders,
+        messages: &[LoRaMessage],
+    ) -> Option<(
+        frame::RadioFrameWithHeaders,
+        Vec<LoRaMessage>,
+        Vec<LoRaMessage>,
+    )> {
+        if messages.is_empty() {
+            return None;
+        }
+        // Transmission power needed by each recipient of each message.
+        let default_power = self.atpc.get_default_tx_power();
+        let mut tx_powers: Vec<(LoRaAddress, i8)> = Vec::new();
+        let mut dest_powers = Vec::with_capacity(messages.len());
+        for msg in messages {
+            let mut powers = Vec::with_capacity(msg.dest.len());
+            for dest in &msg.dest {
+                let members = self.expand_recipients(&[AddressHeader::from(*dest)]);
+                let tp = members
+                    .iter()
+                    .map(|na| self.atpc.get_tx_power(*na))
+                    .max()
+                    .unwrap_or(default_power);
+                if !tx_powers.iter().any(|(addr, _)| addr == dest) {
+                    tx_powers.push((*dest, tp));
+                }
+                powers.push(tp);
+            }
+            dest_powers.push(powers);
+        }
+        let partition = |threshold: i8| {
+            let (mut near, mut far) = (Vec::new(), Vec::new());
+            let mut lead_near = false;
+            for (i, (msg, powers)) in messages.iter().zip(dest_powers.iter()).enumerate() {
+                if msg.dest.is_empty() {
+                    // Kept as is, so the missing recipients are reported.
+                    if default_power <= threshold {
+                        near.push(msg.clone());
+                    } else {
+                        far.push(msg.clone());
+                    }
+                } else {
+                    let (mut near_dest, mut far_dest) = (Vec::new(), Vec::new());
+                    for (dest, tp) in msg.dest.iter().zip(powers.iter()) {
+                        if *tp <= threshold {
+                            near_dest.push(*dest);
+                        } else {
+                            far_dest.push(*dest);
+                        }
+                    }
+                    if !near_dest.is_empty() {
+                        near.push(LoRaMessage {
+                            dest: near_dest,
+                            ..msg.clone()
+                        });
+                    }
+                    if !far_dest.is_empty() {
+                        far.push(LoRaMessage {
+                            dest: far_dest,
+                            ..msg.clone()
+                        });
+                    }
+                }
+                if i == 0 {
+                    lead_near = !near.is_empty();
+                }
+            }
+            if lead_near {
+                (near, far)
+            } else {
+                (far, near)
+            }
+        };
+        let full_cost = self.frame_energy(frame);
+        let threshold = plan_split(&tx_powers, full_cost, |threshold| {
+            let (first, second) = partition(threshold);
+            if first.is_empty() || second.is_empty() {
+                return None;
+            }
+            let first_frame = self
+                .build_frame(&first, &self.tx_buf_acknowledgments)
+                .ok()?;
+            let second_frame = self.build_frame(&second, &Vec::new()).ok()?;
+            Some(self.frame_energy(&first_frame) + self.frame_energy(&second_frame))
+        })?;
+        let (first, second) = partition(threshold);
+        let first_frame = self
+            .build_frame(&first, &self.tx_buf_acknowledgments)
+            .ok()?;
+        Some((first_frame, first, second))
+    }
+
+    /// Rebuilds the transmission queue from the given messages (and the queued acknowledgments).
+    ///
+    /// Messages are ordered by priority, the lowest priority messages that do not fit in the
+    /// queue are deferred.
+    fn refill_queue(&mut self, mut messages: Vec<LoRaMessage>) {
+        sort_by_priority(&mut messages);
+        let mut deferred = Vec::new();
+        loop {
+            match self.build_frames(&messages, &self.tx_buf_acknowledgments) {
+                Ok(frames) => {
+                    self.tx_buffer = messages;
+                    self.tx_frames = frames;
+                    break;
+                }
+                Err(err) => {
+                    if let Some(msg) = messages.pop() {
+                        deferred.push(msg);
+                    } else {
+                        warn!("Failed to rebuild the transmission queue: {:?}", err);
+                        self.tx_buffer.clear();
+                        self.tx_frames.clear();
+                        break;
+                    }
+                }
+            }
+        }
+        self.defer(deferred);
+    }
+
+    /// Removes the expired messages from the queue (and the deferred messages), and notifies the
+    /// transmission client.
+    fn purge_expired(&mut self) {
+        let now = Instant::now();
+        if !self
+            .tx_buffer
+            .iter()
+            .chain(self.tx_deferred.iter())
+            .any(|msg| msg.is_expired(now))
+        {
+            return;
+        }
+        let (expired, kept): (Vec<LoRaMessage>, Vec<LoRaMessage>) = self
+            .tx_buffer
+            .drain(..)
+            .chain(self.tx_deferred.drain(..))
+            .partition(|msg| msg.is_expired(now));
+        info!(
+            "Removing {} expired messages from the queue.",
+            expired.len()
+        );
+        self.refill_queue(kept);
+        if let Some(client) = &self.tx_client {
+            for msg in expired {
+                let _ = client.transmission_expired(msg.handle, msg.payload); // TODO: Error silenced here!
+            }
+        }
+    }
+
+    /// Defers the given messages to the next transmission.
+    ///
+    /// When too many messages are deferred, the lowest priority ones are dropped and the
+    /// transmission client is notified.
+    fn defer(&mut self, mut messages: Vec<LoRaMessage>) {
+        self.tx_deferred.append(&mut messages);
+        sort_by_priority(&mut self.tx_deferred);
+        if self.tx_deferred.len() > MAX_DEFERRED_MESSAGES {
+            let dropped = self.tx_deferred.split_off(MAX_DEFERRED_MESSAGES);
+            for msg in dropped {
+                info!(
+                    "Dropping a deferred message (priority: {:?}).",
+                    msg.priority
+                );
+                if let Some(client) = &self.tx_client {
+                    let _ = client.transmission_dropped(msg.handle, msg.payload, msg.priority);
+                    // TODO: Error silenced here!
+                }
+            }
         }
     }
 }
@@ -363,11 +912,54 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
         self.rx_client = Some(client);
     }
     fn set_address(&mut self, address: LoRaAddress) {
+        assert!(
+            AddressHeader::new(address, false).is_unicast(),
+            "Reserved address {:#06x} used as the radio address!",
+            address
+        );
         self.address = address;
     }
     fn get_address(&self) -> LoRaAddress {
         self.address
     }
+    fn join_group(&mut self, group: LoRaGroup) -> bool {
+        if group > frame::MAX_GROUP_ID || self.groups.contains(&group) {
+            return false;
+        }
+        self.groups.push(group);
+        true
+    }
+    fn leave_group(&mut self, group: LoRaGroup) -> bool {
+        let len = self.groups.len();
+        self.groups.retain(|g| *g != group);
+        self.groups.len() != len
+    }
+    fn get_groups(&self) -> Vec<LoRaGroup> {
+        self.groups.clone()
+    }
+    fn add_group_member(&mut self, group: LoRaGroup, member: LoRaAddress) -> bool {
+        if group > frame::MAX_GROUP_ID || !AddressHeader::new(member, false).is_unicast() {
+            return false;
+        }
+        let members = self.group_members.entry(group).or_default();
+        if members.contains(&member) {
+            return false;
+        }
+        members.push(member);
+        members.sort();
+        true
+    }
+    fn remove_group_member(&mut self, group: LoRaGroup, member: LoRaAddress) -> bool {
+        if let Some(members) = self.group_members.get_mut(&group) {
+            let len = members.len();
+            members.retain(|m| *m != member);
+            return members.len() != len;
+        }
+        false
+    }
+    fn get_group_members(&self, group: LoRaGroup) -> Vec<LoRaAddress> {
+        self.group_members.get(&group).cloned().unwrap_or_default()
+    }
     fn is_transmitting(&mut self) -> Result<bool, Self::DeviceError> {
         match self.radio.get_state() {
             Ok(state) if state == RadioState::Tx || state == RadioState::FsTx => Ok(true),
@@ -378,6 +970,17 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
             }),
         }
     }
+    fn get_statistics(&self) -> Statistics {
+        self.stats.clone()
+    }
+
+    fn reset_statistics(&mut self) {
+        self.stats = Statistics::default();
+    }
+
+    fn pending_frames(&self) -> usize {
+        self.tx_frames.len() + self.pending_beacon_acknowledgments.len()
+    }
     fn is_listening(&mut self) -> Result<bool, Self::DeviceError> {
         match self.radio.get_state() {
             Ok(state) if state == RadioState::Rx || state == RadioState::FsRx => Ok(true),
@@ -402,16 +1005,15 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                 // Note: It might be possible to optimize a little bit more the number of acknowledgments by frame. Nonetheless, there is several parameters
                 // that intervene on the size of the packet: number of recipients, the number of messages received by minute, etc.. Therefore, while it might
                 // not be optimal, I'm not sure if those particular optimizations could result in significant improvements for the complexity they add.
-                match self.build_frame(&self.tx_buffer, &ack_buf) {
-                    Ok(frame) => {
+                match self.build_frames(&self.tx_buffer, &ack_buf) {
+                    Ok(frames) => {
                         self.tx_buf_acknowledgments = ack_buf;
-                        self.tx_frame = Some(frame);
+                        self.tx_frames = frames;
                         Ok(true)
                     }
-                    Err(RadioError::TooBigFrameError { size }) => {
-                        Err(QueueError::QueueFullError(RadioError::TooBigFrameError {
-                            size,
-                        }))
+                    Err(err @ RadioError::TooBigFrameError { .. })
+                    | Err(err @ RadioError::TooManyFramesError { .. }) => {
+                        Err(QueueError::QueueFullError(err))
                     }
                     Err(err) => Err(QueueError::DeviceError(err)),
                 }
@@ -427,12 +1029,27 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
         }
     }
 
-    fn queue<'b>(
+    fn queue_with_options(
         &mut self,
         dest: LoRaDestination,
-        payload: &'b [u8],
-        ack: bool,
-    ) -> Result<(), QueueError<Self::DeviceError>> {
+        payload: &[u8],
+        options: QueueOptions,
+    ) -> Result<MessageHandle, QueueError<Self::DeviceError>> {
+        let ack = options.ack;
+        // The addresses reserved to the multicast groups and the global address are not peers.
+        let peers: &[LoRaAddress] = match &dest {
+            LoRaDestination::Unique(addr) => core::slice::from_ref(addr),
+            LoRaDestination::Group(addrs) => addrs,
+            _ => &[],
+        };
+        if let Some(addr) = peers
+            .iter()
+            .find(|addr| !AddressHeader::new(**addr, false).is_unicast())
+        {
+            return Err(QueueError::DeviceError(RadioError::InvalidRecipentsError {
+                context: format!("Reserved address used as a peer address : {:#06x}", addr),
+            }));
+        }
         // Construct of the recipient list.
         let recipients = match dest {
             LoRaDestination::Global if ack => vec![frame::GLOBAL_ACKNOWLEDGMENT],
@@ -455,82 +1072,160 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                     }
                 })
                 .collect(),
+            LoRaDestination::Multicast(group) => {
+                if group > frame::MAX_GROUP_ID {
+                    return Err(QueueError::DeviceError(RadioError::InvalidRecipentsError {
+                        context: format!(
+                            "Invalid group identifier : {} (max: {})",
+                            group,
+                            frame::MAX_GROUP_ID
+                        ),
+                    }));
+                }
+                // Register the known members (the most early possible)
+                for member in self.get_group_members(group) {
+                    self.atpc.register_neighbor(member);
+                }
+                vec![AddressHeader::new_group(group, ack).into()]
+            }
         };
-        if recipients.len() > 48 {
+        if recipients.len() > MAX_QUEUED_RECIPIENTS {
             // By design, a packet can only transmit up to 48 clients at a time.
             return Err(QueueError::QueueFullError(
                 RadioError::InvalidRecipentsError {
-                    context: format!("Too many recipients : {}/48", recipients.len()),
+                    context: format!(
+                        "Too many recipients : {}/{}",
+                        recipients.len(),
+                        MAX_QUEUED_RECIPIENTS
+                    ),
                 },
             ));
         }
         // Build the new packet with the future queue and check if it is valid.
+        let handle = MessageHandle::new(self.next_handle);
         let mut buf = self.tx_buffer.clone();
         buf.push(LoRaMessage {
+            handle,
             dest: recipients,
             payload: payload.to_owned(),
+            priority: options.priority,
+            // A time-to-live too long to be represented never expires.
+            expires: options.ttl.and_then(|ttl| Instant::now().checked_add(ttl)),
+            beacon: false,
         });
-        match self.build_frame(&buf, &self.tx_buf_acknowledgments) {
-            Ok(frame) => {
-                self.tx_buffer = buf;
-                self.tx_frame = Some(frame);
-                Ok(())
-            }
-            Err(RadioError::TooBigFrameError { size }) => {
-                Err(QueueError::QueueFullError(RadioError::TooBigFrameError {
-                    size,
-                }))
+        sort_by_priority(&mut buf);
+        let mut preempted = Vec::new();
+        loop {
+            match self.build_frames(&buf, &self.tx_buf_acknowledgments) {
+                Ok(frames) => {
+                    self.next_handle = self.next_handle.wrapping_add(1);
+                    self.tx_buffer = buf;
+                    self.tx_frames = frames;
+                    self.defer(preempted);
+                    return Ok(handle);
+                }
+                Err(err @ RadioError::TooBigFrameError { .. })
+                | Err(err @ RadioError::TooManyFramesError { .. }) => {
+                    // Queue is full, preempts the lowest priority message if it has a lower
+                    // priority than the new one.
+                    match buf.last() {
+                        Some(msg) if msg.priority < options.priority => {
+                            preempted.push(buf.pop().unwrap());
+                        }
+                        _ => return Err(QueueError::QueueFullError(err)),
+                    }
+                }
+                Err(err) => return Err(QueueError::DeviceError(err)),
             }
-            Err(err) => Err(QueueError::DeviceError(err)),
         }
     }
 
     fn transmit(&mut self) -> Result<FrameNonce, Self::DeviceError> {
+        // Expired messages are not worth the airtime.
+        self.purge_expired();
         // Ignore if no trame is available
-        if let None = self.tx_frame {
+        if self.tx_frames.is_empty() && self.pending_beacon_acknowledgments.is_empty() {
             return Ok(0);
         }
         // Report busy device
         if self.is_transmitting()? {
             return Err(RadioError::BusyDevice);
         }
-        let frame = self.tx_frame.as_ref().unwrap().clone(); // TODO: Clone avoidable...
-        let nframes = frame.headers.rec_n_frames.get_frames() as usize;
+        // ATPC: Beacon acknowledgments are short and time sensitive, they go first.
+        self.transmit_beacon_acknowledgments()?;
+        if self.tx_frames.is_empty() {
+            return Ok(0);
+        }
+        let mut frame = self.tx_frames[0].frame.clone(); // TODO: Clone avoidable...
+        let mut nframes = frame.headers.rec_n_frames.get_frames() as usize;
+        // When the duty cycle only allows a shorter transmission, only the highest priority
+        // messages are transmitted, the others are deferred.
+        let mut sent_messages = self.tx_frames[0].messages.clone();
+        let mut leftovers = Vec::new();
+        let available = self.available_channels(nframes);
+        if available > 0 && available < nframes {
+            if let Some((reduced, rest)) = self.reduce_first_frame(available) {
+                info!(
+                    "Duty cycle only allows {} physical frames, deferring {} messages.",
+                    available,
+                    rest.len()
+                );
+                frame = reduced;
+                nframes = frame.headers.rec_n_frames.get_frames() as usize;
+                leftovers = sent_messages.split_off(sent_messages.len() - rest.len());
+            }
+        }
+        // ATPC: A frame for close and far recipients might be cheaper in two transmissions.
+        if matches!(self.group_policy, GroupPolicy::Split) {
+            if let Some((first, kept, mut rest)) = self.split_first_frame(&frame, &sent_messages) {
+                info!(
+                    "Splitting the frame in two transmissions, deferring {} messages.",
+                    rest.len()
+                );
+                frame = first;
+                nframes = frame.headers.rec_n_frames.get_frames() as usize;
+                sent_messages = kept;
+                leftovers.append(&mut rest);
+            }
+        }
         // Check channel availability
-        println!("Transmission check");
+        debug!("Transmission check");
         self.transmission_check(nframes)?;
-        let bytes = frame.to_bytes();
+        // ADR: Select the data rate of the following physical frames, announced in the headers.
+        let addrs = self.frame_recipients(&frame);
+        let data_rate = self
+            .adr
+            .get_min_data_rate(&addrs, &self.data_rate_floors(nframes));
+        let data_rate = announce_data_rate(&mut frame, data_rate);
+        let mut bytes = [0u8; MAX_FRAME_LENGTH];
+        let len = frame.encode_into(&mut bytes)?;
         let mut fcursor = 0;
-        let mut buf = Vec::with_capacity(MAX_LORA_PAYLOAD + 1);
+        let mut buf = [0u8; MAX_LORA_PAYLOAD + 1];
         let mut last = Instant::now();
         let nonce = frame.headers.nonce;
         // ATPC: Calculate the TX power required then transmit
-        println!("Transmission, selecting TX power...");
-        let (tx_power, atpc_farest_peers) = {
-            match &frame.headers.recipients {
-                RecipientHeader::Direct(ah) => self.atpc.get_min_tx_power(vec![ah.get_address()]),
-                RecipientHeader::Group(ahs) => {
-                    let addrs: Vec<u16> = ahs.iter().map(|(ah, _)| ah.get_address()).collect();
-                    self.atpc.get_min_tx_power(addrs)
-                }
-            }
-        };
+        debug!("Transmission, selecting TX power...");
+        let (tx_power, atpc_farest_peers) = self.recipients_tx_power(&addrs);
         self.radio
             .set_power(tx_power)
             .map_err(|src| RadioError::InternalRadioError(src))?;
-        println!("Transmission starting...");
+        debug!("Transmission starting...");
         for ch in self.channels.iter().take(nframes as usize) {
             // TODO: Better Error distinction for Internal Radio Error.
-            println!("Prepare radio for the correct channel");
+            debug!("Prepare radio for the correct channel");
+            // The first physical frame always uses the default data rate, as every peer listens to it.
+            let radio_channel = if fcursor == 0 {
+                &ch.radio_channel
+            } else {
+                ch.data_rate(data_rate)
+            };
             self.radio
-                .set_channel(&ch.radio_channel)
+                .set_channel(radio_channel)
                 .map_err(|src| RadioError::InternalRadioError(src))?;
-            buf.push((FrameType::Message as u8).to_be());
-            let mut end = (fcursor + 1) * MAX_LORA_PAYLOAD;
-            if end > bytes.len() {
-                end = bytes.len();
-            }
-            buf.extend_from_slice(&bytes[fcursor * MAX_LORA_PAYLOAD..end]);
+            buf[0] = (FrameType::Message as u8).to_be();
+            let start = usize::min(fcursor * MAX_LORA_PAYLOAD, len);
+            let end = usize::min(start + MAX_LORA_PAYLOAD, len);
+            buf[1..=(end - start)].copy_from_slice(&bytes[start..end]);
             if fcursor > 0 {
                 // Wait the 600ms period.
                 if let Some(delay) = 600_u32.checked_sub(last.elapsed().as_millis() as u32) {
@@ -538,12 +1233,17 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                 }
             }
             last = Instant::now();
-            println!("Transmission on air");
+            debug!("Transmission on air");
             self.radio
-                .start_transmit(&buf)
+                .start_transmit(&buf[..=(end - start)])
                 .map_err(|src| RadioError::InternalRadioError(src))?;
-            buf.clear();
-            fcursor += 1;
+            let frame_data_rate = if fcursor == 0 { 0 } else { data_rate };
+            self.capture(CapturedFrame::transmitted(
+                fcursor,
+                frame_data_rate,
+                tx_power,
+                &buf[..=(end - start)],
+            ));
             //self.radio.delay_ms(400); // TODO: Adapt delay to the real ToA (from Channel info),
             // currently it will be always : 400ms ToA + 200ms of space.
             while !self
@@ -551,10 +1251,13 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                 .check_transmit()
                 .map_err(|src| RadioError::InternalRadioError(src))?
             {
-                println!("Transmission check");
-                self.radio.delay_ms(self.channels[fcursor].delay.poll_delay);
+                debug!("Transmission check");
+                self.radio.delay_ms(ch.delay.poll_delay);
             }
-            println!("Transmission on channel successful, updating stats");
+            debug!("Transmission on channel successful, updating stats");
+            // Note: the airtime is measured by polling, its precision depends on the poll delay.
+            self.stats
+                .record_transmission(tx_power, last.elapsed(), &addrs, &self.pa_table);
             let consumed = {
                 let (clast, consumed) = self.channel_usages[fcursor];
                 if clast.elapsed().as_micros() > ch.delay.duty_interval.into() {
@@ -567,13 +1270,20 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
             if last.elapsed().as_millis() > 600 {
                 return Err(RadioError::OutOfSync{ context: format!("Frame transmission + channel change should have happened in 600ms, but it is already {}ms late.", last.elapsed().as_millis()-600)});
             }
+            fcursor += 1;
         }
-        println!("Clearing queue, acknowledging the transmission to API client");
+        debug!("Clearing queue, acknowledging the transmission to API client");
 
         let _ = self.tx_history.push(frame.clone());
+        self.atpc.register_transmission(nonce, tx_power, &addrs);
+        let _ = self.tx_powers.push_overwrite((nonce, tx_power));
+        let handles: Vec<MessageHandle> = sent_messages.iter().map(|msg| msg.handle).collect();
+        let _ = self.tx_handles.push_overwrite((nonce, handles.clone()));
         match frame.headers.recipients {
             // Do not require acknowledgment for GLOBAL as we do not want a retransmission.
-            RecipientHeader::Direct(ah) if ah.get_acknowledgment() && !ah.is_global() => {
+            RecipientHeader::Direct(ah)
+                if ah.get_acknowledgment() && !ah.is_global() && !ah.is_group() =>
+            {
                 let _ = self.pending_tx_acknowledgments.push((
                     ah.clone(),
                     frame.headers.nonce.clone(),
@@ -581,30 +1291,55 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                     true,
                 ));
             }
+            RecipientHeader::Direct(ah) if ah.get_acknowledgment() && ah.is_group() => {
+                // Multicast: every known member of the group is expected to acknowledge the frame.
+                for member in self.expand_recipients(&[ah]) {
+                    let should_update = atpc_farest_peers.binary_search(&member).is_ok();
+                    let _ = self.pending_tx_acknowledgments.push((
+                        AddressHeader::new(member, true),
+                        frame.headers.nonce,
+                        last,
+                        should_update,
+                    ));
+                }
+            }
             RecipientHeader::Group(ahs) => {
                 for (ah, _) in ahs {
                     if ah.get_acknowledgment() && !ah.is_global() {
                         // ATPC: Determine if this particular peer should be updated in the ATPC on fail reception.
                         // Reason? In group message, transmit power can be much more higher than the threshold required
                         // by another recipient, therefore we should only update the recipients with the highest threshold.
-                        let should_update =
-                            atpc_farest_peers.binary_search(&ah.get_address()).is_ok();
-                        let _ = self.pending_tx_acknowledgments.push((
-                            ah.clone(),
-                            frame.headers.nonce.clone(),
-                            last.clone(),
-                            should_update,
-                        ));
+                        for member in self.expand_recipients(&[ah]) {
+                            let should_update = atpc_farest_peers.binary_search(&member).is_ok();
+                            let _ = self.pending_tx_acknowledgments.push((
+                                AddressHeader::new(member, true),
+                                frame.headers.nonce,
+                                last,
+                                should_update,
+                            ));
+                        }
                     }
                 }
             }
             _ => { /* No acknowledgment requested */ }
         }
-        self.tx_frame = None;
-        self.tx_buffer.clear();
+        // Only the transmitted frame leaves the queue, the following ones (and the deferred
+        // messages) are kept for the next transmissions.
+        self.tx_frames.remove(0);
         self.tx_buf_acknowledgments.clear();
+        let mut remaining: Vec<LoRaMessage> = self
+            .tx_frames
+            .iter()
+            .flat_map(|qf| qf.messages.iter().cloned())
+            .collect();
+        remaining.append(&mut leftovers);
+        remaining.append(&mut self.tx_deferred);
+        self.refill_queue(remaining);
         if let Some(client) = &self.tx_client {
             let _ = client.transmission_done(nonce); // TODO: Error silenced here!
+            for handle in handles {
+                let _ = client.message_sent(handle, nonce); // TODO: Error silenced here!
+            }
         }
         Ok(nonce)
     }
@@ -637,6 +1372,10 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
             if update_atpc {
                 self.atpc.report_failed_reception(ah.get_address());
             }
+            // ADR: The data rate might be too fast for this peer.
+            self.adr.report_failure(ah.get_address());
+            self.stats.record_failure(ah.get_address());
+            self.trace_outcome(ah.get_address(), nonce, None);
             if let Some(tx_client) = &self.tx_client {
                 let mut frame_ = self.tx_history.pop();
                 while frame_.is_some() && frame_.as_ref().unwrap().headers.nonce != nonce {
@@ -644,37 +1383,41 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                 }
                 if let Some(frame) = frame_ {
                     let _ = self.tx_history.push(frame.clone());
-                    match &frame.headers.recipients {
-                        RecipientHeader::Direct(ah2) if ah.get_address() == ah2.get_address() => {
-                            for pl in frame.payloads {
-                                let _ = tx_client.transmission_failed(
-                                    ah.get_address(),
-                                    nonce.clone(),
-                                    pl.clone(),
-                                ); // TODO: Error silenced here!
-                            }
+                    for mid in self.payload_ids_for(&frame, ah.get_address()) {
+                        // The payload is handed back as it was queued, not as it was transmitted.
+                        if let Some(Ok(pl)) = frame.decompressed_payload(mid) {
+                            let _ = tx_client.transmission_failed(ah.get_address(), nonce, pl);
+                            // TODO: Error silenced here!
                         }
-                        RecipientHeader::Group(ahs) => {
-                            for (ah2, pf) in ahs {
-                                if ah.get_address() == ah2.get_address() {
-                                    for mid in pf.to_message_ids() {
-                                        if let Some(pl) = frame.payloads.get(mid as usize) {
-                                            let _ = tx_client.transmission_failed(
-                                                ah.get_address(),
-                                                nonce.clone(),
-                                                pl.clone(),
-                                            ); // TODO: Error silenced here!
-                                        }
-                                    }
-                                }
-                            }
+                        if let Some(handle) = self.handle_of(nonce, mid) {
+                            let _ = tx_client.message_failed(handle, ah.get_address());
+                            // TODO: Error silenced here!
                         }
-                        _ => {}
                     }
                 }
             }
             next = self.pending_tx_acknowledgments.pop();
         }
+        // ATPC: Notify the application of the links considered down.
+        for peer in self.atpc.take_lost_links() {
+            info!("Link with peer {} is down.", peer);
+            // ADR: Start again from the default data rate.
+            self.adr.report_failure(peer);
+            if let Some(tx_client) = &self.tx_client {
+                let _ = tx_client.link_down(peer); // TODO: Error silenced here!
+            }
+        }
+        // ATPC: Acknowledge the sweeps of beacons that are over.
+        let now = Instant::now();
+        let ended: Vec<LoRaAddress> = self
+            .rx_beacon_sweeps
+            .iter()
+            .filter(|(_, sweep)| now.duration_since(sweep.last) > BEACON_SWEEP_TIMEOUT)
+            .map(|(peer, _)| *peer)
+            .collect();
+        for peer in ended {
+            self.acknowledge_beacon_sweep(peer);
+        }
         info!("checking_reception...");
         if self
             .radio
@@ -688,33 +1431,47 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                     info!("Packet ignored: size <= 0");
                     return Ok(false);
                 }
-                if buf[0] != (FrameType::Message as u8)
-                    && buf[0] != (FrameType::BroadcastCheckSignal as u8)
-                {
-                    info!(
-                        "Packet ignored: FrameType is not Message, it is {}!",
-                        buf[0]
-                    );
-                    return Ok(false);
+                self.capture(CapturedFrame::received(
+                    0,
+                    0,
+                    packet_info.rssi(),
+                    packet_info.snr,
+                    &buf[..size],
+                ));
+                let frame_type = match FrameType::try_from(buf[0]) {
+                    Ok(frame_type) => frame_type,
+                    Err(_) => {
+                        info!("Packet ignored: unknown FrameType {}!", buf[0]);
+                        return Ok(false);
+                    }
+                };
+                if frame_type == FrameType::BeaconAcknowledgment {
+                    let (ack, _read) = BeaconAcknowledgment::try_from_bytes(&buf[1..size])
+                        .map_err(|src| RadioError::FrameError(src))?;
+                    if let Some(snr) = packet_info.snr {
+                        self.adr
+                            .report_reception(ack.sender.get_address(), snr as f32);
+                    }
+                    if ack.recipient.get_address() != self.address {
+                        info!("Beacon acknowledgment ignored because it is not addressed for us.");
+                        return Ok(false);
+                    }
+                    self.handle_beacon_acknowledgment(ack);
+                    self.start_reception()?;
+                    return Ok(true);
                 }
-                let (headers, _read) = RadioHeaders::try_from_bytes(&buf[1..])
+                let (headers, _read) = RadioHeadersView::try_from_bytes(&buf[1..size])
                     .map_err(|src| RadioError::FrameError(src))?;
+                // ADR: The following physical frames use the announced data rate.
+                let data_rate = headers.data_rate();
+                // ADR: Every frame gives an insight on the link with its sender.
+                if let Some(snr) = packet_info.snr {
+                    self.adr
+                        .report_reception(headers.sender.get_address(), snr as f32);
+                }
                 let interest = match headers.recipients {
-                    RecipientHeader::Direct(ah)
-                        if ah.get_address() == self.address || ah.is_global() =>
-                    {
-                        true
-                    }
-                    RecipientHeader::Group(ahs) => {
-                        if let Some((_ah, _pl)) = ahs
-                            .iter()
-                            .find(|(ah, _pl)| ah.get_address() == self.address || ah.is_global())
-                        {
-                            true
-                        } else {
-                            false
-                        }
-                    }
+                    RecipientsView::Direct(ah) if self.is_recipient(&ah) => true,
+                    RecipientsView::Group(mut ahs) => ahs.any(|(ah, _pl)| self.is_recipient(&ah)),
                     _ => {
                         info!("Message ignored because it is not addressed for us.");
                         false
@@ -729,17 +1486,23 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                     // If a authenticating method (or signing method) have to be added it should be added in
                     // the lead frame (otherwise the attacker can craft its own signature too) */
                     let nframes = headers.rec_n_frames.get_frames();
-                    if nframes > 5 {
+                    if nframes == 0 || nframes > 5 {
                         self.start_reception()?;
                         return Ok(false);
                     } // SECURITY: Do not accept arbitrary value from the outside.
-                    let mut cursor = Cursor::new(Vec::with_capacity(
-                        (nframes as usize * MAX_LORA_PAYLOAD) as usize,
-                    ));
-                    cursor.write_all(&mut buf[1..])?;
-                    for ch in self.channels.iter().skip(1).take((nframes - 1) as usize) {
+                      // The physical frames are reassembled without allocation.
+                    let mut msg = [0u8; MAX_FRAME_LENGTH];
+                    let mut len = usize::min(size - 1, MAX_LORA_PAYLOAD);
+                    msg[..len].copy_from_slice(&buf[1..=len]);
+                    for (channel, ch) in self
+                        .channels
+                        .iter()
+                        .enumerate()
+                        .skip(1)
+                        .take((nframes - 1) as usize)
+                    {
                         self.radio
-                            .set_channel(&ch.radio_channel)
+                            .set_channel(ch.data_rate(data_rate))
                             .map_err(|src| RadioError::InternalRadioError(src))?;
                         let mut i = 0;
                         let mut new_frame = self
@@ -755,21 +1518,33 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
                             i += 1;
                         }
                         if !new_frame {
-                            eprintln!("Silencing missing following frame.");
+                            warn!("Silencing missing following frame.");
                             self.start_reception()?;
                             return Ok(false);
                         }
                         let mut buf_fp = [0u8; 256];
-                        let (_size, _packet_info) = self
+                        let (size_fp, packet_info_fp) = self
                             .radio
                             .get_received(&mut buf_fp)
                             .map_err(|src| RadioError::InternalRadioError(src))?;
-                        cursor.write_all(&buf_fp[1..])?;
+                        self.capture(CapturedFrame::received(
+                            channel,
+                            data_rate,
+                            packet_info_fp.rssi(),
+                            packet_info_fp.snr,
+                            &buf_fp[..size_fp],
+                        ));
+                        let part = usize::min(size_fp.saturating_sub(1), MAX_LORA_PAYLOAD);
+                        msg[len..(len + part)].copy_from_slice(&buf_fp[1..=part]);
+                        len += part;
+                    }
+                    if frame_type == FrameType::BroadcastCheckSignal {
+                        // ATPC: Beacons are not forwarded to the RxClient, their RSSI is recorded
+                        // to acknowledge the whole sweep at once.
+                        self.handle_beacon(&msg[..len], packet_info.rssi())?;
+                    } else {
+                        self.handle_message(&msg[..len], self.rssi_target - packet_info.rssi())?;
                     }
-                    self.handle_message(
-                        cursor.into_inner(),
-                        self.rssi_target - packet_info.rssi(),
-                    )?;
                     self.start_reception()?;
                     return Ok(true);
                 }
@@ -790,23 +1565,68 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
             return Err(QueueError::DeviceError(RadioError::BusyDevice));
         }
 
-        let mut tx_buf = vec![];
-        tx_buf.push(LoRaMessage {
-            dest: vec![frame::GLOBAL_ACKNOWLEDGMENT],
-            payload: vec![],
-        });
-
         // Check channel availability
-        println!("Channel check");
+        debug!("Channel check");
         self.transmission_check(1)?;
-        let mut last;
+        self.transmit_sweep(frame::GLOBAL_ACKNOWLEDGMENT)?;
+        Ok(())
+    }
+
+    fn is_probe_needed(&mut self) -> bool {
+        self.atpc.is_probe_needed()
+    }
+
+    fn transmit_probe(&mut self) -> Result<Option<LoRaAddress>, QueueError<Self::DeviceError>> {
+        // Report busy device
+        if self.is_transmitting()? {
+            return Err(QueueError::DeviceError(RadioError::BusyDevice));
+        }
+
+        // Check channel availability before registering the probe to the ATPC.
+        self.transmission_check(1)?;
+        let Some(peer) = self.atpc.next_probe() else {
+            return Ok(None);
+        };
+        info!("Probing peer {}.", peer);
+        self.transmit_sweep(AddressHeader::new(peer, true).into())?;
+        Ok(Some(peer))
+    }
+}
 
+impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> LoRaRadio<'a, A, T, C, E> {
+    /// Transmits a sweep of ATPC beacons to the given destination (the global address or a
+    /// single peer), one beacon per transmission power of the ATPC.
+    ///
+    /// The channel availability must have been checked beforehand.
+    fn transmit_sweep(&mut self, dest: u16) -> Result<(), RadioError<E>> {
         let powers = self.atpc.get_beacon_powers();
+        let mut beacon = BeaconPayload {
+            sweep: 0,
+            tpi: 0,
+            levels: powers.len() as u8,
+        };
+        let mut tx_buf = vec![];
+        tx_buf.push(LoRaMessage {
+            handle: MessageHandle::new(0),
+            dest: vec![dest],
+            payload: beacon.to_bytes(),
+            priority: MessagePriority::Control,
+            expires: None,
+            beacon: true,
+        });
+        let mut last;
 
-        let mut buf = Vec::new();
+        let mut buf = [0u8; MAX_LORA_PAYLOAD + 1];
+        buf[0] = (FrameType::BroadcastCheckSignal as u8).to_be();
         for (tpi, tp) in powers.iter().enumerate() {
-            let frame = self.build_frame(&tx_buf, &Vec::new())?;
-            let bytes = frame.to_bytes();
+            let mut frame = self.build_frame(&tx_buf, &Vec::new())?;
+            // The first beacon identifies the sweep.
+            if tpi == 0 {
+                beacon.sweep = frame.headers.nonce;
+            }
+            beacon.tpi = tpi as u8;
+            beacon.encode_into(&mut frame.payloads[0])?;
+            let len = frame.encode_into(&mut buf[1..])?;
             self.radio
                 .set_power(*tp)
                 .map_err(|src| RadioError::InternalRadioError(src))?;
@@ -814,22 +1634,22 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
             self.radio
                 .set_channel(&self.channels[0].radio_channel)
                 .map_err(|src| RadioError::InternalRadioError(src))?;
-            buf.push((FrameType::BroadcastCheckSignal as u8).to_be());
-            buf.extend_from_slice(&bytes[..]);
             last = Instant::now();
             self.radio
-                .start_transmit(&buf)
+                .start_transmit(&buf[..=len])
                 .map_err(|src| RadioError::InternalRadioError(src))?;
-            buf.clear();
+            self.capture(CapturedFrame::transmitted(0, 0, *tp, &buf[..=len]));
             while !self
                 .radio
                 .check_transmit()
                 .map_err(|src| RadioError::InternalRadioError(src))?
             {
-                println!("Transmission check");
+                debug!("Transmission check");
                 self.radio.delay_us(self.channels[0].delay.poll_delay);
             }
-            println!("Beacon at TP {} successful, updating stats", tp);
+            debug!("Beacon at TP {} successful, updating stats", tp);
+            self.stats
+                .record_beacon(*tp, last.elapsed(), &self.pa_table);
             let consumed = {
                 let (clast, consumed) = self.channel_usages[0];
                 if clast.elapsed().as_secs() > self.channels[0].delay.duty_interval.into() {
@@ -846,9 +1666,7 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> Device<'a> for LoRaRadio<'
         }
         Ok(())
     }
-}
 
-impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> LoRaRadio<'a, A, T, C, E> {
     /// Transmission checks, it checks that every channel can be used and that the radio channel is not busy right now.
     ///
     /// Note: it only checks that the first channel is not busy, as channels, should be use in the order by protocol
@@ -858,16 +1676,8 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> LoRaRadio<'a, A, T, C, E>
     fn transmission_check(&mut self, nframes: usize) -> Result<(), RadioError<E>> {
         // Checking delay of channels
         let now = Instant::now();
-        for (i, ch) in self.channels.iter().enumerate().take(nframes as usize) {
-            let (last_used, consumed) = self.channel_usages[i];
-            if (now - last_used) < Duration::from_secs(ch.delay.duty_interval)
-                && consumed.as_secs_f64() / (ch.delay.duty_interval as f64) > ch.delay.duty_cycle
-            {
-                return Err(RadioError::DutyCycleConsumed);
-            }
-            if (now - last_used) < Duration::from_micros(ch.delay.min_delay) {
-                return Err(RadioError::MinChannelDelayError);
-            }
+        for i in 0..usize::min(nframes, self.channels.len()) {
+            self.check_channel_delay(i, now)?;
         }
         // Checking channels are available (well that the first one is available in reality based on protocol
         // assumptions).
@@ -904,34 +1714,358 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> LoRaRadio<'a, A, T, C, E>
         return Ok(());
     }
 
+    /// Checks if the given address header designates this radio, either directly, globally or
+    /// through one of the multicast groups it subscribed to.
+    fn is_recipient(&self, ah: &AddressHeader) -> bool {
+        ah.get_address() == self.address
+            || ah.is_global()
+            || ah.get_group().is_some_and(|g| self.groups.contains(&g))
+    }
+
+    /// Checks if the given address header designates the given peer, either directly or through
+    /// one of the known members of a multicast group.
+    fn is_addressed_to(&self, ah: &AddressHeader, peer: LoRaAddress) -> bool {
+        ah.get_address() == peer
+            || ah.get_group().is_some_and(|g| {
+                self.group_members
+                    .get(&g)
+                    .is_some_and(|members| members.contains(&peer))
+            })
+    }
+
+    /// Expands the multicast group addresses into the addresses of their known members.
+    ///
+    /// A group address is never a recipient by itself: a group without any known member is
+    /// left out, so it gets no state of its own in the ATPC, the ADR or the statistics.
+    fn expand_recipients(&self, ahs: &[AddressHeader]) -> Vec<LoRaAddress> {
+        let mut addrs = Vec::new();
+        for ah in ahs {
+            match ah.get_group() {
+                Some(group) => {
+                    if let Some(members) = self.group_members.get(&group) {
+                        addrs.extend_from_slice(members);
+                    }
+                }
+                None => addrs.push(ah.get_address()),
+            }
+        }
+        addrs.sort();
+        addrs.dedup();
+        addrs
+    }
+
+    /// Gets the demodulation floors of the data rates available on the first `nframes` channels.
+    ///
+    /// A data rate is only available if every channel of the transmission has it, and the floor
+    /// is the highest of the channels.
+    fn data_rate_floors(&self, nframes: usize) -> Vec<f32> {
+        let used = &self.channels[..usize::min(nframes, self.channels.len())];
+        let available = used.iter().map(|ch| ch.data_rates.len()).min().unwrap_or(0);
+        (0..available)
+            .map(|i| {
+                used.iter()
+                    .map(|ch| ch.data_rates[i].min_snr)
+                    .fold(f32::MIN, f32::max)
+            })
+            .collect()
+    }
+
+    /// Handles the reception of a beacon, by recording its RSSI in the sweep of its transmitter.
+    ///
+    /// The sweep is acknowledged once its last beacon is received, or after
+    /// [BEACON_SWEEP_TIMEOUT] (see [check_reception](Device::check_reception)).
+    fn handle_beacon(&mut self, msg: &[u8], rssi: i16) -> Result<bool, RadioError<E>> {
+        let (frame, _length) = RadioFrameView::try_from_bytes(msg)?;
+        let ack = match frame.headers.recipients {
+            RecipientsView::Direct(ah) => ah.get_acknowledgment(),
+            RecipientsView::Group(_) => false,
+        };
+        let Some(payload) = frame.payload(0) else {
+            info!("Beacon ignored: missing beacon payload.");
+            return Ok(false);
+        };
+        let (beacon, _read) = BeaconPayload::try_from_bytes(payload)?;
+        if !ack {
+            return Ok(false);
+        }
+        let peer = frame.headers.sender.get_address();
+        if self
+            .rx_beacon_sweeps
+            .get(&peer)
+            .is_some_and(|sweep| sweep.sweep != beacon.sweep)
+        {
+            // A new sweep started, the previous one is over.
+            self.acknowledge_beacon_sweep(peer);
+        }
+        let sweep = self
+            .rx_beacon_sweeps
+            .entry(peer)
+            .or_insert_with(|| BeaconSweep {
+                sweep: beacon.sweep,
+                rssi: vec![None; beacon.levels as usize],
+                last: Instant::now(),
+            });
+        if let Some(level) = sweep.rssi.get_mut(beacon.tpi as usize) {
+            *level = Some(rssi);
+        }
+        sweep.last = Instant::now();
+        if beacon.tpi + 1 >= beacon.levels {
+            self.acknowledge_beacon_sweep(peer);
+        }
+        Ok(true)
+    }
+
+    /// Queues the acknowledgment of the sweep of beacons of a peer.
+    fn acknowledge_beacon_sweep(&mut self, peer: LoRaAddress) {
+        if let Some(sweep) = self.rx_beacon_sweeps.remove(&peer) {
+            if self.pending_beacon_acknowledgments.len() >= MAX_PENDING_BEACON_ACKNOWLEDGMENTS {
+                self.pending_beacon_acknowledgments.remove(0);
+            }
+            self.pending_beacon_acknowledgments
+                .push(BeaconAcknowledgment {
+                    sender: AddressHeader::new(self.address, false),
+                    recipient: AddressHeader::new(peer, false),
+                    sweep: sweep.sweep,
+                    rssi: sweep.rssi,
+                });
+        }
+    }
+
+    /// Handles the reception of an acknowledgment of one of our sweeps of beacons.
+    fn handle_beacon_acknowledgment(&mut self, ack: BeaconAcknowledgment) {
+        info!(
+            "Peer {} acknowledged the beacons of sweep {}.",
+            ack.sender.get_address(),
+            ack.sweep
+        );
+        self.atpc
+            .report_beacon_acknowledgment(ack.sender.get_address(), ack.sweep, &ack.rssi);
+    }
+
+    /// Transmits the pending beacon acknowledgments, each one in its own physical frame on the
+    /// first channel.
+    fn transmit_beacon_acknowledgments(&mut self) -> Result<(), RadioError<E>> {
+        while let Some(ack) = self.pending_beacon_acknowledgments.first().cloned() {
+            self.transmission_check(1)?;
+            let tx_power = self.atpc.get_tx_power(ack.recipient.get_address());
+            let mut buf = [0u8; MAX_LORA_PAYLOAD + 1];
+            buf[0] = (FrameType::BeaconAcknowledgment as u8).to_be();
+            let len = ack.encode_into(&mut buf[1..])?;
+            self.radio
+                .set_power(tx_power)
+                .map_err(|src| RadioError::InternalRadioError(src))?;
+            self.radio
+                .set_channel(&self.channels[0].radio_channel)
+                .map_err(|src| RadioError::InternalRadioError(src))?;
+            let last = Instant::now();
+            self.radio
+                .start_transmit(&buf[..=len])
+                .map_err(|src| RadioError::InternalRadioError(src))?;
+            self.capture(CapturedFrame::transmitted(0, 0, tx_power, &buf[..=len]));
+            while !self
+                .radio
+                .check_transmit()
+                .map_err(|src| RadioError::InternalRadioError(src))?
+            {
+                self.radio.delay_us(self.channels[0].delay.poll_delay);
+            }
+            self.stats.record_transmission(
+                tx_power,
+                last.elapsed(),
+                &[ack.recipient.get_address()],
+                &self.pa_table,
+            );
+            let consumed = {
+                let (clast, consumed) = self.channel_usages[0];
+                if clast.elapsed().as_secs() > self.channels[0].delay.duty_interval {
+                    Duration::from_millis(400)
+                } else {
+                    consumed + Duration::from_millis(400)
+                }
+            };
+            self.channel_usages[0] = (last, consumed);
+            self.pending_beacon_acknowledgments.remove(0);
+            if let Some(delay) = 600_u32.checked_sub(last.elapsed().as_millis() as u32) {
+                self.radio.delay_ms(delay);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the identifiers of the payloads of a transmitted frame that were addressed to the
+    /// given peer.
+    fn payload_ids_for(&self, frame: &RadioFrameWithHeaders, peer: LoRaAddress) -> Vec<u8> {
+        match &frame.headers.recipients {
+            RecipientHeader::Direct(ah) if self.is_addressed_to(ah, peer) => {
+                (0..frame.headers.payloads).collect()
+            }
+            RecipientHeader::Direct(_) => Vec::new(),
+            RecipientHeader::Group(ahs) => {
+                let mut ids: Vec<u8> = ahs
+                    .iter()
+                    .filter(|(ah, _)| self.is_addressed_to(ah, peer))
+                    .flat_map(|(_, pf)| pf.to_message_ids())
+                    .collect();
+                ids.sort();
+                ids.dedup();
+                ids
+            }
+        }
+    }
+
+    /// Gets the handle of a payload of a transmitted frame.
+    fn handle_of(&self, nonce: FrameNonce, payload_id: u8) -> Option<MessageHandle> {
+        self.tx_handles
+            .iter()
+            .find(|(n, _)| *n == nonce)
+            .and_then(|(_, handles)| handles.get(payload_id as usize))
+            .copied()
+    }
+
+    /// Removes the awaited acknowledgment of a peer for a frame, once it has been received.
+    fn clear_pending_acknowledgment(&mut self, peer: LoRaAddress, nonce: FrameNonce) {
+        let pending: Vec<(AddressHeader, FrameNonce, Instant, bool)> =
+            self.pending_tx_acknowledgments.pop_iter().collect();
+        for item in pending {
+            if item.0.get_address() != peer || item.1 != nonce {
+                let _ = self.pending_tx_acknowledgments.push(item);
+            }
+        }
+    }
+
+    /// Logs the outcome of a transmission to a peer as a [TraceRecord] (see [crate::trace]).
+    ///
+    /// `rssi` is the RSSI of the frame at the peer, `None` if the frame was not acknowledged.
+    fn trace_outcome(&self, peer: LoRaAddress, nonce: FrameNonce, rssi: Option<i16>) {
+        let Some(tx_power) = self
+            .tx_powers
+            .iter()
+            .find(|(n, _)| *n == nonce)
+            .map(|(_, tp)| *tp)
+        else {
+            return;
+        };
+        let record = TraceRecord {
+            time_ms: sys::unix_time().as_millis() as u64,
+            neighbor: peer,
+            tx_power,
+            ack: rssi.is_some(),
+            rssi,
+        };
+        info!(target: TRACE_TARGET, "{}", record);
+    }
+
+    /// Learns a new member of a multicast group, following an acknowledgment from this peer
+    /// of a frame we sent to the group.
+    fn learn_group_member(&mut self, peer: LoRaAddress, nonce: FrameNonce) {
+        let groups: Vec<LoRaGroup> = self
+            .tx_history
+            .iter()
+            .filter(|frame| frame.headers.nonce == nonce)
+            .flat_map(|frame| match &frame.headers.recipients {
+                RecipientHeader::Direct(ah) => vec![*ah],
+                RecipientHeader::Group(ahs) => ahs.iter().map(|(ah, _)| *ah).collect(),
+            })
+            .filter_map(|ah| ah.get_group())
+            .collect();
+        for group in groups {
+            if self.add_group_member(group, peer) {
+                info!(
+                    "Peer {} is now a known member of the group {}.",
+                    peer, group
+                );
+            }
+        }
+    }
+
+    /// Checks that the given channel respects its duty cycle and minimal delay.
+    fn check_channel_delay(&self, channel: usize, now: Instant) -> Result<(), RadioError<E>> {
+        let ch = &self.channels[channel];
+        let (last_used, consumed) = self.channel_usages[channel];
+        if (now - last_used) < Duration::from_secs(ch.delay.duty_interval)
+            && consumed.as_secs_f64() / (ch.delay.duty_interval as f64) > ch.delay.duty_cycle
+        {
+            return Err(RadioError::DutyCycleConsumed);
+        }
+        if (now - last_used) < Duration::from_micros(ch.delay.min_delay) {
+            return Err(RadioError::MinChannelDelayError);
+        }
+        Ok(())
+    }
+
+    /// Counts the channels (in protocol order) that can currently be used to transmit, up to
+    /// `nframes` channels.
+    fn available_channels(&self, nframes: usize) -> usize {
+        let now = Instant::now();
+        (0..usize::min(nframes, self.channels.len()))
+            .take_while(|i| self.check_channel_delay(*i, now).is_ok())
+            .count()
+    }
+
     /// Once a message is fully receive in its entirety, this method is called to verify
     /// integrity of the message and called the needed Client and send acknowledgment.
-    fn handle_message(&mut self, msg: Vec<u8>, drssi: i16) -> Result<bool, RadioError<E>> {
+    fn handle_message(&mut self, msg: &[u8], drssi: i16) -> Result<bool, RadioError<E>> {
         // TODO: Verify integrity if implemented
         info!("Handling reception of an incoming frame.");
-        let (frame, _length) = RadioFrameWithHeaders::try_from_bytes(msg.as_slice())?;
+        let (frame, _length) = RadioFrameView::try_from_bytes(msg)?;
+        for (ah, nonce, drssi) in frame.acknowledgments() {
+            if ah.get_address() == self.address {
+                debug!(
+                    "Peer {} acknowledged the reception of message {} with a DRSSI of {} dBm",
+                    frame.headers.sender.get_address(),
+                    nonce,
+                    drssi
+                );
+                self.learn_group_member(frame.headers.sender.get_address(), nonce);
+                self.clear_pending_acknowledgment(frame.headers.sender.get_address(), nonce);
+                // ATPC: Report the successful reception of a frame by a peer.
+                self.atpc.report_successful_reception(
+                    frame.headers.sender.get_address(),
+                    nonce,
+                    drssi,
+                );
+                self.stats
+                    .record_delivery(frame.headers.sender.get_address());
+                // The RSSI target of the peer is assumed to be the same than ours.
+                self.trace_outcome(
+                    frame.headers.sender.get_address(),
+                    nonce,
+                    Some(self.rssi_target - drssi),
+                );
+            }
+        }
         if let Some(tx_client) = &self.tx_client {
-            for (ah, nonce, drssi) in frame.acknowledgments {
+            for (ah, nonce, _drssi) in frame.acknowledgments() {
                 if ah.get_address() == self.address {
-                    println!("DEBUG: Peer {} acknowledged the reception of message {} with a DRSSI of {} dBm", frame.headers.sender.get_address(), nonce.clone(), drssi.clone());
-                    // ATPC: Report the successful reception of a frame by a peer.
-                    self.atpc.report_successful_reception(
-                        frame.headers.sender.get_address(),
-                        nonce.clone(),
-                        drssi,
-                    );
                     // TxClient: Report successful reception by a peer.
                     let _ = tx_client
                         .transmission_successful(frame.headers.sender.get_address(), nonce.clone());
                     // TODO: Error silenced here.
+                    if let Some(sent) = self
+                        .tx_history
+                        .iter()
+                        .find(|sent| sent.headers.nonce == nonce)
+                    {
+                        for mid in self.payload_ids_for(sent, frame.headers.sender.get_address()) {
+                            if let Some(handle) = self.handle_of(nonce, mid) {
+                                let _ = tx_client
+                                    .message_delivered(handle, frame.headers.sender.get_address());
+                                // TODO: Error silenced here.
+                            }
+                        }
+                    }
                 }
             }
         }
         if let Some(client) = &self.rx_client {
-            match frame.headers.recipients {
-                RecipientHeader::Direct(ah) => {
+            match &frame.headers.recipients {
+                RecipientsView::Direct(ah) => {
                     info!("Forwarding payloads to the RxClient.");
-                    for pl in frame.payloads {
+                    for id in 0..frame.headers.payloads {
+                        let Some(Ok(pl)) = frame.decompressed_payload(id) else {
+                            warn!("Badly formatted frame: invalid payload {}.", id);
+                            continue;
+                        };
                         let _ = client.receive(
                             frame.headers.sender.get_address(),
                             pl,
@@ -947,27 +2081,26 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> LoRaRadio<'a, A, T, C, E>
                     }
                     Ok(true)
                 }
-                RecipientHeader::Group(ahs) => {
+                RecipientsView::Group(ahs) => {
                     let mut reception_flag = false;
-                    for (ah, pl) in ahs {
-                        if !(ah.get_address() == self.address || ah.is_global()) {
+                    for (ah, pl) in ahs.clone() {
+                        if !self.is_recipient(&ah) {
                             continue;
                         }
                         info!("Forwarding payloads to the RxClient.");
                         let pls: Vec<Vec<u8>> = pl
                             .to_message_ids()
                             .iter()
-                            .filter_map(|id| frame.payloads.get(*id as usize))
-                            .cloned()
+                            .filter_map(|id| frame.decompressed_payload(*id)?.ok())
                             .collect();
-                        println!("Debug pls: {:?}", pls);
-                        if dbg!(pls.len()) < frame.headers.payloads.into() {
-                            eprintln!("WARN: Badly formatted frame: missing message.");
+                        debug!("Payloads for us: {:?}", pls);
+                        if pls.len() < frame.headers.payloads.into() {
+                            warn!("Badly formatted frame: missing message.");
                         }
                         for pl in pls {
                             let _ = client.receive(
-                                dbg!(frame.headers.sender.get_address()),
-                                dbg!(pl),
+                                frame.headers.sender.get_address(),
+                                pl,
                                 frame.headers.nonce,
                             ); // TODO: Error silenced here!
                         }
@@ -999,8 +2132,72 @@ impl<'a, A: ATPC, C: Debug, E: Debug, T: Radio<C, E>> LoRaRadio<'a, A, T, C, E>
 /// Internal LoRa Message representation.
 #[derive(Debug, Clone)]
 struct LoRaMessage {
+    /// Handle given to the application when the message was queued.
+    ///
+    /// Chunks of the same message (when it is split in several frames) share the same handle.
+    handle: MessageHandle,
     dest: Vec<LoRaAddress>,
     payload: Vec<u8>,
+    priority: MessagePriority,
+    /// Instant after which the message should not be transmitted anymore.
+    expires: Option<Instant>,
+    /// Is the payload an ATPC beacon? It is rewritten in place for every transmission power, so
+    /// it is never compressed.
+    beacon: bool,
+}
+
+/// Internal representation of a sweep of beacons being received.
+#[derive(Debug, Clone)]
+struct BeaconSweep {
+    /// Nonce of the first beacon of the sweep.
+    sweep: FrameNonce,
+    /// RSSI of the received beacons, by transmission power level.
+    rssi: Vec<Option<i16>>,
+    /// Instant of the last received beacon of the sweep.
+    last: Instant,
+}
+
+impl LoRaMessage {
+    /// Checks if the message expired at the given instant.
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+/// Sorts the given messages by decreasing priority, keeping the queue order for messages of
+/// the same priority.
+fn sort_by_priority(messages: &mut [LoRaMessage]) {
+    messages.sort_by_key(|msg| core::cmp::Reverse(msg.priority));
+}
+
+/// Announces the data rate of the physical frames following the first one in the headers of a
+/// frame (see [DATA_RATE_EXTENSION](frame::DATA_RATE_EXTENSION)), returns the announced data rate.
+///
+/// A frame in a single physical frame has no following physical frame, and the default data rate
+/// is kept when the announcement does not fit in the physical frames of the frame.
+fn announce_data_rate(frame: &mut frame::RadioFrameWithHeaders, data_rate: u8) -> u8 {
+    let nframes = frame.headers.rec_n_frames.get_frames() as usize;
+    if data_rate == 0 || nframes < 2 {
+        return 0;
+    }
+    let mut announced = frame.clone();
+    announced.headers.set_data_rate(data_rate);
+    let ffsize = announced.headers.size()
+        + announced
+            .headers
+            .acknowledgments_size(&announced.acknowledgments);
+    if ffsize > MAX_LORA_PAYLOAD || announced.size() / MAX_LORA_PAYLOAD + 1 != nframes {
+        return 0;
+    }
+    *frame = announced;
+    data_rate
+}
+
+/// Internal representation of a frame ready to be transmitted, with the messages it carries.
+#[derive(Debug, Clone)]
+struct QueuedFrame {
+    frame: frame::RadioFrameWithHeaders,
+    messages: Vec<LoRaMessage>,
 }
 
 /// Error representation of either IO or Frame serialization errors.
@@ -1017,6 +2214,18 @@ where
     #[error("First frame (containing headers and acknowledgments) is too big to be transmitted (is: {}B, max: {}B)!", .size, MAX_LORA_PAYLOAD)]
     TooBigFirstFrameError { size: usize },
 
+    /// Frame contains too much recipients (more than 16) in one frame.
+    #[error("Frame contains too much recipients in one frame (is: {}, max: {})!", .count, MAX_RECIPIENTS_PER_FRAME)]
+    TooManyRecipientsError { count: usize },
+
+    /// Frame contains too much payloads (more than 16) in one frame.
+    #[error("Frame contains too much payloads in one frame (is: {}, max: {})!", .count, MAX_PAYLOADS_PER_FRAME)]
+    TooManyPayloadsError { count: usize },
+
+    /// The queue would need too much frames (more than 3) to be transmitted.
+    #[error("The queue would need too much frames to be transmitted (is: {}, max: {})!", .count, MAX_QUEUED_FRAMES)]
+    TooManyFramesError { count: usize },
+
     /// Frame contains too much acknowledgments (more than 16) in one frame.
     #[error("Frame contains too much acknowledgments in one frame (is: {}, max: 16)!", .count)]
     TooManyAcknowledgmentsError { count: usize },
@@ -1039,6 +2248,7 @@ where
     FrameError(#[from] frame::FrameError),
 
     /// Underlying I/O Error.
+    #[cfg(feature = "std")]
     #[error("Underlying I/O Error.")]
     IoError(#[from] std::io::Error),
 
@@ -1066,3 +2276,962 @@ where
     #[error("Unknown radio error. Context: {}", .context)]
     Unknown { context: String },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use radio_sx127x::device::lora::Irq;
+    use radio_sx127x::device::{Interrupts as RadioInterrupts, PacketInfo};
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    /// Physical radio recording the transmitted packets and the selected channels, and receiving
+    /// the packets pushed in its queue. The channel is always free and the delays are skipped.
+    #[derive(Default)]
+    struct MockRadio {
+        power: i8,
+        channels: Vec<u32>,
+        sent: Vec<(i8, Vec<u8>)>,
+        incoming: VecDeque<Vec<u8>>,
+    }
+
+    impl Transmit for MockRadio {
+        type Error = ();
+
+        fn start_transmit(&mut self, data: &[u8]) -> Result<(), ()> {
+            self.sent.push((self.power, data.to_vec()));
+            Ok(())
+        }
+
+        fn check_transmit(&mut self) -> Result<bool, ()> {
+            Ok(true)
+        }
+    }
+
+    impl Receive for MockRadio {
+        type Error = ();
+        type Info = PacketInfo;
+
+        fn start_receive(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn check_receive(&mut self, _restart: bool) -> Result<bool, ()> {
+            Ok(!self.incoming.is_empty())
+        }
+
+        fn get_received(&mut self, buff: &mut [u8]) -> Result<(usize, PacketInfo), ()> {
+            let packet = self.incoming.pop_front().ok_or(())?;
+            buff[..packet.len()].copy_from_slice(&packet);
+            Ok((
+                packet.len(),
+                PacketInfo {
+                    rssi: -90,
+                    snr: Some(5),
+                },
+            ))
+        }
+    }
+
+    impl Power for MockRadio {
+        type Error = ();
+
+        fn set_power(&mut self, power: i8) -> Result<(), ()> {
+            self.power = power;
+            Ok(())
+        }
+    }
+
+    impl radio::Channel for MockRadio {
+        type Channel = u32;
+        type Error = ();
+
+        fn set_channel(&mut self, channel: &u32) -> Result<(), ()> {
+            self.channels.push(*channel);
+            Ok(())
+        }
+    }
+
+    impl State for MockRadio {
+        type State = RadioState;
+        type Error = ();
+
+        fn set_state(&mut self, _state: RadioState) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn get_state(&mut self) -> Result<RadioState, ()> {
+            Ok(RadioState::Standby)
+        }
+    }
+
+    impl Interrupts for MockRadio {
+        type Irq = RadioInterrupts;
+        type Error = ();
+
+        fn get_interrupts(&mut self, _clear: bool) -> Result<RadioInterrupts, ()> {
+            Ok(RadioInterrupts::LoRa(Irq::RX_TIMEOUT))
+        }
+    }
+
+    impl DelayMs<u32> for MockRadio {
+        fn delay_ms(&mut self, _ms: u32) {}
+    }
+
+    impl DelayUs<u32> for MockRadio {
+        fn delay_us(&mut self, _us: u32) {}
+    }
+
+    /// ATPC with fixed transmission powers (10dBm by default), recording every address it is
+    /// given.
+    #[derive(Default)]
+    struct RecordingATPC {
+        powers: HashMap<LoRaAddress, i8>,
+        addresses: Vec<LoRaAddress>,
+        acknowledged: Vec<LoRaAddress>,
+    }
+
+    impl ATPC for RecordingATPC {
+        fn is_beacon_needed(&self) -> bool {
+            false
+        }
+
+        fn get_beacon_powers(&self) -> Vec<i8> {
+            vec![]
+        }
+
+        fn register_beacon(&mut self, _tpi: usize, _nonce: FrameNonce) {}
+
+        fn register_transmission(
+            &mut self,
+            _nonce: FrameNonce,
+            _tx_power: i8,
+            recipients: &[LoRaAddress],
+        ) {
+            self.addresses.extend_from_slice(recipients);
+        }
+
+        fn register_neighbor(&mut self, neighbor_addr: LoRaAddress) -> bool {
+            self.addresses.push(neighbor_addr);
+            true
+        }
+
+        fn unregister_neighbor(&mut self, neighbor_addr: LoRaAddress) -> bool {
+            self.addresses.push(neighbor_addr);
+            true
+        }
+
+        fn get_tx_power(&mut self, neighbor_addr: LoRaAddress) -> i8 {
+            self.addresses.push(neighbor_addr);
+            self.powers.get(&neighbor_addr).copied().unwrap_or(10)
+        }
+
+        fn get_default_tx_power(&mut self) -> i8 {
+            14
+        }
+
+        fn report_successful_reception(
+            &mut self,
+            neighbor_addr: LoRaAddress,
+            _nonce: FrameNonce,
+            _drssi: i16,
+        ) {
+            self.acknowledged.push(neighbor_addr);
+        }
+
+        fn report_failed_reception(&mut self, neighbor_addr: LoRaAddress) {
+            self.addresses.push(neighbor_addr);
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Inbox(Rc<RefCell<Vec<(LoRaAddress, Vec<u8>)>>>);
+
+    impl RxClient for Inbox {
+        fn receive(
+            &self,
+            sender: LoRaAddress,
+            payload: Vec<u8>,
+            _nonce: FrameNonce,
+        ) -> Result<(), ()> {
+            self.0.borrow_mut().push((sender, payload));
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Outbox(Rc<RefCell<Vec<LoRaAddress>>>);
+
+    impl TxClient for Outbox {
+        fn transmission_done(&self, _nonce: FrameNonce) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn transmission_successful(
+            &self,
+            recipient: LoRaAddress,
+            _nonce: FrameNonce,
+        ) -> Result<(), ()> {
+            self.0.borrow_mut().push(recipient);
+            Ok(())
+        }
+
+        fn transmission_failed(
+            &self,
+            _sender: LoRaAddress,
+            _nonce: FrameNonce,
+            _payload: Vec<u8>,
+        ) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    /// Notification received by a [TxLog].
+    #[derive(Clone, Debug, PartialEq)]
+    enum TxEvent {
+        Done(FrameNonce),
+        Successful(LoRaAddress, FrameNonce),
+        Failed(LoRaAddress, FrameNonce, Vec<u8>),
+        Dropped(MessageHandle, Vec<u8>, MessagePriority),
+        Expired(MessageHandle, Vec<u8>),
+        Sent(MessageHandle, FrameNonce),
+        Delivered(MessageHandle, LoRaAddress),
+        MessageFailed(MessageHandle, LoRaAddress),
+    }
+
+    /// Transmission client recording every notification, in order.
+    #[derive(Clone, Default)]
+    struct TxLog(Rc<RefCell<Vec<TxEvent>>>);
+
+    impl TxLog {
+        fn take(&self) -> Vec<TxEvent> {
+            self.0.borrow_mut().drain(..).collect()
+        }
+    }
+
+    impl TxClient for TxLog {
+        fn transmission_done(&self, nonce: FrameNonce) -> Result<(), ()> {
+            self.0.borrow_mut().push(TxEvent::Done(nonce));
+            Ok(())
+        }
+
+        fn transmission_successful(
+            &self,
+            recipient: LoRaAddress,
+            nonce: FrameNonce,
+        ) -> Result<(), ()> {
+            self.0
+                .borrow_mut()
+                .push(TxEvent::Successful(recipient, nonce));
+            Ok(())
+        }
+
+        fn transmission_failed(
+            &self,
+            sender: LoRaAddress,
+            nonce: FrameNonce,
+            payload: Vec<u8>,
+        ) -> Result<(), ()> {
+            self.0
+                .borrow_mut()
+                .push(TxEvent::Failed(sender, nonce, payload));
+            Ok(())
+        }
+
+        fn transmission_dropped(
+            &self,
+            handle: MessageHandle,
+            payload: Vec<u8>,
+            priority: MessagePriority,
+        ) -> Result<(), ()> {
+            self.0
+                .borrow_mut()
+                .push(TxEvent::Dropped(handle, payload, priority));
+            Ok(())
+        }
+
+        fn transmission_expired(&self, handle: MessageHandle, payload: Vec<u8>) -> Result<(), ()> {
+            self.0.borrow_mut().push(TxEvent::Expired(handle, payload));
+            Ok(())
+        }
+
+        fn message_sent(&self, handle: MessageHandle, nonce: FrameNonce) -> Result<(), ()> {
+            self.0.borrow_mut().push(TxEvent::Sent(handle, nonce));
+            Ok(())
+        }
+
+        fn message_delivered(
+            &self,
+            handle: MessageHandle,
+            recipient: LoRaAddress,
+        ) -> Result<(), ()> {
+            self.0
+                .borrow_mut()
+                .push(TxEvent::Delivered(handle, recipient));
+            Ok(())
+        }
+
+        fn message_failed(&self, handle: MessageHandle, recipient: LoRaAddress) -> Result<(), ()> {
+            self.0
+                .borrow_mut()
+                .push(TxEvent::MessageFailed(handle, recipient));
+            Ok(())
+        }
+    }
+
+    type TestRadio<'a> = LoRaRadio<'a, RecordingATPC, MockRadio, u32, ()>;
+
+    /// Channels `10 * i`, with a faster data rate `10 * i + 1`.
+    fn channels() -> Vec<Channel<u32>> {
+        (0..5)
+            .map(|ch| Channel {
+                radio_channel: 10 * ch,
+                data_rates: vec![DataRate {
+                    radio_channel: 10 * ch + 1,
+                    min_snr: -10.0,
+                }],
+                delay: DelayParams {
+                    duty_cycle: 1.0,
+                    min_delay: 0,
+                    poll_delay: 0,
+                    duty_interval: 3600,
+                },
+            })
+            .collect()
+    }
+
+    fn test_radio<'a>(
+        channels: &'a [Channel<u32>],
+        address: LoRaAddress,
+        rx_client: Option<Box<dyn RxClient>>,
+        tx_client: Option<Box<dyn TxClient>>,
+    ) -> TestRadio<'a> {
+        LoRaRadio::new(
+            MockRadio::default(),
+            channels,
+            RecordingATPC::default(),
+            -100,
+            rx_client,
+            tx_client,
+            address,
+        )
+    }
+
+    /// Delivers the packets transmitted by `from` to `to`, returns whether `to` was interested.
+    fn deliver(from: &TestRadio, to: &mut TestRadio) -> bool {
+        to.radio
+            .incoming
+            .extend(from.radio.sent.iter().map(|(_, packet)| packet.clone()));
+        let interested = to.check_reception().unwrap();
+        to.radio.incoming.clear();
+        interested
+    }
+
+    /// Makes the acknowledgments awaited by `radio` overdue, they are reported missing on the next
+    /// reception check.
+    fn expire_acknowledgments(radio: &mut TestRadio) {
+        let overdue = Instant::now().checked_sub(Duration::from_secs(61)).unwrap();
+        let pending: Vec<_> = radio.pending_tx_acknowledgments.pop_iter().collect();
+        for (ah, nonce, _instant, update_atpc) in pending {
+            let _ = radio
+                .pending_tx_acknowledgments
+                .push((ah, nonce, overdue, update_atpc));
+        }
+    }
+
+    fn is_unicast(addr: &LoRaAddress) -> bool {
+        AddressHeader::new(*addr, false).is_unicast()
+    }
+
+    #[test]
+    fn radio_group_subscribe_deliver_acknowledge() {
+        let channels = channels();
+        let outbox = Outbox::default();
+        let inbox = Inbox::default();
+        let outsider_inbox = Inbox::default();
+        let mut sender = test_radio(&channels, 0x0001, None, Some(Box::new(outbox.clone())));
+        let mut member = test_radio(&channels, 0x0002, Some(Box::new(inbox.clone())), None);
+        let mut outsider = test_radio(
+            &channels,
+            0x0003,
+            Some(Box::new(outsider_inbox.clone())),
+            None,
+        );
+        assert!(member.join_group(5));
+        assert!(!member.join_group(5));
+
+        sender
+            .queue(LoRaDestination::Multicast(5), b"Hello group", true)
+            .unwrap();
+        sender.transmit().unwrap();
+        // Without any known member, the group gets the default power and no state of its own.
+        assert_eq!(sender.radio.sent.len(), 1);
+        assert_eq!(sender.radio.sent[0].0, 14);
+        assert!(sender.atpc.addresses.is_empty());
+        assert!(sender.get_statistics().by_neighbor.is_empty());
+
+        assert!(deliver(&sender, &mut member));
+        assert_eq!(*inbox.0.borrow(), vec![(0x0001, b"Hello group".to_vec())]);
+        assert!(!deliver(&sender, &mut outsider));
+        assert!(outsider_inbox.0.borrow().is_empty());
+
+        // The member acknowledges, the sender learns it belongs to the group.
+        assert!(member.queue_acknowledgments().unwrap());
+        member.transmit().unwrap();
+        sender.radio.sent.clear();
+        assert!(deliver(&member, &mut sender));
+        assert_eq!(sender.get_group_members(5), vec![0x0002]);
+        assert_eq!(*outbox.0.borrow(), vec![0x0002]);
+        assert_eq!(sender.atpc.acknowledged, vec![0x0002]);
+
+        // The next transmission to the group is sized for its known member.
+        sender
+            .queue(LoRaDestination::Multicast(5), b"Hello again", true)
+            .unwrap();
+        sender.transmit().unwrap();
+        assert_eq!(sender.radio.sent[0].0, 10);
+        assert!(sender.atpc.addresses.contains(&0x0002));
+        assert!(sender.atpc.addresses.iter().all(is_unicast));
+        let stats = sender.get_statistics();
+        assert!(stats.by_neighbor.keys().all(is_unicast));
+        assert!(stats.by_neighbor.contains_key(&0x0002));
+    }
+
+    #[test]
+    fn radio_rejects_reserved_addresses() {
+        let channels = channels();
+        let mut radio = test_radio(&channels, 0x0001, None, None);
+        assert!(matches!(
+            radio.queue(LoRaDestination::Unique(0x7005), b"", true),
+            Err(QueueError::DeviceError(
+                RadioError::InvalidRecipentsError { .. }
+            ))
+        ));
+        assert!(matches!(
+            radio.queue(LoRaDestination::Group(vec![0x0002, 0x7FFF]), b"", true),
+            Err(QueueError::DeviceError(
+                RadioError::InvalidRecipentsError { .. }
+            ))
+        ));
+        assert!(!radio.add_group_member(5, 0x7001));
+        assert!(radio.add_group_member(5, 0x0002));
+        assert!(radio
+            .queue(LoRaDestination::Unique(0x6FFF), b"", true)
+            .is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn radio_rejects_reserved_own_address() {
+        let channels = channels();
+        test_radio(&channels, 0x7001, None, None);
+    }
+
+    #[test]
+    fn radio_split_single_message() {
+        let channels = channels();
+        let mut radio = test_radio(&channels, 0x0001, None, None);
+        radio.atpc.powers.insert(0x0002, 2);
+        radio.atpc.powers.insert(0x0003, 14);
+        radio.set_group_policy(GroupPolicy::Split);
+        radio.set_pa_current_table(PaCurrentTable::new(vec![(2, 1.0), (14, 100.0)], 3.3));
+        // A payload just too long for a single physical frame with both recipients, a copy for a
+        // single recipient (with a shorter recipient header) fits in one.
+        let message = |dest: Vec<LoRaAddress>, len: usize| LoRaMessage {
+            handle: MessageHandle::new(1),
+            dest,
+            payload: vec![0x42; len],
+            priority: MessagePriority::default(),
+            expires: None,
+            beacon: false,
+        };
+        let n_frames = |radio: &TestRadio, dest: Vec<LoRaAddress>, len: usize| {
+            radio
+                .build_frame(&vec![message(dest, len)], &Vec::new())
+                .unwrap()
+                .headers
+                .rec_n_frames
+                .get_frames()
+        };
+        let len = (1..MAX_LORA_PAYLOAD)
+            .find(|len| n_frames(&radio, vec![0x0002, 0x0003], *len) > 1)
+            .unwrap();
+        assert_eq!(n_frames(&radio, vec![0x0003], len), 1);
+
+        radio
+            .queue(
+                LoRaDestination::Group(vec![0x0002, 0x0003]),
+                &vec![0x42; len],
+                true,
+            )
+            .unwrap();
+        let mut near = test_radio(&channels, 0x0002, None, None);
+        let mut far = test_radio(&channels, 0x0003, None, None);
+        // The close recipient is served first at a low transmission power.
+        radio.transmit().unwrap();
+        assert_eq!(radio.radio.sent.len(), 1);
+        assert_eq!(radio.radio.sent[0].0, 2);
+        assert!(deliver(&radio, &mut near));
+        assert!(!deliver(&radio, &mut far));
+        // Then the copy for the far recipient.
+        radio.radio.sent.clear();
+        radio.transmit().unwrap();
+        assert_eq!(radio.radio.sent.len(), 1);
+        assert_eq!(radio.radio.sent[0].0, 14);
+        assert!(deliver(&radio, &mut far));
+        assert!(radio.tx_frames.is_empty());
+    }
+
+    #[test]
+    fn radio_split_recipients_across_frames() {
+        let channels = channels();
+        let recipients = |count: usize| (0..count as LoRaAddress).map(|i| 0x0100 + i);
+        for (count, n_frames) in [
+            (MAX_RECIPIENTS_PER_FRAME, 1),
+            (MAX_RECIPIENTS_PER_FRAME + 1, 2),
+            (MAX_QUEUED_RECIPIENTS, MAX_QUEUED_FRAMES),
+        ] {
+            let mut radio = test_radio(&channels, 0x0001, None, None);
+            radio
+                .queue(
+                    LoRaDestination::Group(recipients(count).collect()),
+                    b"Hello everyone",
+                    false,
+                )
+                .unwrap();
+            assert_eq!(radio.tx_frames.len(), n_frames);
+            let mut dest: Vec<LoRaAddress> = Vec::new();
+            for qf in &radio.tx_frames {
+                assert_eq!(qf.messages.len(), 1);
+                assert!(qf.messages[0].dest.len() <= MAX_RECIPIENTS_PER_FRAME);
+                dest.extend(&qf.messages[0].dest);
+            }
+            assert_eq!(dest, recipients(count).collect::<Vec<_>>());
+
+            // The last recipient receives the payload once, in the last frame.
+            let inbox = Inbox::default();
+            let last = recipients(count).last().unwrap();
+            let mut recipient = test_radio(&channels, last, Some(Box::new(inbox.clone())), None);
+            for i in 0..n_frames {
+                radio.radio.sent.clear();
+                radio.transmit().unwrap();
+                assert_eq!(radio.radio.sent.len(), 1);
+                assert_eq!(deliver(&radio, &mut recipient), i == n_frames - 1);
+            }
+            assert!(radio.tx_frames.is_empty());
+            assert_eq!(
+                *inbox.0.borrow(),
+                vec![(0x0001, b"Hello everyone".to_vec())]
+            );
+        }
+
+        let mut radio = test_radio(&channels, 0x0001, None, None);
+        assert!(matches!(
+            radio.queue(
+                LoRaDestination::Group(recipients(MAX_QUEUED_RECIPIENTS + 1).collect()),
+                b"Hello everyone",
+                false,
+            ),
+            Err(QueueError::QueueFullError(
+                RadioError::InvalidRecipentsError { .. }
+            ))
+        ));
+        assert!(radio.tx_frames.is_empty());
+    }
+
+    #[test]
+    fn radio_split_payloads_across_frames() {
+        let channels = channels();
+        let mut radio = test_radio(&channels, 0x0001, None, None);
+        for count in 1..=MAX_PAYLOADS_PER_FRAME * MAX_QUEUED_FRAMES {
+            radio
+                .queue(LoRaDestination::Unique(0x0002), &[count as u8], false)
+                .unwrap();
+            assert_eq!(
+                radio.tx_frames.len(),
+                count.div_ceil(MAX_PAYLOADS_PER_FRAME)
+            );
+        }
+        assert!(radio
+            .tx_frames
+            .iter()
+            .all(|qf| qf.messages.len() == MAX_PAYLOADS_PER_FRAME));
+        assert!(matches!(
+            radio.queue(LoRaDestination::Unique(0x0002), b"One too many", false),
+            Err(QueueError::QueueFullError(_))
+        ));
+        assert_eq!(radio.tx_frames.len(), MAX_QUEUED_FRAMES);
+
+        // The payloads are received in the queue order.
+        let inbox = Inbox::default();
+        let mut recipient = test_radio(&channels, 0x0002, Some(Box::new(inbox.clone())), None);
+        for _ in 0..MAX_QUEUED_FRAMES {
+            radio.radio.sent.clear();
+            radio.transmit().unwrap();
+            assert!(deliver(&radio, &mut recipient));
+        }
+        assert!(radio.tx_frames.is_empty());
+        let received: Vec<Vec<u8>> = inbox.0.borrow().iter().map(|(_, pl)| pl.clone()).collect();
+        assert_eq!(
+            received,
+            (1..=MAX_PAYLOADS_PER_FRAME * MAX_QUEUED_FRAMES)
+                .map(|count| vec![count as u8])
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn radio_split_frame_size_boundary() {
+        let channels = channels();
+        let radio = test_radio(&channels, 0x0001, None, None);
+        let n_frames = |lens: &[usize]| {
+            let messages: Vec<LoRaMessage> = lens
+                .iter()
+                .enumerate()
+                .map(|(i, len)| LoRaMessage {
+                    handle: MessageHandle::new(i as u32),
+                    dest: vec![0x0002],
+                    payload: vec![0x42; *len],
+                    priority: MessagePriority::default(),
+                    expires: None,
+                    beacon: false,
+                })
+                .collect();
+            radio.build_frames(&messages, &Vec::new())
+        };
+        // The largest payload fitting next to a first one, one more byte needs a second frame.
+        let len = (1..MAX_FRAME_LENGTH)
+            .rev()
+            .find(|len| matches!(n_frames(&[100, *len]), Ok(frames) if frames.len() == 1))
+            .unwrap();
+        assert!(n_frames(&[100, len]).unwrap()[0].frame.size() <= MAX_FRAME_LENGTH);
+        let frames = n_frames(&[100, len + 1]).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].messages[0].payload.len(), 100);
+        assert_eq!(frames[1].messages[0].payload.len(), len + 1);
+    }
+
+    #[test]
+    fn radio_data_rate_announcement() {
+        let channels = channels();
+        let inbox = Inbox::default();
+        let mut sender = test_radio(&channels, 0x0001, None, None);
+        let mut receiver = test_radio(&channels, 0x0002, Some(Box::new(inbox.clone())), None);
+        for _ in 0..5 {
+            sender.data_rate_adapter().report_reception(0x0002, 5.0);
+        }
+        let payload = vec![0x42; 300];
+        sender
+            .queue(LoRaDestination::Unique(0x0002), &payload, false)
+            .unwrap();
+        sender.transmit().unwrap();
+        assert_eq!(sender.radio.sent.len(), 2);
+        // The physical header only holds the frame type, the data rate is in the headers.
+        assert!(sender
+            .radio
+            .sent
+            .iter()
+            .all(|(_, packet)| packet[0] == FrameType::Message as u8));
+        let (headers, _) = RadioHeadersView::try_from_bytes(&sender.radio.sent[0].1[1..]).unwrap();
+        assert_eq!(headers.data_rate(), 1);
+        assert_eq!(sender.radio.channels, vec![0, 11]);
+
+        assert!(deliver(&sender, &mut receiver));
+        assert_eq!(receiver.radio.channels, vec![11, 0]);
+        assert_eq!(*inbox.0.borrow(), vec![(0x0001, payload)]);
+
+        // A frame in a single physical frame does not announce a data rate.
+        sender.radio.sent.clear();
+        sender
+            .queue(LoRaDestination::Unique(0x0002), b"short", false)
+            .unwrap();
+        sender.transmit().unwrap();
+        let (headers, _) = RadioHeadersView::try_from_bytes(&sender.radio.sent[0].1[1..]).unwrap();
+        assert_eq!(headers.extensions.count(), 0);
+    }
+
+    #[test]
+    fn radio_failed_transmission_returns_queued_payload() {
+        let channels = channels();
+        let log = TxLog::default();
+        let mut radio = test_radio(&channels, 0x0001, None, Some(Box::new(log.clone())));
+        radio.set_compression(true);
+        let payload = b"Hello hello hello hello hello hello hello!".to_vec();
+        radio
+            .queue(LoRaDestination::Unique(0x0002), &payload, true)
+            .unwrap();
+        radio.transmit().unwrap();
+        let nonce = radio.tx_history.iter().last().unwrap().headers.nonce;
+        assert!(radio
+            .tx_history
+            .iter()
+            .last()
+            .unwrap()
+            .compressed
+            .contains(0));
+        log.take();
+
+        expire_acknowledgments(&mut radio);
+        assert!(!radio.check_reception().unwrap());
+        assert!(log
+            .take()
+            .contains(&TxEvent::Failed(0x0002, nonce, payload)));
+    }
+
+    #[test]
+    fn radio_compresses_control_messages() {
+        let channels = channels();
+        let inbox = Inbox::default();
+        let mut sender = test_radio(&channels, 0x0001, None, None);
+        let mut receiver = test_radio(&channels, 0x0002, Some(Box::new(inbox.clone())), None);
+        sender.set_compression(true);
+        let payload = b"Hello hello hello hello hello hello hello!".to_vec();
+        let options = QueueOptions {
+            priority: MessagePriority::Control,
+            ..QueueOptions::default()
+        };
+        sender
+            .queue_with_options(LoRaDestination::Unique(0x0002), &payload, options)
+            .unwrap();
+        sender.transmit().unwrap();
+        assert!(sender
+            .tx_history
+            .iter()
+            .last()
+            .unwrap()
+            .compressed
+            .contains(0));
+        assert!(deliver(&sender, &mut receiver));
+        assert_eq!(*inbox.0.borrow(), vec![(0x0001, payload)]);
+    }
+
+    #[test]
+    fn radio_expired_messages_are_not_transmitted() {
+        let channels = channels();
+        let log = TxLog::default();
+        let inbox = Inbox::default();
+        let mut sender = test_radio(&channels, 0x0001, None, Some(Box::new(log.clone())));
+        let mut receiver = test_radio(&channels, 0x0002, Some(Box::new(inbox.clone())), None);
+        let with_ttl = |ttl| QueueOptions {
+            ttl: Some(ttl),
+            ..QueueOptions::default()
+        };
+        let expired = sender
+            .queue_with_options(
+                LoRaDestination::Unique(0x0002),
+                b"Too late",
+                with_ttl(Duration::ZERO),
+            )
+            .unwrap();
+        // A time-to-live overflowing the clock never expires.
+        let forever = sender
+            .queue_with_options(
+                LoRaDestination::Unique(0x0002),
+                b"Whenever",
+                with_ttl(Duration::MAX),
+            )
+            .unwrap();
+        let queued = sender.tx_buffer.iter().find(|msg| msg.handle == forever);
+        assert!(queued.unwrap().expires.is_none());
+        sender
+            .queue(LoRaDestination::Unique(0x0002), b"Right now", false)
+            .unwrap();
+
+        let nonce = sender.transmit().unwrap();
+        let events = log.take();
+        assert!(events.contains(&TxEvent::Expired(expired, b"Too late".to_vec())));
+        assert!(events.contains(&TxEvent::Sent(forever, nonce)));
+        assert!(!events.iter().any(|event| matches!(event,
+            TxEvent::Sent(handle, _) if *handle == expired)));
+        assert!(deliver(&sender, &mut receiver));
+        assert_eq!(
+            *inbox.0.borrow(),
+            vec![
+                (0x0001, b"Whenever".to_vec()),
+                (0x0001, b"Right now".to_vec())
+            ]
+        );
+
+        // Nothing is left to expire.
+        sender.radio.sent.clear();
+        assert_eq!(sender.transmit().unwrap(), 0);
+        assert!(sender.radio.sent.is_empty());
+        assert!(log.take().is_empty());
+    }
+
+    #[test]
+    fn radio_message_outcomes() {
+        let channels = channels();
+        let log = TxLog::default();
+        let inbox = Inbox::default();
+        let mut sender = test_radio(&channels, 0x0001, None, Some(Box::new(log.clone())));
+        let mut near = test_radio(&channels, 0x0002, Some(Box::new(inbox.clone())), None);
+        let delivered = sender
+            .queue(LoRaDestination::Unique(0x0002), b"Delivered", true)
+            .unwrap();
+        let failed = sender
+            .queue(LoRaDestination::Unique(0x0003), b"Failed", true)
+            .unwrap();
+        let expired = sender
+            .queue_with_options(
+                LoRaDestination::Unique(0x0002),
+                b"Expired",
+                QueueOptions {
+                    ttl: Some(Duration::ZERO),
+                    ..QueueOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(delivered != failed && failed != expired);
+
+        // Both remaining payloads leave in the same frame.
+        let nonce = sender.transmit().unwrap();
+        assert_eq!(
+            log.take(),
+            vec![
+                TxEvent::Expired(expired, b"Expired".to_vec()),
+                TxEvent::Done(nonce),
+                TxEvent::Sent(delivered, nonce),
+                TxEvent::Sent(failed, nonce),
+            ]
+        );
+
+        // Only the first recipient acknowledges.
+        assert!(deliver(&sender, &mut near));
+        assert_eq!(*inbox.0.borrow(), vec![(0x0001, b"Delivered".to_vec())]);
+        assert!(near.queue_acknowledgments().unwrap());
+        near.transmit().unwrap();
+        assert!(deliver(&near, &mut sender));
+        assert_eq!(
+            log.take(),
+            vec![
+                TxEvent::Successful(0x0002, nonce),
+                TxEvent::Delivered(delivered, 0x0002),
+            ]
+        );
+
+        // The missing acknowledgment of the second one is reported after a while.
+        assert!(!sender.check_reception().unwrap());
+        assert!(log.take().is_empty());
+        expire_acknowledgments(&mut sender);
+        assert!(!sender.check_reception().unwrap());
+        assert_eq!(
+            log.take(),
+            vec![
+                TxEvent::Failed(0x0003, nonce, b"Failed".to_vec()),
+                TxEvent::MessageFailed(failed, 0x0003),
+            ]
+        );
+    }
+
+    #[test]
+    fn radio_priority_preemption() {
+        let channels = channels();
+        let log = TxLog::default();
+        let mut radio = test_radio(&channels, 0x0001, None, Some(Box::new(log.clone())));
+        let with_priority = |priority| QueueOptions {
+            priority,
+            ..QueueOptions::default()
+        };
+        // Fills the queue with small bulk payloads.
+        let mut bulk = Vec::new();
+        loop {
+            let payload = vec![bulk.len() as u8; 60];
+            match radio.queue_with_options(
+                LoRaDestination::Unique(0x0002),
+                &payload,
+                with_priority(MessagePriority::Bulk),
+            ) {
+                Ok(handle) => bulk.push((handle, payload)),
+                Err(QueueError::QueueFullError(_)) => break,
+                Err(err) => panic!("Unexpected error: {:?}", err),
+            }
+        }
+        assert_eq!(bulk.len(), MAX_PAYLOADS_PER_FRAME * MAX_QUEUED_FRAMES);
+        // A payload cannot preempt payloads of the same priority.
+        let large = vec![0x42; 1200];
+        assert!(matches!(
+            radio.queue_with_options(
+                LoRaDestination::Unique(0x0002),
+                &large,
+                with_priority(MessagePriority::Bulk)
+            ),
+            Err(QueueError::QueueFullError(_))
+        ));
+        assert!(log.take().is_empty());
+
+        // A higher priority payload goes first, the last bulk payloads are deferred.
+        let urgent = radio
+            .queue_with_options(
+                LoRaDestination::Unique(0x0002),
+                &large,
+                with_priority(MessagePriority::Normal),
+            )
+            .unwrap();
+        assert_eq!(radio.tx_buffer[0].handle, urgent);
+        assert_eq!(radio.tx_deferred.len(), MAX_PAYLOADS_PER_FRAME);
+        assert!(log.take().is_empty());
+        // The bulk payloads not fitting in the deferred messages anymore are dropped.
+        let alarm = radio
+            .queue_with_options(
+                LoRaDestination::Unique(0x0002),
+                &large,
+                with_priority(MessagePriority::Alarm),
+            )
+            .unwrap();
+        assert_eq!(radio.tx_buffer[0].handle, alarm);
+        assert_eq!(radio.tx_buffer[1].handle, urgent);
+        assert_eq!(radio.tx_deferred.len(), MAX_DEFERRED_MESSAGES);
+        let dropped: Vec<MessageHandle> = log
+            .take()
+            .into_iter()
+            .map(|event| match event {
+                TxEvent::Dropped(handle, payload, MessagePriority::Bulk) => {
+                    assert!(bulk.contains(&(handle, payload)));
+                    handle
+                }
+                event => panic!("Unexpected event: {:?}", event),
+            })
+            .collect();
+        assert!(!dropped.is_empty());
+        let queued = |handle: &MessageHandle| {
+            radio
+                .tx_buffer
+                .iter()
+                .chain(radio.tx_deferred.iter())
+                .any(|msg| msg.handle == *handle)
+        };
+        assert!(dropped.iter().all(|handle| !queued(handle)));
+        assert_eq!(
+            bulk.iter().filter(|(handle, _)| queued(handle)).count() + dropped.len(),
+            bulk.len()
+        );
+
+        // Every payload but the dropped ones is eventually transmitted, by decreasing priority.
+        let mut sent = Vec::new();
+        for _ in 0..bulk.len() {
+            if radio.transmit().unwrap() == 0 {
+                break;
+            }
+            sent.extend(log.take().into_iter().filter_map(|event| match event {
+                TxEvent::Sent(handle, _) => Some(handle),
+                _ => None,
+            }));
+        }
+        assert_eq!(sent[..2], [alarm, urgent]);
+        assert_eq!(sent.len(), bulk.len() + 2 - dropped.len());
+        assert!(bulk
+            .iter()
+            .all(|(handle, _)| sent.contains(handle) != dropped.contains(handle)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn radio_rejects_unsupported_frame_version() {
+        let channels = channels();
+        let mut radio = test_radio(&channels, 0x0001, None, None);
+        radio.set_frame_version(frame::COMPACT_VERSION);
+        radio.set_frame_version(3);
+    }
+}
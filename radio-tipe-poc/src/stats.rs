@@ -0,0 +1,276 @@
+//! Transmission statistics and energy accounting.
+//!
+//! The [LoRaRadio](crate::radio::LoRaRadio) accounts every physical transmission (frames, beacons
+//! and beacon acknowledgments) by transmission power and by neighbor, along with the deliveries
+//! and failures reported by the acknowledgments. The energy consumed by the radio is estimated
+//! from a [PaCurrentTable] of the radio module.
+//!
+//! ## Usage
+//! ```rust,ignore
+//! let stats = device.get_statistics();
+//! println!("Energy consumed: {:.1} mJ", stats.total_energy_mj());
+//! stats.write_csv(&mut std::fs::File::create("stats.csv")?)?;
+//! ```
+use crate::prelude::*;
+use crate::sys;
+use crate::LoRaAddress;
+
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+
+/// Current drawn by a radio module while transmitting, by transmission power.
+///
+/// The current between two entries of the table is linearly interpolated.
+#[derive(Clone, Debug)]
+pub struct PaCurrentTable {
+    /// Transmission power (dBm) and current (mA), sorted by transmission power.
+    entries: Vec<(i8, f32)>,
+    /// Supply voltage (V).
+    voltage: f32,
+}
+
+impl PaCurrentTable {
+    /// Builds a table from a list of transmission powers (dBm) and their current (mA).
+    pub fn new(mut entries: Vec<(i8, f32)>, voltage: f32) -> Self {
+        assert!(!entries.is_empty(), "Empty PA current table!");
+        entries.sort_by_key(|(tp, _)| *tp);
+        Self { entries, voltage }
+    }
+
+    /// Approximate table of a RFM95W (SX1276) module using the PA_BOOST output, at 3.3V.
+    pub fn rfm95w() -> Self {
+        Self::new(
+            vec![
+                (2, 24.0),
+                (5, 28.0),
+                (8, 33.0),
+                (11, 41.0),
+                (14, 55.0),
+                (17, 90.0),
+                (20, 120.0),
+            ],
+            3.3,
+        )
+    }
+
+    /// Gets the current (mA) drawn when transmitting at a transmission power.
+    pub fn current(&self, tx_power: i8) -> f32 {
+        let first = self.entries[0];
+        let last = self.entries[self.entries.len() - 1];
+        if tx_power <= first.0 {
+            return first.1;
+        }
+        if tx_power >= last.0 {
+            return last.1;
+        }
+        let i = self.entries.partition_point(|(tp, _)| *tp <= tx_power);
+        let (tp0, c0) = self.entries[i - 1];
+        let (tp1, c1) = self.entries[i];
+        c0 + (c1 - c0) * ((tx_power - tp0) as f32) / ((tp1 - tp0) as f32)
+    }
+
+    /// Gets the energy (mJ) consumed by a transmission.
+    pub fn energy_mj(&self, tx_power: i8, airtime: Duration) -> f64 {
+        (self.voltage as f64) * (self.current(tx_power) as f64) * airtime.as_secs_f64()
+    }
+}
+
+impl Default for PaCurrentTable {
+    fn default() -> Self {
+        Self::rfm95w()
+    }
+}
+
+/// Radiated energy (mJ) of a transmission, the airtime times the transmission power.
+fn radiated_mj(tx_power: i8, airtime: Duration) -> f64 {
+    sys::pow(10.0, tx_power as f64 / 10.0) * airtime.as_secs_f64()
+}
+
+/// Statistics of the transmissions at a transmission power.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PowerStatistics {
+    /// Number of physical frames transmitted.
+    pub frames: u64,
+    /// Cumulative airtime.
+    pub airtime: Duration,
+    /// Radiated energy (mJ), the airtime times the transmission power.
+    pub radiated_mj: f64,
+    /// Estimated energy consumed by the radio (mJ).
+    pub energy_mj: f64,
+}
+
+/// Statistics of the transmissions to a neighbor.
+///
+/// A transmission addressed to several neighbors is shared equally between them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NeighborStatistics {
+    /// Number of physical frames transmitted (shared frames included).
+    pub frames: u64,
+    /// Cumulative airtime.
+    pub airtime: Duration,
+    /// Radiated energy (mJ), the airtime times the transmission power.
+    pub radiated_mj: f64,
+    /// Estimated energy consumed by the radio (mJ).
+    pub energy_mj: f64,
+    /// Cumulative airtime by transmission power.
+    pub airtime_by_power: BTreeMap<i8, Duration>,
+    /// Number of frames acknowledged by this neighbor.
+    pub delivered: u64,
+    /// Number of frames not acknowledged by this neighbor.
+    pub failed: u64,
+}
+
+/// Transmission statistics of a radio, see the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// Statistics by transmission power, of every transmission (beacons included).
+    pub by_power: BTreeMap<i8, PowerStatistics>,
+    /// Statistics by neighbor.
+    pub by_neighbor: BTreeMap<LoRaAddress, NeighborStatistics>,
+    /// Number of beacons transmitted.
+    pub beacons: u64,
+    /// Estimated energy consumed by the beacons (mJ).
+    pub beacons_energy_mj: f64,
+}
+
+impl Statistics {
+    /// Records a physical transmission to some neighbors.
+    pub fn record_transmission(
+        &mut self,
+        tx_power: i8,
+        airtime: Duration,
+        recipients: &[LoRaAddress],
+        table: &PaCurrentTable,
+    ) {
+        let energy = table.energy_mj(tx_power, airtime);
+        let radiated = radiated_mj(tx_power, airtime);
+        let power = self.by_power.entry(tx_power).or_default();
+        power.frames += 1;
+        power.airtime += airtime;
+        power.radiated_mj += radiated;
+        power.energy_mj += energy;
+        if recipients.is_empty() {
+            return;
+        }
+        let share = recipients.len() as u32;
+        for recipient in recipients {
+            let neigh = self.by_neighbor.entry(*recipient).or_default();
+            neigh.frames += 1;
+            neigh.airtime += airtime / share;
+            neigh.radiated_mj += radiated / (share as f64);
+            neigh.energy_mj += energy / (share as f64);
+            *neigh.airtime_by_power.entry(tx_power).or_default() += airtime / share;
+        }
+    }
+
+    /// Records the transmission of a beacon.
+    pub fn record_beacon(&mut self, tx_power: i8, airtime: Duration, table: &PaCurrentTable) {
+        self.record_transmission(tx_power, airtime, &[], table);
+        self.beacons += 1;
+        self.beacons_energy_mj += table.energy_mj(tx_power, airtime);
+    }
+
+    /// Records the acknowledgment of a frame by a neighbor.
+    pub fn record_delivery(&mut self, neighbor_addr: LoRaAddress) {
+        self.by_neighbor.entry(neighbor_addr).or_default().delivered += 1;
+    }
+
+    /// Records a missing acknowledgment of a neighbor.
+    pub fn record_failure(&mut self, neighbor_addr: LoRaAddress) {
+        self.by_neighbor.entry(neighbor_addr).or_default().failed += 1;
+    }
+
+    /// Gets the cumulative airtime of every transmission.
+    pub fn total_airtime(&self) -> Duration {
+        self.by_power.values().map(|p| p.airtime).sum()
+    }
+
+    /// Gets the estimated energy consumed by every transmission (mJ).
+    pub fn total_energy_mj(&self) -> f64 {
+        self.by_power.values().map(|p| p.energy_mj).sum()
+    }
+
+    /// Exports these statistics as CSV.
+    ///
+    /// Each line is either a transmission power (`power`), a neighbor (`neighbor`) or the beacons
+    /// (`beacons`) with the columns `kind,key,frames,airtime_ms,radiated_mj,energy_mj,delivered,failed`.
+    #[cfg(feature = "std")]
+    pub fn write_csv<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "kind,key,frames,airtime_ms,radiated_mj,energy_mj,delivered,failed"
+        )?;
+        for (tx_power, p) in &self.by_power {
+            writeln!(
+                writer,
+                "power,{},{},{},{:.3},{:.3},,",
+                tx_power,
+                p.frames,
+                p.airtime.as_millis(),
+                p.radiated_mj,
+                p.energy_mj
+            )?;
+        }
+        for (addr, n) in &self.by_neighbor {
+            writeln!(
+                writer,
+                "neighbor,{:#06x},{},{},{:.3},{:.3},{},{}",
+                addr,
+                n.frames,
+                n.airtime.as_millis(),
+                n.radiated_mj,
+                n.energy_mj,
+                n.delivered,
+                n.failed
+            )?;
+        }
+        writeln!(
+            writer,
+            "beacons,,{},,,{:.3},,",
+            self.beacons, self.beacons_energy_mj
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_pa_current_interpolation() {
+        let table = PaCurrentTable::new(vec![(14, 50.0), (2, 20.0)], 3.0);
+        assert_eq!(table.current(0), 20.0);
+        assert_eq!(table.current(8), 35.0);
+        assert_eq!(table.current(20), 50.0);
+        assert!((table.energy_mj(8, Duration::from_millis(400)) - 42.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stats_accounting_and_csv() {
+        let table = PaCurrentTable::new(vec![(10, 40.0)], 3.0);
+        let mut stats = Statistics::default();
+        stats.record_transmission(10, Duration::from_millis(400), &[0x0042, 0x0043], &table);
+        stats.record_beacon(10, Duration::from_millis(400), &table);
+        stats.record_delivery(0x0042);
+        stats.record_failure(0x0043);
+
+        assert_eq!(stats.by_power[&10].frames, 2);
+        assert_eq!(stats.total_airtime(), Duration::from_millis(800));
+        assert!((stats.total_energy_mj() - 96.0).abs() < 1e-9);
+        let neigh = &stats.by_neighbor[&0x0042];
+        assert_eq!(neigh.airtime, Duration::from_millis(200));
+        assert!((neigh.energy_mj - 24.0).abs() < 1e-9);
+        assert!((neigh.radiated_mj - 2.0).abs() < 1e-9);
+        assert_eq!(neigh.delivered, 1);
+        assert_eq!(stats.by_neighbor[&0x0043].failed, 1);
+
+        let mut csv = Vec::new();
+        stats.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[1], "power,10,2,800,8.000,96.000,,");
+        assert_eq!(lines[2], "neighbor,0x0042,1,200,2.000,24.000,1,0");
+        assert_eq!(lines[4], "beacons,,1,,,48.000,,");
+    }
+}
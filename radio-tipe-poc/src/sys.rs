@@ -0,0 +1,207 @@
+//! Platform services used by the crate: clocks, randomness, collections and float math.
+//!
+//! With the `std` feature (enabled by default), they come from the standard library and the
+//! operating system. Without it, the crate only needs `alloc`, and the application provides the
+//! clocks and the random numbers with `set_platform` before using an ATPC or the radio.
+//!
+//! ```ignore
+//! use radio_tipe_poc::sys::{set_platform, Platform};
+//!
+//! fn now_us() -> u64 { /* Hardware timer */ }
+//! fn unix_time_ms() -> u64 { /* Real time clock */ }
+//! fn fill_random(buf: &mut [u8]) { /* Hardware random number generator */ }
+//!
+//! static PLATFORM: Platform = Platform { now_us, unix_time_ms, fill_random };
+//! set_platform(&PLATFORM);
+//! ```
+pub use core::time::Duration;
+
+#[cfg(feature = "std")]
+pub use std::time::Instant;
+
+#[cfg(not(feature = "std"))]
+pub use self::no_std::{set_platform, Instant, Platform};
+
+#[cfg(feature = "std")]
+pub(crate) use std::collections::HashMap;
+
+#[cfg(not(feature = "std"))]
+pub(crate) use hashbrown::HashMap;
+
+/// Gets the current time since the UNIX epoch.
+#[cfg(feature = "std")]
+pub fn unix_time() -> Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Gets the current time since the UNIX epoch.
+#[cfg(not(feature = "std"))]
+pub fn unix_time() -> Duration {
+    Duration::from_millis((no_std::platform().unix_time_ms)())
+}
+
+/// Fills the buffer with random bytes.
+///
+/// Returns `false` if no randomness is available, the buffer is then left untouched.
+#[cfg(feature = "std")]
+pub fn fill_random(buf: &mut [u8]) -> bool {
+    getrandom::getrandom(buf).is_ok()
+}
+
+/// Fills the buffer with random bytes.
+///
+/// Returns `false` if no randomness is available, the buffer is then left untouched.
+#[cfg(not(feature = "std"))]
+pub fn fill_random(buf: &mut [u8]) -> bool {
+    (no_std::platform().fill_random)(buf);
+    true
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) use libm::{ceilf, pow, sqrtf};
+
+/// Square root of a `f32` (`libm::sqrtf` without the standard library).
+#[cfg(feature = "std")]
+pub(crate) fn sqrtf(x: f32) -> f32 {
+    x.sqrt()
+}
+
+/// Smallest integer greater than or equal to a `f32` (`libm::ceilf` without the standard
+/// library).
+#[cfg(feature = "std")]
+pub(crate) fn ceilf(x: f32) -> f32 {
+    x.ceil()
+}
+
+/// `x` raised to the power `y` (`libm::pow` without the standard library).
+#[cfg(feature = "std")]
+pub(crate) fn pow(x: f64, y: f64) -> f64 {
+    x.powf(y)
+}
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use core::ops::{Add, AddAssign, Sub, SubAssign};
+    use core::ptr;
+    use core::sync::atomic::{AtomicPtr, Ordering};
+    use core::time::Duration;
+
+    /// Clocks and randomness of the platform, see [set_platform].
+    pub struct Platform {
+        /// Monotonic clock, in microseconds since an arbitrary origin (usually the boot).
+        pub now_us: fn() -> u64,
+        /// Wall clock, in milliseconds since the UNIX epoch.
+        ///
+        /// Without a real time clock, the time since the boot is acceptable, but the nonces of the
+        /// frames and the ATPC snapshots then only make sense until the next reboot.
+        pub unix_time_ms: fn() -> u64,
+        /// Fills the buffer with random bytes.
+        pub fill_random: fn(&mut [u8]),
+    }
+
+    static PLATFORM: AtomicPtr<Platform> = AtomicPtr::new(ptr::null_mut());
+
+    /// Sets the clocks and the randomness of the platform.
+    ///
+    /// It must be called before using an ATPC or the radio, which panic otherwise.
+    pub fn set_platform(platform: &'static Platform) {
+        PLATFORM.store(
+            platform as *const Platform as *mut Platform,
+            Ordering::Release,
+        );
+    }
+
+    pub(super) fn platform() -> &'static Platform {
+        let platform = PLATFORM.load(Ordering::Acquire);
+        assert!(!platform.is_null(), "No platform set, see `set_platform`.");
+        // SAFETY: the pointer was built from a `&'static Platform` by `set_platform`.
+        unsafe { &*platform }
+    }
+
+    /// A measurement of the monotonic clock of the platform, see `std::time::Instant`.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant(u64);
+
+    impl Instant {
+        /// Gets the current instant.
+        pub fn now() -> Self {
+            Instant((platform().now_us)())
+        }
+
+        /// Gets the duration elapsed since `earlier`, zero if `earlier` is later.
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            self.saturating_duration_since(earlier)
+        }
+
+        /// Gets the duration elapsed since `earlier`, `None` if `earlier` is later.
+        pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+            self.0.checked_sub(earlier.0).map(Duration::from_micros)
+        }
+
+        /// Gets the duration elapsed since `earlier`, zero if `earlier` is later.
+        pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+            self.checked_duration_since(earlier).unwrap_or_default()
+        }
+
+        /// Gets the duration elapsed since this instant.
+        pub fn elapsed(&self) -> Duration {
+            Instant::now().saturating_duration_since(*self)
+        }
+
+        /// Gets the instant after `duration`, `None` if it can not be represented.
+        pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+            u64::try_from(duration.as_micros())
+                .ok()
+                .and_then(|us| self.0.checked_add(us))
+                .map(Instant)
+        }
+
+        /// Gets the instant before `duration`, `None` if it can not be represented.
+        pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+            u64::try_from(duration.as_micros())
+                .ok()
+                .and_then(|us| self.0.checked_sub(us))
+                .map(Instant)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, other: Duration) -> Instant {
+            self.checked_add(other)
+                .expect("overflow when adding duration to instant")
+        }
+    }
+
+    impl AddAssign<Duration> for Instant {
+        fn add_assign(&mut self, other: Duration) {
+            *self = *self + other;
+        }
+    }
+
+    impl Sub<Duration> for Instant {
+        type Output = Instant;
+
+        fn sub(self, other: Duration) -> Instant {
+            self.checked_sub(other)
+                .expect("overflow when subtracting duration from instant")
+        }
+    }
+
+    impl SubAssign<Duration> for Instant {
+        fn sub_assign(&mut self, other: Duration) {
+            *self = *self - other;
+        }
+    }
+
+    impl Sub<Instant> for Instant {
+        type Output = Duration;
+
+        fn sub(self, other: Instant) -> Duration {
+            self.duration_since(other)
+        }
+    }
+}
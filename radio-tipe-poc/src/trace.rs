@@ -0,0 +1,359 @@
+//! Transmission traces, and their replay through an ATPC.
+//!
+//! The [LoRaRadio](crate::radio::LoRaRadio) logs the outcome of each transmission to a neighbor
+//! (the transmission power, the RSSI reported by the acknowledgment, or its absence) as a
+//! [TraceRecord], with the `log` target [TRACE_TARGET]. The serial output of a node is therefore
+//! a trace that can be read with [read_csv], and replayed offline through any [ATPC] to compare
+//! the transmission powers it would have chosen, its delivery ratio and its energy.
+//!
+//! ## Trace format
+//! One record per line, `time_ms,neighbor,tx_power,rssi,ack`, with an empty RSSI when the frame
+//! was not acknowledged. In a serial capture the records are prefixed by [TRACE_MARKER], every
+//! other line (logs, header...) is ignored.
+//! ```text
+//! I (52140) radio_trace: TRACE,1684921337412,0x0042,11,-104,1
+//! I (53310) radio_trace: TRACE,1684921338582,0x0042,8,,0
+//! ```
+//!
+//! ## Replay model
+//! The path loss towards a neighbor is the one observed in the trace: a frame acknowledged at
+//! `rssi` with `tx_power` would have been received at `rssi + (tp - tx_power)` with another
+//! transmission power `tp`, and is delivered if it is above the sensitivity. A frame that was
+//! not acknowledged is only delivered with a higher transmission power that the latest path loss
+//! of the neighbor allows. Beacons are answered with the latest path loss of each neighbor.
+//!
+//! Note that the ATPC runs on the clock of the host: the timestamps of the trace only order the
+//! records, time based behaviors (beacon delay, aging) are not replayed.
+use crate::atpc::ATPC;
+use crate::frame::FrameNonce;
+use crate::stats::PaCurrentTable;
+use crate::LoRaAddress;
+
+use crate::prelude::*;
+use crate::sys::HashMap;
+
+use alloc::collections::BTreeMap;
+use core::fmt;
+use core::time::Duration;
+
+/// `log` target of the trace records logged by the radio.
+pub const TRACE_TARGET: &str = "radio_trace";
+/// Prefix of the trace records in a log.
+pub const TRACE_MARKER: &str = "TRACE,";
+
+/// Outcome of a transmission to a neighbor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// Time of the outcome, in milliseconds (since the UNIX epoch on the nodes).
+    pub time_ms: u64,
+    /// The neighbor the frame was transmitted to.
+    pub neighbor: LoRaAddress,
+    /// Transmission power of the frame (dBm).
+    pub tx_power: i8,
+    /// RSSI of the frame at the neighbor (dBm), as reported by its acknowledgment.
+    pub rssi: Option<i16>,
+    /// Was the frame acknowledged?
+    pub ack: bool,
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{},{:#06x},{},",
+            TRACE_MARKER, self.time_ms, self.neighbor, self.tx_power
+        )?;
+        if let Some(rssi) = self.rssi {
+            write!(f, "{}", rssi)?;
+        }
+        write!(f, ",{}", self.ack as u8)
+    }
+}
+
+/// Error while reading a trace.
+#[derive(thiserror::Error, Debug)]
+pub enum TraceError {
+    /// The trace could not be read.
+    #[cfg(feature = "std")]
+    #[error("Failed to read the trace")]
+    IoError(#[from] std::io::Error),
+    /// A record of the trace is malformed.
+    #[error("Malformed record at line {line}: {context}")]
+    ParseError { line: usize, context: String },
+}
+
+/// Parses a neighbor address, either hexadecimal (`0x0042`) or decimal.
+#[cfg(feature = "std")]
+fn parse_address(field: &str) -> Option<LoRaAddress> {
+    match field.strip_prefix("0x") {
+        Some(hex) => LoRaAddress::from_str_radix(hex, 16).ok(),
+        None => field.parse().ok(),
+    }
+}
+
+/// Parses a record (without its marker).
+#[cfg(feature = "std")]
+fn parse_record(record: &str) -> Result<TraceRecord, String> {
+    let fields: Vec<&str> = record.trim().split(',').map(str::trim).collect();
+    if fields.len() != 5 {
+        return Err(format!("expected 5 fields, got {}", fields.len()));
+    }
+    let time_ms = fields[0]
+        .parse()
+        .map_err(|_| format!("invalid time `{}`", fields[0]))?;
+    let neighbor =
+        parse_address(fields[1]).ok_or_else(|| format!("invalid neighbor `{}`", fields[1]))?;
+    let tx_power = fields[2]
+        .parse()
+        .map_err(|_| format!("invalid transmission power `{}`", fields[2]))?;
+    let rssi = match fields[3] {
+        "" => None,
+        rssi => Some(
+            rssi.parse()
+                .map_err(|_| format!("invalid RSSI `{}`", rssi))?,
+        ),
+    };
+    let ack = match fields[4] {
+        "1" | "true" => true,
+        "0" | "false" => false,
+        ack => return Err(format!("invalid acknowledgment `{}`", ack)),
+    };
+    Ok(TraceRecord {
+        time_ms,
+        neighbor,
+        tx_power,
+        rssi,
+        ack,
+    })
+}
+
+/// Reads a trace, either a CSV file or a serial capture of a node (see the
+/// [module documentation](self)).
+///
+/// Lines holding the [TRACE_MARKER] and lines starting with a digit are records, the other
+/// ones are ignored. The records are sorted by time.
+#[cfg(feature = "std")]
+pub fn read_csv<R: std::io::BufRead>(reader: R) -> Result<Vec<TraceRecord>, TraceError> {
+    let mut records = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let record = match line.find(TRACE_MARKER) {
+            Some(start) => &line[start + TRACE_MARKER.len()..],
+            None if line.starts_with(|c: char| c.is_ascii_digit()) => line.as_str(),
+            None => continue,
+        };
+        records.push(
+            parse_record(record).map_err(|context| TraceError::ParseError {
+                line: i + 1,
+                context,
+            })?,
+        );
+    }
+    records.sort_by_key(|record| record.time_ms);
+    Ok(records)
+}
+
+/// Parameters of a replay.
+#[derive(Clone, Debug)]
+pub struct ReplayConfig {
+    /// Sensitivity of the receivers (dBm), frames received below it are lost.
+    pub sensitivity: i16,
+    /// RSSI target of the neighbors (dBm), used to report the acknowledgments to the ATPC.
+    pub rssi_target: i16,
+    /// Time on air of a frame or a beacon.
+    pub time_on_air: Duration,
+    /// Current drawn by the radio module, to estimate the energy of the transmissions.
+    pub pa_table: PaCurrentTable,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            sensitivity: -120,
+            rssi_target: -100,
+            time_on_air: Duration::from_millis(400),
+            pa_table: PaCurrentTable::default(),
+        }
+    }
+}
+
+/// Results of a replay.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Number of frames transmitted.
+    pub transmissions: u64,
+    /// Number of frames delivered.
+    pub delivered: u64,
+    /// Number of beacons transmitted.
+    pub beacons: u64,
+    /// Estimated energy consumed by the radio (mJ), beacons included.
+    pub energy_mj: f64,
+    /// Number of frames transmitted by transmission power.
+    pub tx_powers: BTreeMap<i8, u64>,
+}
+
+impl ReplayReport {
+    /// Gets the ratio of the frames delivered.
+    pub fn delivery_ratio(&self) -> f64 {
+        if self.transmissions == 0 {
+            return 0.0;
+        }
+        (self.delivered as f64) / (self.transmissions as f64)
+    }
+
+    /// Records the transmission of a frame.
+    fn record_transmission(&mut self, tx_power: i8, delivered: bool, config: &ReplayConfig) {
+        self.transmissions += 1;
+        if delivered {
+            self.delivered += 1;
+        }
+        self.energy_mj += config.pa_table.energy_mj(tx_power, config.time_on_air);
+        *self.tx_powers.entry(tx_power).or_default() += 1;
+    }
+}
+
+/// Gets the results of the transmissions as recorded in the trace, the baseline of the replays.
+pub fn baseline(trace: &[TraceRecord], config: &ReplayConfig) -> ReplayReport {
+    let mut report = ReplayReport::default();
+    for record in trace {
+        report.record_transmission(record.tx_power, record.ack, config);
+    }
+    report
+}
+
+/// Replays a trace through an ATPC, see the [module documentation](self).
+pub fn replay<A: ATPC>(atpc: &mut A, trace: &[TraceRecord], config: &ReplayConfig) -> ReplayReport {
+    let mut report = ReplayReport::default();
+    let mut path_losses: HashMap<LoRaAddress, i16> = HashMap::new();
+    let mut nonce: FrameNonce = 0;
+    for record in trace {
+        atpc.register_neighbor(record.neighbor);
+        // Beacons: a sweep is answered by every neighbor, a probe by a single one.
+        let targets: Vec<LoRaAddress> = if atpc.is_beacon_needed() {
+            path_losses.keys().copied().collect()
+        } else if let Some(neighbor) = atpc.next_probe() {
+            vec![neighbor]
+        } else {
+            Vec::new()
+        };
+        if !targets.is_empty() {
+            let sweep = nonce;
+            let powers = atpc.get_beacon_powers();
+            for (tpi, tp) in powers.iter().enumerate() {
+                atpc.register_beacon(tpi, nonce);
+                nonce = nonce.wrapping_add(1);
+                report.beacons += 1;
+                report.energy_mj += config.pa_table.energy_mj(*tp, config.time_on_air);
+            }
+            for target in targets {
+                let Some(path_loss) = path_losses.get(&target) else {
+                    continue;
+                };
+                let rssi: Vec<Option<i16>> = powers
+                    .iter()
+                    .map(|tp| Some(*tp as i16 - path_loss).filter(|r| *r >= config.sensitivity))
+                    .collect();
+                if rssi.iter().any(|r| r.is_some()) {
+                    atpc.report_beacon_acknowledgment(target, sweep, &rssi);
+                }
+            }
+        }
+
+        // The transmission itself, at the power chosen by the ATPC.
+        let tx_power = atpc.get_tx_power(record.neighbor);
+        atpc.register_transmission(nonce, tx_power, &[record.neighbor]);
+        if let (true, Some(rssi)) = (record.ack, record.rssi) {
+            path_losses.insert(record.neighbor, record.tx_power as i16 - rssi);
+        }
+        let rssi = match path_losses.get(&record.neighbor) {
+            Some(path_loss) if record.ack || tx_power > record.tx_power => {
+                Some(tx_power as i16 - path_loss)
+            }
+            // Acknowledged without a known path loss: the recorded power was enough.
+            None if record.ack && tx_power >= record.tx_power => Some(config.rssi_target),
+            _ => None,
+        }
+        .filter(|rssi| *rssi >= config.sensitivity);
+        match rssi {
+            Some(rssi) => {
+                atpc.report_successful_reception(record.neighbor, nonce, config.rssi_target - rssi)
+            }
+            None => atpc.report_failed_reception(record.neighbor),
+        }
+        let _ = atpc.take_lost_links();
+        report.record_transmission(tx_power, rssi.is_some(), config);
+        nonce = nonce.wrapping_add(1);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atpc::TestingATPC;
+
+    #[test]
+    fn trace_read_serial_capture() {
+        let record = TraceRecord {
+            time_ms: 1200,
+            neighbor: 0x0042,
+            tx_power: 11,
+            rssi: Some(-104),
+            ack: true,
+        };
+        let capture = format!(
+            "I (52140) radio: Transmission starting...\n\
+             I (52140) radio_trace: {}\n\
+             time_ms,neighbor,tx_power,rssi,ack\n\
+             1100,66,8,,0\n",
+            record
+        );
+        let trace = read_csv(capture.as_bytes()).unwrap();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(
+            trace[0],
+            TraceRecord {
+                time_ms: 1100,
+                neighbor: 0x0042,
+                tx_power: 8,
+                rssi: None,
+                ack: false,
+            }
+        );
+        assert_eq!(trace[1], record);
+
+        let err = read_csv("1100,0x0042,eight,,0\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, TraceError::ParseError { line: 1, .. }));
+    }
+
+    #[test]
+    fn trace_replay() {
+        // Path loss of 120dB, with a sensitivity of -110dBm only 11dBm and above are delivered.
+        let trace: Vec<TraceRecord> = (0..48)
+            .map(|i| TraceRecord {
+                time_ms: i * 1000,
+                neighbor: 0x0042,
+                tx_power: 14,
+                rssi: Some(-106),
+                ack: true,
+            })
+            .collect();
+        let config = ReplayConfig {
+            sensitivity: -110,
+            pa_table: PaCurrentTable::new(vec![(2, 20.0)], 3.0),
+            time_on_air: Duration::from_millis(500),
+            ..ReplayConfig::default()
+        };
+        let mut atpc = TestingATPC::new(vec![2, 5, 8, 11, 14, 17]);
+        let report = replay(&mut atpc, &trace, &config);
+        assert_eq!(report.transmissions, 48);
+        assert_eq!(report.delivered, 24);
+        assert_eq!(report.beacons, 0);
+        assert_eq!(report.tx_powers.len(), 6);
+        assert!((report.energy_mj - 48.0 * 30.0).abs() < 1e-9);
+
+        let baseline = baseline(&trace, &config);
+        assert_eq!(baseline.delivery_ratio(), 1.0);
+        assert_eq!(baseline.tx_powers[&14], 48);
+    }
+}
@@ -0,0 +1,376 @@
+-- Wireshark dissector of the TIPE frames, for the captures of `radio_tipe_poc::capture`.
+--
+-- Install it in the personal Lua plugins folder (Help > About Wireshark > Folders), or run
+-- `wireshark -X lua_script:tipe.lua capture.pcapng`.
+--
+-- The captures use the LoRaTap link header (link type 270), this dissector replaces the one of
+-- Wireshark for this link type: it decodes the LoRaTap header then the TIPE physical frame.
+--
+-- Format of a physical frame (big endian):
+--
+--   physical header   u8   frame type (0 message, 6 beacon, 7 beacon ack.)
+--
+-- Message and beacon frames, split in up to 5 physical frames (fragments) sent on consecutive
+-- channels, each one starting with the physical header:
+--
+--   version, flags    u8   version (high nibble: 1 standard, 2 compact), flags (0x1 extensions,
+--                          0x2 compression)
+--   info header       u8   number of recipients (high nibble) and of physical frames (low nibble)
+--   recipients        u8   count, then a single address (u16) or, for each recipient, an address
+--                          (u16) and the bitmap of its payloads (u16)
+--   sender            u16  address
+--   payloads          u8   count
+--   nonce             u64  (varint in the compact encoding), unix time (s) << 16 | random
+--   extensions             if flagged: area length (u8) then kind (u8), length (u8), value
+--                            kind 1: data rate of the following fragments (u8, default 0)
+--   acknowledgments   u8   count, then each acknowledgment:
+--                            standard: address (u16), nonce (u64), RSSI delta (i16)
+--                            compact: address (u16), zigzag varint of the frame nonce minus the
+--                                     acknowledged nonce (0: followed by the nonce, u64), RSSI
+--                                     delta (i8)
+--   payloads               each payload: length then bytes
+--                            standard: u16, the high bit flags a compressed payload
+--                            compact: varint, (length << 1 | compressed) with compression
+--
+-- An address is 15 bits (0x7FFF: global, 0x7000 | id: group) and an acknowledgment request bit
+-- (0x8000). The first payload of a beacon is the sweep nonce (u64), the power level (u8) and the
+-- number of levels (u8).
+--
+-- Beacon acknowledgments fit in one physical frame: sender (u16), recipient (u16), sweep nonce
+-- (u64), number of levels (u8) then the RSSI of each level (u8, RSSI + 164, 0: missed).
+--
+-- The fragments of a frame are not reassembled, only the lead physical frame is decoded.
+
+local tipe = Proto("tipe", "TIPE LoRa frame")
+
+local frame_types = { [0] = "Message", [6] = "Beacon", [7] = "Beacon acknowledgment" }
+
+local f = tipe.fields
+-- LoRaTap
+f.lt_frequency = ProtoField.uint32("tipe.loratap.frequency", "Frequency (Hz)")
+f.lt_bandwidth = ProtoField.uint8("tipe.loratap.bandwidth", "Bandwidth (125kHz)")
+f.lt_sf = ProtoField.uint8("tipe.loratap.sf", "Spreading factor")
+f.lt_rssi = ProtoField.int16("tipe.loratap.rssi", "RSSI (dBm)")
+f.lt_snr = ProtoField.float("tipe.loratap.snr", "SNR (dB)")
+-- Physical header
+f.frame_type = ProtoField.uint8("tipe.type", "Frame type", base.DEC, frame_types)
+-- Headers
+f.version = ProtoField.uint8("tipe.version", "Version", base.DEC, nil, 0xF0)
+f.ext_flag = ProtoField.bool("tipe.flags.extensions", "Extensions", 8, nil, 0x01)
+f.comp_flag = ProtoField.bool("tipe.flags.compression", "Compression", 8, nil, 0x02)
+f.info_recipients = ProtoField.uint8("tipe.info.recipients", "Recipients", base.DEC, nil, 0xF0)
+f.info_frames = ProtoField.uint8("tipe.info.frames", "Physical frames", base.DEC, nil, 0x0F)
+f.address = ProtoField.uint16("tipe.address", "Address", base.HEX, nil, 0x7FFF)
+f.ack = ProtoField.bool("tipe.address.ack", "Acknowledgment requested", 16, nil, 0x8000)
+f.payload_flag = ProtoField.uint16("tipe.recipient.payloads", "Payloads", base.HEX)
+f.sender = ProtoField.uint16("tipe.sender", "Sender", base.HEX, nil, 0x7FFF)
+f.payload_count = ProtoField.uint8("tipe.payloads", "Payloads")
+f.nonce = ProtoField.uint64("tipe.nonce", "Nonce", base.HEX)
+f.timestamp = ProtoField.absolute_time("tipe.nonce.time", "Nonce time", base.UTC)
+f.extension_kind = ProtoField.uint8("tipe.extension.kind", "Kind")
+f.extension_value = ProtoField.bytes("tipe.extension.value", "Value")
+f.data_rate = ProtoField.uint8("tipe.data_rate", "Data rate")
+f.ack_count = ProtoField.uint8("tipe.acks", "Acknowledgments")
+f.ack_nonce = ProtoField.uint64("tipe.ack.nonce", "Acknowledged nonce", base.HEX)
+f.ack_drssi = ProtoField.int16("tipe.ack.drssi", "RSSI delta (dB)")
+f.payload_len = ProtoField.uint16("tipe.payload.length", "Length")
+f.payload_compressed = ProtoField.bool("tipe.payload.compressed", "Compressed")
+f.payload = ProtoField.bytes("tipe.payload", "Payload")
+-- Beacons
+f.sweep = ProtoField.uint64("tipe.beacon.sweep", "Sweep", base.HEX)
+f.tpi = ProtoField.uint8("tipe.beacon.level", "Power level")
+f.levels = ProtoField.uint8("tipe.beacon.levels", "Levels")
+f.beacon_rssi = ProtoField.uint8("tipe.beacon.rssi", "RSSI (dBm)")
+f.fragment = ProtoField.framenum("tipe.fragment_of", "Fragment of")
+
+local ef_malformed = ProtoExpert.new("tipe.malformed", "Malformed TIPE frame", expert.group.MALFORMED,
+    expert.severity.ERROR)
+tipe.experts = { ef_malformed }
+
+-- Lead frame of each fragment, and the fragments still expected after a lead frame (first pass).
+local fragment_of = {}
+local expected = { lead = nil, remaining = 0 }
+
+function tipe.init()
+    fragment_of = {}
+    expected = { lead = nil, remaining = 0 }
+end
+
+-- Raised (through error) when the frame is too short.
+local Truncated = {}
+
+local function range(tvb, offset, len)
+    if offset + len > tvb:len() then
+        error(Truncated)
+    end
+    return tvb:range(offset, len)
+end
+
+-- Reads a LEB128 varint, returns its range and value (UInt64).
+local function varint(tvb, offset)
+    local value = UInt64(0)
+    local shift = 0
+    local len = 0
+    repeat
+        local byte = range(tvb, offset + len, 1):uint()
+        value = value:bor(UInt64(bit.band(byte, 0x7F)):lshift(shift))
+        shift = shift + 7
+        len = len + 1
+    until bit.band(byte, 0x80) == 0 or len == 10
+    return tvb:range(offset, len), value
+end
+
+local function add_address(tree, field, tvb, offset, label)
+    local r = range(tvb, offset, 2)
+    local raw = r:uint()
+    local addr = bit.band(raw, 0x7FFF)
+    local text
+    if addr == 0x7FFF then
+        text = "global"
+    elseif bit.band(addr, 0x7000) == 0x7000 then
+        text = string.format("group %d", bit.band(addr, 0x0FFF))
+    else
+        text = string.format("0x%04x", addr)
+    end
+    local subtree = tree:add(field, r)
+    subtree:set_text(string.format("%s: %s", label, text))
+    subtree:add(f.ack, r)
+    return offset + 2
+end
+
+local function add_nonce(tree, field, r, nonce)
+    local subtree = tree:add(field, r, nonce)
+    local secs = nonce:rshift(16):tonumber()
+    subtree:add(f.timestamp, r, NSTime.new(secs, 0))
+    return subtree
+end
+
+local function dissect_beacon_ack(tvb, tree, offset)
+    offset = add_address(tree, f.sender, tvb, offset, "Sender")
+    offset = add_address(tree, f.address, tvb, offset, "Recipient")
+    tree:add(f.sweep, range(tvb, offset, 8))
+    offset = offset + 8
+    local levels = range(tvb, offset, 1):uint()
+    tree:add(f.levels, tvb:range(offset, 1))
+    offset = offset + 1
+    for tpi = 0, levels - 1 do
+        local r = range(tvb, offset, 1)
+        local item = tree:add(f.beacon_rssi, r)
+        if r:uint() == 0 then
+            item:set_text(string.format("Level %d: missed", tpi))
+        else
+            item:set_text(string.format("Level %d: %d dBm", tpi, r:uint() - 164))
+        end
+        offset = offset + 1
+    end
+    return offset
+end
+
+local function dissect_frame(tvb, pinfo, tree, offset, beacon)
+    local vf = range(tvb, offset, 1):uint()
+    local version = bit.rshift(vf, 4)
+    local compact = version == 2
+    local extensions = bit.band(vf, 0x01) ~= 0
+    local compression = bit.band(vf, 0x02) ~= 0
+    tree:add(f.version, tvb:range(offset, 1))
+    tree:add(f.ext_flag, tvb:range(offset, 1))
+    tree:add(f.comp_flag, tvb:range(offset, 1))
+    if version ~= 1 and version ~= 2 then
+        tree:add_proto_expert_info(ef_malformed, "Unsupported version " .. version)
+        return offset + 1
+    end
+    offset = offset + 1
+
+    local info = range(tvb, offset, 1)
+    tree:add(f.info_recipients, info)
+    tree:add(f.info_frames, info)
+    local nframes = bit.band(info:uint(), 0x0F)
+    offset = offset + 1
+    if not pinfo.visited and nframes > 1 then
+        expected = { lead = pinfo.number, remaining = nframes - 1 }
+    end
+
+    local nrec = range(tvb, offset, 1):uint()
+    offset = offset + 1
+    if nrec == 1 then
+        offset = add_address(tree, f.address, tvb, offset, "Recipient")
+    elseif nrec >= 2 and nrec <= 16 then
+        local recipients = tree:add(tipe, tvb:range(offset - 1, 1 + 4 * nrec), "Recipients")
+        for _ = 1, nrec do
+            offset = add_address(recipients, f.address, tvb, offset, "Recipient")
+            local pl = range(tvb, offset, 2)
+            local ids = {}
+            for id = 0, 15 do
+                if bit.band(pl:uint(), bit.lshift(1, id)) ~= 0 then
+                    ids[#ids + 1] = tostring(id)
+                end
+            end
+            recipients:add(f.payload_flag, pl):append_text(" [" .. table.concat(ids, ", ") .. "]")
+            offset = offset + 2
+        end
+    else
+        tree:add_proto_expert_info(ef_malformed, "Invalid recipient count " .. nrec)
+        return offset
+    end
+
+    offset = add_address(tree, f.sender, tvb, offset, "Sender")
+    local npayloads = range(tvb, offset, 1):uint()
+    tree:add(f.payload_count, tvb:range(offset, 1))
+    offset = offset + 1
+
+    local r, nonce
+    if compact then
+        r, nonce = varint(tvb, offset)
+    else
+        r = range(tvb, offset, 8)
+        nonce = r:uint64()
+    end
+    add_nonce(tree, f.nonce, r, nonce)
+    offset = offset + r:len()
+
+    if extensions then
+        local area = range(tvb, offset, 1):uint()
+        local ext_tree = tree:add(tipe, range(tvb, offset, 1 + area), "Extensions")
+        local stop = offset + 1 + area
+        offset = offset + 1
+        while offset < stop do
+            local kind = range(tvb, offset, 1)
+            local len = range(tvb, offset + 1, 1):uint()
+            ext_tree:add(f.extension_kind, kind)
+            if kind:uint() == 1 and len == 1 then
+                ext_tree:add(f.data_rate, range(tvb, offset + 2, 1))
+            else
+                ext_tree:add(f.extension_value, range(tvb, offset + 2, len))
+            end
+            offset = offset + 2 + len
+        end
+    end
+
+    local nacks = range(tvb, offset, 1):uint()
+    local acks = tree:add(f.ack_count, tvb:range(offset, 1))
+    offset = offset + 1
+    for _ = 1, nacks do
+        offset = add_address(acks, f.address, tvb, offset, "Acknowledged")
+        if compact then
+            local dr, delta = varint(tvb, offset)
+            -- Unzigzag the delta then subtract it from the frame nonce, a zero delta is
+            -- followed by the nonce itself.
+            local magnitude = delta:rshift(1)
+            if delta == UInt64(0) then
+                offset = offset + dr:len()
+                dr = range(tvb, offset, 8)
+                add_nonce(acks, f.ack_nonce, dr, dr:uint64())
+            elseif delta:band(1) ~= UInt64(0) then
+                add_nonce(acks, f.ack_nonce, dr, nonce + magnitude + 1)
+            else
+                add_nonce(acks, f.ack_nonce, dr, nonce - magnitude)
+            end
+            offset = offset + dr:len()
+            acks:add(f.ack_drssi, range(tvb, offset, 1), range(tvb, offset, 1):int())
+            offset = offset + 1
+        else
+            local nr = range(tvb, offset, 8)
+            add_nonce(acks, f.ack_nonce, nr, nr:uint64())
+            offset = offset + 8
+            acks:add(f.ack_drssi, range(tvb, offset, 2))
+            offset = offset + 2
+        end
+    end
+
+    for id = 0, npayloads - 1 do
+        local lr, len, compressed
+        if compact then
+            local value
+            lr, value = varint(tvb, offset)
+            len = value:tonumber()
+            compressed = false
+            if compression then
+                compressed = len % 2 == 1
+                len = math.floor(len / 2)
+            end
+        else
+            lr = range(tvb, offset, 2)
+            len = lr:uint()
+            compressed = compression and len >= 0x8000
+            if compression then
+                len = bit.band(len, 0x7FFF)
+            end
+        end
+        local payload = tree:add(tipe, range(tvb, offset, lr:len() + len), "Payload " .. id)
+        payload:add(f.payload_len, lr, len)
+        payload:add(f.payload_compressed, lr, compressed)
+        offset = offset + lr:len()
+        if len > 0 then
+            local pr = range(tvb, offset, len)
+            if beacon and id == 0 and not compressed and len == 10 then
+                payload:add(f.sweep, pr:range(0, 8))
+                payload:add(f.tpi, pr:range(8, 1))
+                payload:add(f.levels, pr:range(9, 1))
+            else
+                payload:add(f.payload, pr)
+            end
+        end
+        offset = offset + len
+    end
+    return offset
+end
+
+local function dissect_loratap(tvb, tree)
+    local len = tvb:range(2, 2):uint()
+    local lt = tree:add(tipe, tvb:range(0, len), "LoRaTap")
+    lt:add(f.lt_frequency, tvb:range(4, 4))
+    lt:add(f.lt_bandwidth, tvb:range(8, 1))
+    lt:add(f.lt_sf, tvb:range(9, 1))
+    if tvb:range(10, 1):uint() ~= 0 then
+        lt:add(f.lt_rssi, tvb:range(10, 1), tvb:range(10, 1):uint() - 139)
+    end
+    lt:add(f.lt_snr, tvb:range(13, 1), tvb:range(13, 1):int() / 4)
+    return len
+end
+
+function tipe.dissector(tvb, pinfo, tree)
+    if tvb:len() < 15 then
+        return 0
+    end
+    pinfo.cols.protocol = "TIPE"
+    local root = tree:add(tipe, tvb:range())
+    local offset = dissect_loratap(tvb, root)
+    if offset >= tvb:len() then
+        return tvb:len()
+    end
+
+    -- The fragments following a lead frame are only recognized by their position.
+    if not pinfo.visited and expected.remaining > 0 and expected.lead ~= pinfo.number then
+        fragment_of[pinfo.number] = expected.lead
+        expected.remaining = expected.remaining - 1
+    end
+    local ph = tvb:range(offset, 1)
+    root:add(f.frame_type, ph)
+    offset = offset + 1
+    local lead = fragment_of[pinfo.number]
+    if lead then
+        root:add(f.fragment, tvb:range(offset), lead)
+        root:add(f.payload, tvb:range(offset))
+        pinfo.cols.info = "Fragment of frame " .. lead
+        return tvb:len()
+    end
+
+    local frame_type = ph:uint()
+    pinfo.cols.info = frame_types[frame_type] or ("Unknown frame type " .. frame_type)
+    local ok, err = pcall(function()
+        if frame_type == 7 then
+            offset = dissect_beacon_ack(tvb, root, offset)
+        elseif frame_type == 0 or frame_type == 6 then
+            offset = dissect_frame(tvb, pinfo, root, offset, frame_type == 6)
+        end
+    end)
+    if not ok then
+        if err ~= Truncated then
+            error(err)
+        end
+        root:add_proto_expert_info(ef_malformed, "Truncated frame (or its following fragments)")
+    end
+    return tvb:len()
+end
+
+DissectorTable.get("wtap_encap"):add(wtap_encaps.LORATAP, tipe)